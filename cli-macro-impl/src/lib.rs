@@ -28,15 +28,18 @@ pub fn do_gen(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
 
     let ops = get_operations_with_tag(&api, &params.tag)?;
 
+    // The delete command uses the list operation to select resources in bulk.
+    let list_op = ops.iter().find(|op| op.is_root_list_operation(&params.tag));
+
     let og_enum: ItemEnum = syn::parse2(item).unwrap();
     let mut variants = og_enum.variants.clone();
     let mut commands = quote!();
 
     // Let's iterate over the paths and generate the code.
-    for op in ops {
+    for op in ops.iter() {
         // Let's generate the delete command if it exists.
         if op.is_root_level_operation(&params.tag) && op.method == "DELETE" {
-            let (delete_cmd, delete_enum_item) = op.generate_delete_command(&params.tag)?;
+            let (delete_cmd, delete_enum_item) = op.generate_delete_command(&params.tag, list_op)?;
 
             commands = quote! {
                 #commands
@@ -827,7 +830,10 @@ impl Operation {
 
                 let rendered = get_text(&v.schema.render_type(v.required)?)?;
 
-                if rendered.contains("Ipv6Net") || rendered.contains("Ipv4Net") {
+                if tag == "instances" && self.method == "POST" && p == "user_data" {
+                    // Read from the files given on the command line, and encoded.
+                    req_body_rendered.push(quote!(#p_og: user_data.clone()));
+                } else if rendered.contains("Ipv6Net") || rendered.contains("Ipv4Net") {
                     if v.required {
                         req_body_rendered.push(quote!(#p_og: #p_short.as_ref().unwrap().to_string()));
                     } else {
@@ -1050,10 +1056,22 @@ impl Operation {
                     .map(|d| quote! { default_value = #d })
                     .unwrap_or_else(|| quote! { default_value_t });
 
+                // Sizes can be given with a unit, e.g. `20GiB`.
+                let parse = if rendered.ends_with("ByteCount") {
+                    quote! { , parse(try_from_str = crate::types::parse_byte_count) }
+                } else {
+                    quote! {}
+                };
+
                 quote! {
-                    #[clap(#long_flag, #short_flag #default)]
+                    #[clap(#long_flag, #short_flag #default #parse)]
                 }
             }
+        } else if self.method == "POST" && tag == "subnets" && name == "ipv4_block" {
+            // The block of a new subnet can be allocated with `--next-free` instead.
+            quote! {
+                #[clap(#long_flag, #short_flag required_unless_present = "next_free")]
+            }
         } else {
             quote! {
                 #[clap(#long_flag, #short_flag required = #requiredq)]
@@ -1085,6 +1103,27 @@ impl Operation {
         }
 
         for (param, p) in self.get_request_body_properties()? {
+            if tag == "instances" && self.method == "POST" && param == "user_data" {
+                // The API wants the user data base64 encoded, we take it from files.
+                params.push(quote! {
+                    /// A file with the user data for instance initialization systems, such as
+                    /// cloud-init, e.g. a script or a cloud-config. Use `-` to read it from stdin.
+                    #[clap(long = "user-data", short = 'u', conflicts_with_all = &["cloud_config", "ssh_keys"])]
+                    pub user_data: Option<std::path::PathBuf>,
+
+                    /// A cloud-config file to initialize the instance with, checked to be valid
+                    /// YAML before the instance is created. Use `-` to read it from stdin.
+                    #[clap(long = "cloud-config")]
+                    pub cloud_config: Option<std::path::PathBuf>,
+
+                    /// The name of one of your SSH keys, see `oxide ssh-key list`, to authorize
+                    /// on the instance through cloud-init. Can be repeated.
+                    #[clap(long = "ssh-key", multiple_occurrences = true)]
+                    pub ssh_keys: Vec<String>,
+                });
+                continue;
+            }
+
             params.push(self.render_struct_param(&param, tag, p.schema, p.description, p.required, p.default)?);
         }
 
@@ -1150,9 +1189,10 @@ impl Operation {
             let error_msg = format!("{} required in non-interactive mode", formatted);
 
             let is_check = t.get_is_check_fn(true)?;
+            let unless_shorthand = unless_shorthand(tag, &n);
 
             required_checks.push(quote!(
-                if #p.#is_check() && !ctx.io.can_prompt() {
+                if #p.#is_check() #unless_shorthand && !ctx.io.can_prompt() {
                     return Err(anyhow::anyhow!(#error_msg));
                 }
             ));
@@ -1298,9 +1338,10 @@ impl Operation {
                 } else {
                     quote! { oxide_api::types::#rendered::prompt(#base_prompt)? }
                 };
+                let unless_shorthand = unless_shorthand(tag, &n);
                 additional_prompts.push(quote! {
                     // Prompt if they didn't provide the value.
-                    if #p.#is_check() {
+                    if #p.#is_check() #unless_shorthand {
                         {
                             use crate::prompt_ext::PromptExt;
                             #p = #prompt;
@@ -1371,13 +1412,285 @@ impl Operation {
 
         let additional_struct_params = self.get_additional_struct_params(tag)?;
 
+        // The user data of instances comes from files, and it is checked before anything
+        // is created.
+        let user_data = if tag == "instances" {
+            quote! {
+                let user_data = crate::cloud_init::user_data(
+                    ctx,
+                    self.user_data.as_deref(),
+                    self.cloud_config.as_deref(),
+                    &self.ssh_keys,
+                )
+                .await?;
+            }
+        } else {
+            quote!()
+        };
+
+        // Instances can also be created from a file with the whole body of the request, the
+        // name then comes from the file unless it is given.
+        let (name_clap, from_file_params, from_file) = if tag == "instances" {
+            (
+                quote! {
+                    #[clap(name = #singular_tag_str, default_value = "")]
+                },
+                quote! {
+                    /// Create the instance from this YAML, or JSON, file with the whole body of
+                    /// the request: disks, network interfaces, external IPs and all. The flags for
+                    /// the fields of the request are ignored, the name of the instance defaults
+                    /// to the one in the file. Use `-` to read it from stdin.
+                    #[clap(long, conflicts_with = "emit_template")]
+                    pub from_file: Option<std::path::PathBuf>,
+
+                    /// Print a commented template for `--from-file`.
+                    #[clap(long)]
+                    pub emit_template: bool,
+                },
+                quote! {
+                    if self.emit_template {
+                        write!(ctx.io.out, "{}", crate::cmd_instance::INSTANCE_TEMPLATE)?;
+                        return Ok(());
+                    }
+
+                    if let Some(path) = &self.from_file {
+                        return self.create_from_file(ctx, path).await;
+                    }
+                },
+            )
+        } else {
+            (
+                quote! {
+                    #[clap(name = #singular_tag_str, required = true)]
+                },
+                quote!(),
+                quote!(),
+            )
+        };
+
+        // Instances take a while to boot, so wait for them to settle unless told otherwise.
+        let (wait_params, wait_for_state) = if tag == "instances" {
+            (
+                quote! {
+                    /// How long to wait for the instance to be running (or stopped, without
+                    /// `--start`), e.g. `90s` or `5m`. Use `0` to wait forever.
+                    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+                    pub timeout: std::time::Duration,
+
+                    /// How often to check the state of the instance, e.g. `500ms` or `2s`.
+                    /// The interval backs off exponentially while waiting.
+                    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+                    pub poll_interval: std::time::Duration,
+
+                    /// Return once the instance is created, without waiting for it to boot.
+                    #[clap(long)]
+                    pub no_wait: bool,
+                },
+                quote! {
+                    if !self.no_wait {
+                        let status = if self.start {
+                            oxide_api::types::InstanceState::Running
+                        } else {
+                            oxide_api::types::InstanceState::Stopped
+                        };
+
+                        crate::cmd_instance::InstanceDetails {
+                            host: "".to_string(),
+                            organization: organization.to_string(),
+                            project: project.to_string(),
+                            instance: instance.to_string(),
+                        }
+                        .wait_for_state(
+                            ctx,
+                            status,
+                            &crate::wait::WaitOptions {
+                                timeout: self.timeout,
+                                poll_interval: self.poll_interval,
+                            },
+                        )
+                        .await?;
+                    }
+                },
+            )
+        } else if tag == "images" {
+            // Images from URLs are fetched by the rack, wait for them to be there.
+            (
+                quote! {
+                    /// How long to wait for the image to be available, e.g. `90s` or `5m`. Use
+                    /// `0` to wait forever.
+                    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+                    pub timeout: std::time::Duration,
+
+                    /// How often to check whether the image is available, e.g. `500ms` or `2s`.
+                    /// The interval backs off exponentially while waiting.
+                    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+                    pub poll_interval: std::time::Duration,
+
+                    /// Return once the image is created, without waiting for it to be available.
+                    #[clap(long)]
+                    pub no_wait: bool,
+                },
+                quote! {
+                    if !self.no_wait {
+                        crate::image::wait_for_image(
+                            ctx,
+                            &organization,
+                            &project,
+                            &image,
+                            &crate::wait::WaitOptions {
+                                timeout: self.timeout,
+                                poll_interval: self.poll_interval,
+                            },
+                        )
+                        .await?;
+                    }
+                },
+            )
+        } else if tag == "snapshots" {
+            // Snapshots have no state in the API, so all we can wait for is the snapshot
+            // to show up with its size, which the rack sets as soon as it creates it.
+            (
+                quote! {
+                    /// How long to wait for the snapshot to show up with its size, e.g. `90s`
+                    /// or `5m`. Use `0` to wait forever. Snapshots have no state in the API,
+                    /// so this only checks the snapshot exists.
+                    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+                    pub timeout: std::time::Duration,
+
+                    /// How often to check whether the snapshot shows up, e.g. `500ms` or `2s`.
+                    /// The interval backs off exponentially while waiting.
+                    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+                    pub poll_interval: std::time::Duration,
+
+                    /// Return once the snapshot is created, without checking it shows up.
+                    #[clap(long)]
+                    pub no_wait: bool,
+                },
+                quote! {
+                    if !self.no_wait {
+                        crate::cmd_snapshot::wait_for_snapshot(
+                            ctx,
+                            &organization,
+                            &project,
+                            &snapshot,
+                            &crate::wait::WaitOptions {
+                                timeout: self.timeout,
+                                poll_interval: self.poll_interval,
+                            },
+                        )
+                        .await?;
+                    }
+                },
+            )
+        } else {
+            (quote!(), quote!())
+        };
+
+        // Shorthands for the source of images, resolved before the required checks, and
+        // for the source of disks and the block of subnets, resolved after the prompts.
+        // All of them are checked before the request.
+        let (source_params, resolve_source, check_source) = if tag == "images" {
+            (
+                quote! {
+                    /// Create the image from this URL, the size of the image is checked first.
+                    #[clap(long, conflicts_with = "source")]
+                    pub url: Option<String>,
+
+                    /// The SHA-256 checksum of the image at `--url`. The image is downloaded to
+                    /// check it before the image is created.
+                    #[clap(long, requires = "url")]
+                    pub sha256: Option<String>,
+                },
+                quote! {
+                    if let Some(url) = &self.url {
+                        source = Some(oxide_api::types::ImageSource::Url { url: url.to_string() });
+                    }
+                },
+                quote! {
+                    if let Some(url) = &self.url {
+                        crate::image::check_url(ctx, url, block_size, self.sha256.as_deref()).await?;
+                    }
+                },
+            )
+        } else if tag == "disks" {
+            (
+                quote! {
+                    /// Create the disk from this project image. Can be an ID or name.
+                    #[clap(long, conflicts_with_all = &["disk_source", "from_snapshot", "blank"])]
+                    pub from_image: Option<String>,
+
+                    /// Create the disk from this snapshot. Can be an ID or name.
+                    #[clap(long, conflicts_with_all = &["disk_source", "blank"])]
+                    pub from_snapshot: Option<String>,
+
+                    /// Create a blank disk, with blocks of `--block-size` bytes.
+                    #[clap(long, conflicts_with = "disk_source")]
+                    pub blank: bool,
+
+                    /// The block size of a blank disk in bytes.
+                    #[clap(long, default_value = "512", possible_values = &["512", "2048", "4096"])]
+                    pub block_size: oxide_api::types::BlockSize,
+                },
+                quote!(),
+                quote! {
+                    // The organization and project may only be known once we prompted for them.
+                    let sized_source = crate::cmd_disk::SizedDiskSource::resolve(
+                        ctx,
+                        &organization,
+                        &project,
+                        self.from_image.as_deref(),
+                        self.from_snapshot.as_deref(),
+                        if self.blank { Some(self.block_size) } else { None },
+                    )
+                    .await?;
+                    if let Some(sized_source) = &sized_source {
+                        disk_source = Some(sized_source.source.clone());
+                        if size.is_zero() {
+                            size = sized_source.min_size;
+                        }
+                        sized_source.check_size(size)?;
+                    }
+                },
+            )
+        } else if tag == "subnets" {
+            (
+                quote! {
+                    /// Allocate the first free IPv4 block of this size in the VPC, e.g. `/24`,
+                    /// instead of passing `--ipv4-block`.
+                    #[clap(long, conflicts_with = "ipv4_block", parse(try_from_str = crate::cmd_subnet::parse_prefix_len))]
+                    pub next_free: Option<u8>,
+                },
+                quote!(),
+                quote! {
+                    // The VPC may only be known once we prompted for it.
+                    if let Some(prefix_len) = self.next_free {
+                        ipv4_block = Some(
+                            crate::cmd_subnet::next_free_block(ctx, &organization, &project, &vpc, prefix_len).await?,
+                        );
+                    }
+
+                    crate::cmd_subnet::check_blocks(
+                        ctx,
+                        &organization,
+                        &project,
+                        &vpc,
+                        ipv4_block.as_ref(),
+                        self.ipv6_block.as_ref(),
+                    )
+                    .await?;
+                },
+            )
+        } else {
+            (quote!(), quote!(), quote!())
+        };
+
         let cmd = quote!(
             #[doc = #struct_doc]
             #[derive(clap::Parser, Debug, Clone)]
             #[clap(verbatim_doc_comment)]
             pub struct #struct_name {
                 #[doc = #struct_inner_name_doc]
-                #[clap(name = #singular_tag_str, required = true)]
+                #name_clap
                 pub #singular_tag_lc: String,
 
                 #project_param
@@ -1385,15 +1698,27 @@ impl Operation {
                 #organization_param
 
                 #(#additional_struct_params)*
+
+                #from_file_params
+
+                #source_params
+
+                #wait_params
             }
 
             #[async_trait::async_trait]
             impl crate::cmd::Command for #struct_name {
                 async fn run(&self, ctx: &mut crate::context::Context) -> anyhow::Result<()> {
+                    #from_file
+
                     #(#mutable_variables)*
 
+                    #resolve_source
+
                     #(#required_checks)*
 
+                    #user_data
+
                     let client = ctx.api_client("")?;
 
                     // Prompt for various parameters if we can, and the user passed them as empty.
@@ -1407,6 +1732,8 @@ impl Operation {
                         #(#additional_prompts)*
                     }
 
+                    #check_source
+
                     client
                         .#tag_ident()
                         .post(
@@ -1414,6 +1741,8 @@ impl Operation {
                         )
                         .await?;
 
+                    #wait_for_state
+
                     let cs = ctx.io.color_scheme();
                     #output
 
@@ -1694,6 +2023,10 @@ impl Operation {
                 /// Display output in json, yaml, or table format.
                 #[clap(long, short)]
                 pub format: Option<crate::types::FormatOutput>,
+
+                /// Refresh the output every interval, 2s by default, until interrupted.
+                #[clap(long, min_values = 0, default_missing_value = crate::watch::DEFAULT_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+                pub watch: Option<std::time::Duration>,
             }
 
             #[async_trait::async_trait]
@@ -1713,6 +2046,14 @@ impl Operation {
 
                     let client = ctx.api_client("")?;
 
+                    if let Some(interval) = self.watch {
+                        let client = &client;
+                        return crate::watch::watch(ctx, interval, crate::watch::Layout::Record, &self.format, || async move {
+                            Ok(vec![client.#tag_ident().get(#(#api_call_params),*).await?])
+                        })
+                        .await;
+                    }
+
                     let result = client.#tag_ident().get(#(#api_call_params),*).await?;
 
                     let format = ctx.format(&self.format)?;
@@ -1808,6 +2149,10 @@ impl Operation {
                 /// Display output in json, yaml, or table format.
                 #[clap(long, short)]
                 pub format: Option<crate::types::FormatOutput>,
+
+                /// Refresh the output every interval, 2s by default, until interrupted.
+                #[clap(long, min_values = 0, default_missing_value = crate::watch::DEFAULT_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+                pub watch: Option<std::time::Duration>,
             }
 
             #[async_trait::async_trait]
@@ -1818,23 +2163,34 @@ impl Operation {
                 }
 
                 let client = ctx.api_client("")?;
-
-                let results = if self.paginate {
-                    client
-                        .#tag_ident()
-                        .get_all(
-                            #(#api_call_params_all),*
-                        )
-                        .await?
-                } else {
-                    client
-                        .#tag_ident()
-                        .get_page(
-                            #(#api_call_params),*
-                        )
-                        .await?
+                let client = &client;
+
+                let fetch = || async move {
+                    let results = if self.paginate {
+                        client
+                            .#tag_ident()
+                            .get_all(
+                                #(#api_call_params_all),*
+                            )
+                            .await?
+                    } else {
+                        client
+                            .#tag_ident()
+                            .get_page(
+                                #(#api_call_params),*
+                            )
+                            .await?
+                    };
+
+                    Ok(results)
                 };
 
+                if let Some(interval) = self.watch {
+                    return crate::watch::watch(ctx, interval, crate::watch::Layout::Rows, &self.format, fetch).await;
+                }
+
+                let results = fetch().await?;
+
                 let format = ctx.format(&self.format)?;
                 ctx.io.write_output_for_vec(&format, &results)?;
                 Ok(())
@@ -1848,21 +2204,82 @@ impl Operation {
     }
 
     /// Generate the delete command.
-    fn generate_delete_command(&self, tag: &str) -> Result<(TokenStream, syn::Variant)> {
+    ///
+    /// The list operation for the tag, if there is one, is used to resolve the `--all`
+    /// and `--match` selectors.
+    fn generate_delete_command(&self, tag: &str, list_op: Option<&Operation>) -> Result<(TokenStream, syn::Variant)> {
         let tag_ident = format_ident!("{}", tag);
         let singular_tag_str = if tag == "vpcs" {
             singular(tag).to_uppercase()
         } else {
             singular(tag)
         };
+        let plural_tag_str = plural(&singular_tag_str);
         let singular_tag_lc = format_ident!("{}", singular(tag));
         let struct_name = format_ident!("Cmd{}Delete", to_title_case(&singular(tag)));
 
-        let struct_doc = format!("Delete {}.", singular_tag_str);
-        let struct_inner_name_doc = format!("The {} to delete. Can be an ID or name.", singular_tag_str);
+        let struct_doc = format!(
+            "Delete {}.\n\nPass one or more names, or select the {} to delete with `--all`, `--match` or `--from-stdin`.",
+            singular_tag_str, plural_tag_str
+        );
+        let struct_inner_name_doc = format!("The {} to delete. Can be an ID or name.", plural_tag_str);
         let struct_inner_project_doc = format!("The project to delete the {} from.", singular_tag_str);
+        let struct_inner_all_doc = format!("Delete all {}.", plural_tag_str);
+        let struct_inner_selector_doc = format!(
+            "Delete {} whose name matches a glob (e.g. `web-*`), or a regular expression wrapped in slashes (e.g. `/^web-[0-9]+$/`).",
+            plural_tag_str
+        );
+        let nothing_selected_msg = format!(
+            "no {} selected, pass one or more names, --all, --match or --from-stdin",
+            plural_tag_str
+        );
 
-        let api_call_params = self.get_api_call_params(tag)?;
+        // The delete call is made once per selected name, so swap the resource name out for
+        // the name we are currently deleting.
+        let mut params = self.get_parameters()?.keys().cloned().collect::<Vec<_>>();
+        params.sort();
+        let mut api_call_params: Vec<TokenStream> = Vec::new();
+        for p in params {
+            let p = clean_param_name(&p);
+            if p == singular(tag) {
+                api_call_params.push(quote!(&name));
+            } else {
+                let p = format_ident!("{}", p);
+                api_call_params.push(quote!(&self.#p));
+            }
+        }
+
+        // Listing every resource lets us expand `--all` and `--match`.
+        let list_resources = if let Some(list_op) = list_op {
+            let mut list_call_params: Vec<TokenStream> = Vec::new();
+            for p in list_op.get_all_param_names()? {
+                if p == "limit" || p == "page_token" {
+                    continue;
+                }
+
+                if p == "sort_by" {
+                    list_call_params.push(quote!(Default::default()));
+                    continue;
+                }
+
+                let n = clean_param_name(&p);
+                let ident = format_ident!("{}", n);
+
+                list_call_params.push(quote!(&self.#ident));
+            }
+
+            quote! {
+                client
+                    .#tag_ident()
+                    .get_all(#(#list_call_params),*)
+                    .await?
+                    .into_iter()
+                    .map(|item| item.name.to_string())
+                    .collect()
+            }
+        } else {
+            quote!(Vec::new())
+        };
 
         // We need to check if project is a parameter to this call.
         let project_param = if self.is_parameter("project") && tag != "projects" {
@@ -1888,26 +2305,55 @@ impl Operation {
 
         let additional_struct_params = self.get_additional_struct_params(tag)?;
 
+        // Projects and organizations can only be deleted once they are empty, so they can
+        // be torn down together with everything in them.
+        let (recursive_param, recursive_delete) = match tag {
+            "projects" => (
+                quote! {
+                    /// Delete everything in the projects first: instances, disks,
+                    /// snapshots, images, and VPCs with their subnets, routers and routes.
+                    #[clap(long)]
+                    pub recursive: bool,
+                },
+                quote! {
+                    if self.recursive {
+                        return crate::teardown::delete_projects(ctx, &client, &self.organization, &names, self.confirm).await;
+                    }
+                },
+            ),
+            "organizations" => (
+                quote! {
+                    /// Delete everything in the organizations first: their projects with
+                    /// everything in them.
+                    #[clap(long)]
+                    pub recursive: bool,
+                },
+                quote! {
+                    if self.recursive {
+                        return crate::teardown::delete_organizations(ctx, &client, &names, self.confirm).await;
+                    }
+                },
+            ),
+            _ => (quote!(), quote!()),
+        };
+
         // We need to form the output back to the client.
         let output = if self.is_parameter("organization") && self.is_parameter("project") {
-            let start = quote! {
-                let full_name = format!("{}/{}", self.organization, self.project);
-            };
             if tag != "projects" {
                 quote! {
-                    #start
+                    let full_name = format!("{}/{}", self.organization, self.project);
                     writeln!(
                         ctx.io.out,
                         "{} Deleted {} {} from {}",
                         cs.success_icon_with_color(ansi_term::Color::Red),
                         #singular_tag_str,
-                        self.#singular_tag_lc,
+                        name,
                         full_name
                     )?;
                 }
             } else {
                 quote! {
-                    #start
+                    let full_name = format!("{}/{}", self.organization, name);
                     writeln!(
                         ctx.io.out,
                         "{} Deleted {} {}",
@@ -1924,7 +2370,7 @@ impl Operation {
                     "{} Deleted {} {}",
                     cs.success_icon_with_color(ansi_term::Color::Red),
                     #singular_tag_str,
-                    self.#singular_tag_lc
+                    name
                 )?;
             }
         };
@@ -1935,8 +2381,8 @@ impl Operation {
             #[clap(verbatim_doc_comment)]
             pub struct #struct_name {
                 #[doc = #struct_inner_name_doc]
-                #[clap(name = #singular_tag_str, required = true)]
-                pub #singular_tag_lc: String,
+                #[clap(name = #singular_tag_str, multiple_values = true)]
+                pub #singular_tag_lc: Vec<String>,
 
                 #project_param
 
@@ -1944,6 +2390,24 @@ impl Operation {
 
                 #(#additional_struct_params)*
 
+                #[doc = #struct_inner_all_doc]
+                #[clap(long)]
+                pub all: bool,
+
+                #[doc = #struct_inner_selector_doc]
+                #[clap(long = "match")]
+                pub selector: Option<String>,
+
+                /// Read additional names or IDs to delete from stdin, one per line.
+                #[clap(long)]
+                pub from_stdin: bool,
+
+                /// The maximum number of delete requests to run concurrently.
+                #[clap(long, default_value_t = crate::bulk::DEFAULT_PARALLEL)]
+                pub parallel: usize,
+
+                #recursive_param
+
                 /// Confirm deletion without prompting.
                 #[clap(long)]
                 pub confirm: bool,
@@ -1958,33 +2422,66 @@ impl Operation {
 
                     let client = ctx.api_client("")?;
 
+                    // Resolve everything that was selected into a list of names.
+                    let listed = if self.all || self.selector.is_some() {
+                        #list_resources
+                    } else {
+                        Vec::new()
+                    };
+                    let from_stdin = if self.from_stdin {
+                        crate::bulk::read_names(&mut ctx.io.stdin)?
+                    } else {
+                        Vec::new()
+                    };
+                    let names = crate::bulk::targets(&self.#singular_tag_lc, listed, self.all, &self.selector, from_stdin)?;
+                    if names.is_empty() {
+                        return Err(anyhow::anyhow!(#nothing_selected_msg));
+                    }
+
+                    #recursive_delete
 
                     // Confirm deletion.
                     if !self.confirm {
-                        if let Err(err) = dialoguer::Input::<String>::new()
-                            .with_prompt(format!("Type {} to confirm deletion:", self.#singular_tag_lc))
-                            .validate_with(|input: &String| -> Result<(), &str> {
-                                if input.trim() == self.#singular_tag_lc {
-                                    Ok(())
-                                } else {
-                                    Err("mismatched confirmation")
-                                }
-                            })
-                            .interact_text()
-                        {
-                            return Err(anyhow::anyhow!("prompt failed: {}", err));
-                        }
+                        crate::bulk::confirm(ctx, "deletion", #plural_tag_str, &names)?;
                     }
 
+                    if names.len() == 1 {
+                        let name = names[0].to_string();
 
-                    client
-                        .#tag_ident()
-                        .delete(#(#api_call_params),*)
-                        .await?;
+                        client
+                            .#tag_ident()
+                            .delete(#(#api_call_params),*)
+                            .await?;
 
-                    let cs = ctx.io.color_scheme();
+                        let cs = ctx.io.color_scheme();
 
-                    #output
+                        #output
+
+                        return Ok(());
+                    }
+
+                    let results = crate::bulk::run(&names, self.parallel, |name| {
+                        let client = &client;
+                        async move {
+                            client
+                                .#tag_ident()
+                                .delete(#(#api_call_params),*)
+                                .await?;
+                            Ok(())
+                        }
+                    })
+                    .await;
+
+                    crate::bulk::write_summary(ctx, "deleted", #plural_tag_str, results)?;
+
+                    let cs = ctx.io.color_scheme();
+                    writeln!(
+                        ctx.io.out,
+                        "{} Deleted {} {}",
+                        cs.success_icon_with_color(ansi_term::Color::Red),
+                        names.len(),
+                        #plural_tag_str
+                    )?;
 
                     Ok(())
                 }
@@ -2062,6 +2559,20 @@ fn singular(s: &str) -> String {
     s.to_string()
 }
 
+/// The condition for a required parameter to be asked for, when a shorthand can fill it
+/// in after the prompts instead, e.g. the block of a subnet with `--next-free`.
+fn unless_shorthand(tag: &str, n: &str) -> TokenStream {
+    match (tag, n) {
+        ("subnets", "ipv4_block") => quote! { && self.next_free.is_none() },
+        ("disks", "disk_source") => {
+            quote! { && self.from_image.is_none() && self.from_snapshot.is_none() && !self.blank }
+        }
+        // A blank disk has no size to start from.
+        ("disks", "size") => quote! { && self.from_image.is_none() && self.from_snapshot.is_none() },
+        _ => quote!(),
+    }
+}
+
 fn skip_defaults(n: &str, tag: &str) -> bool {
     n == singular(tag)
         || n == "project"
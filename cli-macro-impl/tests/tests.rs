@@ -137,4 +137,17 @@ fn test_do_gen() {
     .unwrap();
 
     expectorate::assert_contents("tests/gen/images_global.rs.gen", &get_text_fmt(&actual).unwrap());
+
+    actual = do_gen(
+        quote! {
+            tag = "snapshots",
+        },
+        quote! {
+            #[derive(Parser, Debug, Clone)]
+            enum SubCommand {}
+        },
+    )
+    .unwrap();
+
+    expectorate::assert_contents("tests/gen/snapshots.rs.gen", &get_text_fmt(&actual).unwrap());
 }
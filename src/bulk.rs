@@ -0,0 +1,261 @@
+use std::{
+    future::Future,
+    io::{BufRead, Write},
+};
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use tabled::Tabled;
+
+/// The default number of requests a bulk operation keeps in flight.
+pub const DEFAULT_PARALLEL: usize = 4;
+
+/// The outcome of a bulk operation for a single resource.
+#[derive(Debug, Clone, serde::Serialize, Tabled)]
+pub struct BulkResult {
+    pub name: String,
+    pub status: String,
+    pub error: String,
+}
+
+/// Read resource names or IDs, one per line. Blank lines and lines starting
+/// with `#` are ignored.
+pub fn read_names<R: std::io::Read>(reader: R) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for line in std::io::BufReader::new(reader).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        names.push(line.to_string());
+    }
+
+    Ok(names)
+}
+
+/// Compile a `--match` selector.
+///
+/// A selector wrapped in slashes (`/^web-[0-9]+$/`) is used as a regular expression,
+/// anything else is treated as a glob where `*` matches any run of characters and `?`
+/// matches a single character.
+pub fn compile_selector(selector: &str) -> Result<regex::Regex> {
+    let pattern = if selector.len() > 1 && selector.starts_with('/') && selector.ends_with('/') {
+        selector[1..selector.len() - 1].to_string()
+    } else {
+        format!("^{}$", regex::escape(selector).replace(r"\*", ".*").replace(r"\?", "."))
+    };
+
+    regex::Regex::new(&pattern).map_err(|e| anyhow!("invalid selector `{}`: {}", selector, e))
+}
+
+/// Build the de-duplicated list of resources to act on from the positional names,
+/// the names listed from the API (when `--all` or `--match` is set), and the names
+/// read from stdin.
+pub fn targets(
+    names: &[String],
+    listed: Vec<String>,
+    all: bool,
+    selector: &Option<String>,
+    from_stdin: Vec<String>,
+) -> Result<Vec<String>> {
+    let mut targets: Vec<String> = names.to_vec();
+
+    if all {
+        targets.extend(listed);
+    } else if let Some(selector) = selector {
+        let re = compile_selector(selector)?;
+        targets.extend(listed.into_iter().filter(|name| re.is_match(name)));
+    }
+
+    targets.extend(from_stdin);
+
+    let mut seen = std::collections::HashSet::new();
+    targets.retain(|name| seen.insert(name.to_string()));
+
+    Ok(targets)
+}
+
+/// Prompt once for an action against one or more resources.
+///
+/// A single resource keeps the usual "type the name to confirm" prompt, several
+/// resources are listed and the user has to type how many there are.
+pub fn confirm(ctx: &mut crate::context::Context, action: &str, kind: &str, names: &[String]) -> Result<()> {
+    let expected = if names.len() == 1 {
+        names[0].to_string()
+    } else {
+        writeln!(ctx.io.out, "Selected {} {}:", names.len(), kind)?;
+        for name in names {
+            writeln!(ctx.io.out, "  - {}", name)?;
+        }
+
+        names.len().to_string()
+    };
+
+    if let Err(err) = dialoguer::Input::<String>::new()
+        .with_prompt(format!("Type {} to confirm {}:", expected, action))
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if input.trim() == expected {
+                Ok(())
+            } else {
+                Err("mismatched confirmation")
+            }
+        })
+        .interact_text()
+    {
+        return Err(anyhow!("prompt failed: {}", err));
+    }
+
+    Ok(())
+}
+
+/// Run `f` for every name, keeping at most `parallel` requests in flight.
+/// The results are returned in the same order as the names.
+pub async fn run<F, Fut>(names: &[String], parallel: usize, f: F) -> Vec<(String, Result<()>)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut results: Vec<(usize, String, Result<()>)> = futures::stream::iter(names.iter().enumerate())
+        .map(|(i, name)| {
+            let fut = f(name.to_string());
+            async move { (i, name.to_string(), fut.await) }
+        })
+        .buffer_unordered(parallel.max(1))
+        .collect()
+        .await;
+
+    results.sort_by_key(|(i, _, _)| *i);
+    results.into_iter().map(|(_, name, result)| (name, result)).collect()
+}
+
+/// Print a table with the outcome of every operation, returning an error if any of
+/// them failed.
+pub fn write_summary(
+    ctx: &mut crate::context::Context,
+    verb: &str,
+    kind: &str,
+    results: Vec<(String, Result<()>)>,
+) -> Result<()> {
+    let cs = ctx.io.color_scheme();
+
+    let total = results.len();
+    let mut failed = 0;
+    let mut rows = Vec::with_capacity(total);
+    for (name, result) in results {
+        match result {
+            Ok(()) => rows.push(BulkResult {
+                name,
+                status: cs.green("ok"),
+                error: "".to_string(),
+            }),
+            Err(err) => {
+                failed += 1;
+                rows.push(BulkResult {
+                    name,
+                    status: cs.red("failed"),
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+
+    ctx.io.write_output_table_for_vec(rows)?;
+
+    if failed > 0 {
+        return Err(anyhow!("{} of {} {} could not be {}", failed, total, kind, verb));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_compile_selector() {
+        let glob = compile_selector("web-*").unwrap();
+        assert!(glob.is_match("web-1"));
+        assert!(glob.is_match("web-"));
+        assert!(!glob.is_match("db-web-1"));
+
+        let single = compile_selector("db-?").unwrap();
+        assert!(single.is_match("db-1"));
+        assert!(!single.is_match("db-10"));
+
+        let literal = compile_selector("my.app").unwrap();
+        assert!(literal.is_match("my.app"));
+        assert!(!literal.is_match("myxapp"));
+
+        let re = compile_selector("/^web-[0-9]+$/").unwrap();
+        assert!(re.is_match("web-10"));
+        assert!(!re.is_match("web-a"));
+
+        assert!(compile_selector("/web-[/").is_err());
+    }
+
+    #[test]
+    fn test_read_names() {
+        let input = "web-1\n\n  web-2  \n# a comment\nweb-3\n";
+        assert_eq!(
+            read_names(input.as_bytes()).unwrap(),
+            vec!["web-1".to_string(), "web-2".to_string(), "web-3".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        let listed = vec!["web-1".to_string(), "web-2".to_string(), "db-1".to_string()];
+
+        let got = targets(
+            &["db-1".to_string()],
+            listed.clone(),
+            false,
+            &Some("web-*".to_string()),
+            vec!["web-2".to_string(), "cache-1".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            got,
+            vec![
+                "db-1".to_string(),
+                "web-1".to_string(),
+                "web-2".to_string(),
+                "cache-1".to_string()
+            ]
+        );
+
+        let got = targets(&[], listed.clone(), true, &None, vec![]).unwrap();
+        assert_eq!(got, listed);
+
+        let got = targets(&[], listed, false, &None, vec![]).unwrap();
+        assert!(got.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_keeps_order() {
+        let names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let results = run(&names, 2, |name| async move {
+            if name == "b" {
+                Err(anyhow!("boom"))
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+
+        let got: Vec<(String, bool)> = results.into_iter().map(|(n, r)| (n, r.is_ok())).collect();
+        assert_eq!(
+            got,
+            vec![
+                ("a".to_string(), true),
+                ("b".to_string(), false),
+                ("c".to_string(), true)
+            ]
+        );
+    }
+}
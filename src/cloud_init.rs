@@ -0,0 +1,185 @@
+use std::{io::Read, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+
+/// The API refuses user data larger than this, before it is base64 encoded.
+const MAX_USER_DATA_SIZE: usize = 32 * 1024;
+
+/// The first line of a cloud-config, which is how cloud-init tells it apart from the
+/// other kinds of user data, e.g. scripts.
+const CLOUD_CONFIG_HEADER: &str = "#cloud-config";
+
+/// The user data of `oxide instance create`, base64 encoded as the API wants it.
+///
+/// It is either the contents of `user_data` as is, or the cloud-config in
+/// `cloud_config` with the public keys of `ssh_keys` added to its
+/// `ssh_authorized_keys`. Either file can be `-`, to read it from stdin.
+pub async fn user_data(
+    ctx: &mut crate::context::Context<'_>,
+    user_data: Option<&Path>,
+    cloud_config: Option<&Path>,
+    ssh_keys: &[String],
+) -> Result<String> {
+    let data = match (user_data, cloud_config) {
+        (Some(path), _) => {
+            let data = read_file(path, &mut ctx.io.stdin)?;
+            validate(&data).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            data
+        }
+        (None, Some(path)) => {
+            let text = String::from_utf8(read_file(path, &mut ctx.io.stdin)?)
+                .map_err(|_| anyhow!("{}: a cloud-config must be UTF-8", path.display()))?;
+            let keys = authorized_keys(ctx, ssh_keys).await?;
+            with_authorized_keys(&text, &keys).map_err(|e| anyhow!("{}: {}", path.display(), e))?
+        }
+        (None, None) if !ssh_keys.is_empty() => {
+            let keys = authorized_keys(ctx, ssh_keys).await?;
+            with_authorized_keys("", &keys)?
+        }
+        (None, None) => return Ok(String::new()),
+    };
+
+    if data.len() > MAX_USER_DATA_SIZE {
+        bail!(
+            "the user data is {} bytes, it cannot be larger than {} bytes",
+            data.len(),
+            MAX_USER_DATA_SIZE
+        );
+    }
+
+    Ok(base64::encode(&data))
+}
+
+fn read_file(path: &Path, stdin: &mut dyn Read) -> Result<Vec<u8>> {
+    if path == Path::new("-") {
+        let mut data = Vec::new();
+        stdin.read_to_end(&mut data)?;
+        return Ok(data);
+    }
+
+    std::fs::read(path).map_err(|e| anyhow!("reading {}: {}", path.display(), e))
+}
+
+/// The public keys of the SSH keys of the current user with these names.
+async fn authorized_keys(ctx: &mut crate::context::Context<'_>, names: &[String]) -> Result<Vec<String>> {
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let client = ctx.api_client("")?;
+    let keys = client
+        .sshkeys()
+        .get_all(oxide_api::types::NameSortMode::NameAscending)
+        .await?;
+
+    names
+        .iter()
+        .map(|name| {
+            keys.iter()
+                .find(|key| key.name == *name)
+                .map(|key| key.public_key.trim().to_string())
+                .ok_or_else(|| anyhow!("you have no SSH key named `{}`, see `oxide ssh-key list`", name))
+        })
+        .collect()
+}
+
+/// Catch the mistakes in a cloud-config before the instance boots with it, other
+/// kinds of user data are opaque to us.
+fn validate(data: &[u8]) -> Result<()> {
+    if !data.starts_with(CLOUD_CONFIG_HEADER.as_bytes()) {
+        return Ok(());
+    }
+
+    let text = std::str::from_utf8(data).map_err(|_| anyhow!("a cloud-config must be UTF-8"))?;
+    parse(text)?;
+    Ok(())
+}
+
+fn parse(text: &str) -> Result<serde_yaml::Mapping> {
+    // Nothing but the header, or comments, is an empty cloud-config.
+    if text
+        .lines()
+        .all(|line| line.trim().is_empty() || line.trim_start().starts_with('#'))
+    {
+        return Ok(serde_yaml::Mapping::new());
+    }
+
+    match serde_yaml::from_str(text).map_err(|e| anyhow!("invalid cloud-config: {}", e))? {
+        serde_yaml::Value::Null => Ok(serde_yaml::Mapping::new()),
+        serde_yaml::Value::Mapping(mapping) => Ok(mapping),
+        _ => bail!("invalid cloud-config: expected a mapping, e.g. `packages: [nginx]`"),
+    }
+}
+
+/// Validate a cloud-config, and add `keys` to its `ssh_authorized_keys`.
+fn with_authorized_keys(text: &str, keys: &[String]) -> Result<Vec<u8>> {
+    let mut config = parse(text)?;
+
+    if keys.is_empty() {
+        // Keep the comments and the formatting of the file.
+        if text.starts_with(CLOUD_CONFIG_HEADER) {
+            return Ok(text.as_bytes().to_vec());
+        }
+        return Ok(format!("{}\n{}", CLOUD_CONFIG_HEADER, text).into_bytes());
+    }
+
+    let field = serde_yaml::Value::String("ssh_authorized_keys".to_string());
+    if config.get(&field).is_none() {
+        config.insert(field.clone(), serde_yaml::Value::Sequence(Vec::new()));
+    }
+    let authorized_keys = match config.get_mut(&field) {
+        Some(serde_yaml::Value::Sequence(authorized_keys)) => authorized_keys,
+        _ => bail!("invalid cloud-config: `ssh_authorized_keys` must be a list"),
+    };
+    for key in keys {
+        let key = serde_yaml::Value::String(key.to_string());
+        if !authorized_keys.contains(&key) {
+            authorized_keys.push(key);
+        }
+    }
+
+    let yaml = serde_yaml::to_string(&config)?;
+    Ok(format!("{}\n{}\n", CLOUD_CONFIG_HEADER, yaml.trim_start_matches("---").trim()).into_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_cloud_config() {
+        let key = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGRs jess@laptop".to_string();
+
+        assert_eq!(
+            String::from_utf8(with_authorized_keys("packages: [nginx]\n", &[key.clone()]).unwrap()).unwrap(),
+            format!(
+                "#cloud-config\npackages:\n  - nginx\nssh_authorized_keys:\n  - {}\n",
+                key
+            )
+        );
+        assert_eq!(
+            String::from_utf8(with_authorized_keys("", &[key.clone(), key.clone()]).unwrap()).unwrap(),
+            format!("#cloud-config\nssh_authorized_keys:\n  - {}\n", key)
+        );
+        assert_eq!(
+            String::from_utf8(with_authorized_keys("# web\npackages: [nginx]\n", &[]).unwrap()).unwrap(),
+            "#cloud-config\n# web\npackages: [nginx]\n"
+        );
+
+        assert_eq!(
+            with_authorized_keys("ssh_authorized_keys: yes\n", &[key])
+                .unwrap_err()
+                .to_string(),
+            "invalid cloud-config: `ssh_authorized_keys` must be a list"
+        );
+        assert_eq!(
+            with_authorized_keys("- nginx\n", &[]).unwrap_err().to_string(),
+            "invalid cloud-config: expected a mapping, e.g. `packages: [nginx]`"
+        );
+
+        assert!(validate(b"#!/bin/sh\necho hello: [\n").is_ok());
+        assert!(validate(b"#cloud-config\npackages: [nginx\n").is_err());
+    }
+}
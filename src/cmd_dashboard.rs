@@ -0,0 +1,867 @@
+use std::{collections::HashMap, io::Write, mem::swap, time::Duration};
+
+use anyhow::Result;
+use clap::Parser;
+use futures::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+/// The default interval between two refreshes of the dashboard.
+const DEFAULT_REFRESH: &str = "5s";
+
+/// The number of sagas shown in the dashboard.
+const SAGA_LIMIT: u32 = 20;
+
+/// The width of the projects pane, unless the terminal is too narrow for it.
+const PROJECTS_WIDTH: usize = 28;
+
+/// Ctrl-], leaves the serial console and goes back to the dashboard.
+const CONSOLE_ESCAPE: u8 = b'\x1d';
+
+/// Show projects, instances, disks and sagas on a single screen.
+///
+/// The dashboard refreshes itself periodically and is driven with the keyboard:
+///
+///     tab, shift-tab    move to the next or previous pane
+///     up, down, j, k    select a row
+///     enter             show the instances and disks of the selected project
+///     s                 start the selected instance
+///     x                 stop the selected instance
+///     b                 reboot the selected instance
+///     c                 attach to the serial console of the selected instance,
+///                       ctrl-] goes back to the dashboard
+///     r                 refresh now
+///     q, ctrl-c         quit
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdDashboard {
+    /// The project to show first. Defaults to the first project of the organization.
+    #[clap(long, short, default_value = "")]
+    pub project: String,
+
+    /// The organization to show.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// How often to refresh the dashboard, e.g. `5s` or `1m`.
+    #[clap(long, default_value = DEFAULT_REFRESH, parse(try_from_str = crate::wait::parse_duration))]
+    pub refresh: Duration,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdDashboard {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        if !ctx.io.is_stdout_tty() {
+            return Err(anyhow::anyhow!("Stdout must be a TTY to use the dashboard."));
+        }
+
+        let client = ctx.api_client("")?;
+
+        let mut dashboard = Dashboard::new(&self.organization, &self.project);
+
+        let mut stdin: Box<dyn std::io::Read + Send + Sync> = Box::new(std::io::empty());
+        swap(&mut stdin, &mut ctx.io.stdin);
+
+        let _raw_guard = crate::cmd_instance_serial::RawTermiosGuard::stdio_guard()?;
+        let _screen = AlternateScreen::enter()?;
+
+        // Reads from stdin are not cancel safe, so read in a separate task and put
+        // the bytes onto this channel. See `cmd_instance_serial`.
+        let (stdintx, mut stdinrx) = tokio::sync::mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut inbuf = [0u8; 1024];
+
+            loop {
+                let n = match tokio::task::block_in_place(|| stdin.read(&mut inbuf)) {
+                    Err(_) | Ok(0) => break,
+                    Ok(n) => n,
+                };
+
+                if stdintx.send(inbuf[0..n].to_vec()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut ticker = tokio::time::interval(self.refresh);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    self.refresh(ctx, &client, &mut dashboard).await;
+                }
+                bytes = stdinrx.recv() => {
+                    let bytes = match bytes {
+                        Some(bytes) => bytes,
+                        None => return Ok(()),
+                    };
+
+                    for key in parse_keys(&bytes) {
+                        match dashboard.handle_key(key) {
+                            Action::None => {}
+                            Action::Quit => return Ok(()),
+                            Action::Refresh => {
+                                self.refresh(ctx, &client, &mut dashboard).await;
+                                ticker.reset();
+                            }
+                            Action::Instance(action, instance) => {
+                                self.instance_action(ctx, &client, &mut dashboard, action, &instance).await;
+                                self.refresh(ctx, &client, &mut dashboard).await;
+                                ticker.reset();
+                            }
+                            Action::Console(instance) => {
+                                let project = dashboard.project.to_string();
+                                if let Err(err) = self.console(ctx, &mut stdinrx, &project, &instance).await {
+                                    dashboard.status = ctx.io.color_scheme().red(&err.to_string());
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            self.draw(ctx, &dashboard)?;
+        }
+    }
+}
+
+impl CmdDashboard {
+    /// Fetch everything shown on the dashboard. Errors are shown in the status line
+    /// rather than stopping the dashboard.
+    async fn refresh(
+        &self,
+        ctx: &mut crate::context::Context<'_>,
+        client: &oxide_api::Client,
+        dashboard: &mut Dashboard,
+    ) {
+        if let Err(err) = dashboard.refresh(client).await {
+            dashboard.status = ctx.io.color_scheme().red(&format!("Refresh failed: {}", err));
+        }
+    }
+
+    async fn instance_action(
+        &self,
+        ctx: &mut crate::context::Context<'_>,
+        client: &oxide_api::Client,
+        dashboard: &mut Dashboard,
+        action: InstanceAction,
+        instance: &str,
+    ) {
+        let instances = client.instances();
+        let result = match action {
+            InstanceAction::Start => instances.start(instance, &self.organization, &dashboard.project).await,
+            InstanceAction::Stop => instances.stop(instance, &self.organization, &dashboard.project).await,
+            InstanceAction::Reboot => instances.reboot(instance, &self.organization, &dashboard.project).await,
+        };
+
+        let cs = ctx.io.color_scheme();
+        dashboard.status = match result {
+            Ok(_) => format!("{} Requested {} of instance {}", cs.success_icon(), action, instance),
+            Err(err) => cs.red(&format!("Could not {} instance {}: {}", action, instance, err)),
+        };
+    }
+
+    /// Hand the screen, below a one line header, to the serial console of an instance
+    /// until the user presses Ctrl-].
+    async fn console(
+        &self,
+        ctx: &mut crate::context::Context<'_>,
+        stdinrx: &mut tokio::sync::mpsc::Receiver<Vec<u8>>,
+        project: &str,
+        instance: &str,
+    ) -> Result<()> {
+        let mut ws = crate::cmd_instance_serial::connect(ctx, &self.organization, project, instance).await?;
+
+        let cs = ctx.io.color_scheme();
+        let height = ctx.io.terminal_height();
+        let header = format!(
+            "Serial console of instance {}. Press Ctrl-] to go back to the dashboard.",
+            instance
+        );
+        // Clear the screen, draw the header and make everything below it scroll.
+        write!(
+            ctx.io.out,
+            "\x1b[2J\x1b[H{}\x1b[2;{}r\x1b[2;1H\x1b[?25h",
+            cs.bold(&header),
+            height
+        )?;
+        ctx.io.out.flush()?;
+
+        let result = async {
+            loop {
+                tokio::select! {
+                    bytes = stdinrx.recv() => {
+                        let bytes = match bytes {
+                            Some(bytes) => bytes,
+                            None => break,
+                        };
+
+                        if let Some(i) = bytes.iter().position(|b| *b == CONSOLE_ESCAPE) {
+                            if i > 0 {
+                                ws.send(Message::Binary(bytes[..i].to_vec())).await?;
+                            }
+                            break;
+                        }
+
+                        ws.send(Message::Binary(bytes)).await?;
+                    }
+                    msg = ws.next() => {
+                        match msg {
+                            Some(Ok(Message::Binary(output))) => {
+                                ctx.io.out.write_all(&output)?;
+                                ctx.io.out.flush()?;
+                            }
+                            Some(Ok(Message::Close(..))) | None => break,
+                            Some(Err(err)) => return Err(err.into()),
+                            _ => continue,
+                        }
+                    }
+                }
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        // Reset the scroll region before the dashboard takes the screen back.
+        write!(ctx.io.out, "\x1b[r\x1b[0m\x1b[?25l\x1b[2J")?;
+        ctx.io.out.flush()?;
+
+        ws.close(None).await.ok();
+        result
+    }
+
+    fn draw(&self, ctx: &mut crate::context::Context<'_>, dashboard: &Dashboard) -> Result<()> {
+        let cs = ctx.io.color_scheme();
+        let width = ctx.io.terminal_width().max(0) as usize;
+        let height = ctx.io.terminal_height().max(0) as usize;
+
+        // The terminal is in raw mode, so lines need an explicit carriage return.
+        write!(ctx.io.out, "\x1b[H")?;
+        for (i, line) in dashboard.render(&cs, width, height).iter().enumerate() {
+            if i > 0 {
+                write!(ctx.io.out, "\r\n")?;
+            }
+            write!(ctx.io.out, "{}\x1b[K", line)?;
+        }
+        ctx.io.out.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Switch to the alternate screen, with a hidden cursor, and back when dropped.
+struct AlternateScreen;
+
+impl AlternateScreen {
+    fn enter() -> Result<Self> {
+        let mut stdout = std::io::stdout();
+        write!(stdout, "\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        stdout.flush()?;
+        Ok(AlternateScreen)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        write!(stdout, "\x1b[r\x1b[?25h\x1b[?1049l").ok();
+        stdout.flush().ok();
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Pane {
+    Projects,
+    #[default]
+    Instances,
+    Disks,
+    Sagas,
+}
+
+impl Pane {
+    const ALL: [Pane; 4] = [Pane::Projects, Pane::Instances, Pane::Disks, Pane::Sagas];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Pane::Projects => "Projects",
+            Pane::Instances => "Instances",
+            Pane::Disks => "Disks",
+            Pane::Sagas => "Sagas",
+        }
+    }
+
+    fn headers(&self) -> &'static [&'static str] {
+        match self {
+            Pane::Projects => &["name"],
+            Pane::Instances => &["name", "state", "ncpus", "memory", "hostname"],
+            Pane::Disks => &["name", "state", "size", "instance"],
+            Pane::Sagas => &["id", "state", "error"],
+        }
+    }
+
+    /// The column holding the state of the rows, colored by `state_color`.
+    fn state_column(&self) -> Option<usize> {
+        match self {
+            Pane::Projects => None,
+            _ => Some(1),
+        }
+    }
+
+    fn index(&self) -> usize {
+        Pane::ALL.iter().position(|p| p == self).unwrap()
+    }
+
+    fn next(&self) -> Pane {
+        Pane::ALL[(self.index() + 1) % Pane::ALL.len()]
+    }
+
+    fn prev(&self) -> Pane {
+        Pane::ALL[(self.index() + Pane::ALL.len() - 1) % Pane::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    Up,
+    Down,
+    Tab,
+    BackTab,
+    Enter,
+    CtrlC,
+    Char(char),
+}
+
+/// Parse the keys in a chunk of input read from a terminal in raw mode.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\x1b' if i + 2 < bytes.len() && (bytes[i + 1] == b'[' || bytes[i + 1] == b'O') => {
+                match bytes[i + 2] {
+                    b'A' => keys.push(Key::Up),
+                    b'B' => keys.push(Key::Down),
+                    b'Z' => keys.push(Key::BackTab),
+                    _ => {}
+                }
+                i += 3;
+                continue;
+            }
+            b'\t' => keys.push(Key::Tab),
+            b'\r' | b'\n' => keys.push(Key::Enter),
+            b'\x03' => keys.push(Key::CtrlC),
+            c if c.is_ascii_graphic() => keys.push(Key::Char(c as char)),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    keys
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, parse_display::Display)]
+#[display(style = "lowercase")]
+enum InstanceAction {
+    Start,
+    Stop,
+    Reboot,
+}
+
+/// What the dashboard needs to do after a key press.
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    None,
+    Quit,
+    Refresh,
+    Instance(InstanceAction, String),
+    Console(String),
+}
+
+/// Everything shown on the dashboard, as rows of already formatted cells.
+#[derive(Debug, Default)]
+struct Dashboard {
+    organization: String,
+    project: String,
+    rows: [Vec<Vec<String>>; 4],
+    selected: [usize; 4],
+    focus: Pane,
+    confirm: Option<(InstanceAction, String)>,
+    status: String,
+    refreshed: String,
+}
+
+impl Dashboard {
+    fn new(organization: &str, project: &str) -> Self {
+        Dashboard {
+            organization: organization.to_string(),
+            project: project.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn rows(&self, pane: Pane) -> &[Vec<String>] {
+        &self.rows[pane.index()]
+    }
+
+    /// The first cell, the name or ID, of the selected row of a pane.
+    fn selected(&self, pane: Pane) -> Option<&str> {
+        self.rows(pane)
+            .get(self.selected[pane.index()])
+            .and_then(|row| row.first())
+            .map(|s| s.as_str())
+    }
+
+    /// Replace the rows of a pane, keeping the same row selected if it is still there.
+    fn set_rows(&mut self, pane: Pane, rows: Vec<Vec<String>>) {
+        let i = pane.index();
+        let selected = self.selected(pane).map(|s| s.to_string());
+        self.selected[i] = match selected.and_then(|s| rows.iter().position(|row| row.first() == Some(&s))) {
+            Some(position) => position,
+            None => self.selected[i].min(rows.len().saturating_sub(1)),
+        };
+        self.rows[i] = rows;
+    }
+
+    async fn refresh(&mut self, client: &oxide_api::Client) -> Result<()> {
+        let projects = client
+            .projects()
+            .get_all(&self.organization, Default::default())
+            .await?;
+        self.set_rows(
+            Pane::Projects,
+            projects.iter().map(|p| vec![p.name.to_string()]).collect(),
+        );
+
+        if self.project.is_empty() {
+            if let Some(project) = projects.first() {
+                self.project = project.name.to_string();
+            }
+        }
+        if let Some(i) = projects.iter().position(|p| p.name == self.project) {
+            if self.focus != Pane::Projects {
+                self.selected[Pane::Projects.index()] = i;
+            }
+        }
+
+        let mut instance_names = HashMap::new();
+        let mut instances = Vec::new();
+        let mut disks = Vec::new();
+        if !self.project.is_empty() {
+            for instance in client
+                .instances()
+                .get_all(&self.organization, &self.project, Default::default())
+                .await?
+            {
+                instance_names.insert(instance.id.to_string(), instance.name.to_string());
+                instances.push(vec![
+                    instance.name.to_string(),
+                    instance.run_state.to_string(),
+                    instance.ncpus.to_string(),
+                    crate::progress::format_bytes(instance.memory as u128),
+                    instance.hostname.to_string(),
+                ]);
+            }
+
+            for disk in client
+                .disks()
+                .get_all(&self.organization, &self.project, Default::default())
+                .await?
+            {
+                let state = serde_json::to_value(&disk.state)?;
+                let attached = match state.get("instance").and_then(|id| id.as_str()) {
+                    Some(id) => instance_names.get(id).cloned().unwrap_or_else(|| id.to_string()),
+                    None => "".to_string(),
+                };
+
+                disks.push(vec![
+                    disk.name.to_string(),
+                    crate::cmd_wait::disk_state(&disk.state)?,
+                    crate::progress::format_bytes(disk.size as u128),
+                    attached,
+                ]);
+            }
+        }
+        self.set_rows(Pane::Instances, instances);
+        self.set_rows(Pane::Disks, disks);
+
+        let sagas = client.sagas().get_page(SAGA_LIMIT, "", Default::default()).await?;
+        let mut rows = Vec::new();
+        for saga in sagas {
+            let state = serde_json::to_value(&saga.state)?;
+            let field = |name: &str| state.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            rows.push(vec![saga.id.to_string(), field("state"), field("error_node_name")]);
+        }
+        self.set_rows(Pane::Sagas, rows);
+
+        self.refreshed = chrono::Local::now().format("%H:%M:%S").to_string();
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: Key) -> Action {
+        // Stopping or rebooting an instance needs to be confirmed.
+        if let Some((action, instance)) = self.confirm.take() {
+            if key == Key::Char('y') {
+                return Action::Instance(action, instance);
+            }
+
+            self.status = format!("Did not {} instance {}", action, instance);
+            return Action::None;
+        }
+
+        self.status = "".to_string();
+        let focus = self.focus;
+        let selected = &mut self.selected[focus.index()];
+        match key {
+            Key::Char('q') | Key::CtrlC => return Action::Quit,
+            Key::Char('r') => return Action::Refresh,
+            Key::Tab => self.focus = focus.next(),
+            Key::BackTab => self.focus = focus.prev(),
+            Key::Up | Key::Char('k') => *selected = selected.saturating_sub(1),
+            Key::Down | Key::Char('j') if *selected + 1 < self.rows[focus.index()].len() => *selected += 1,
+            Key::Enter if focus == Pane::Projects => {
+                if let Some(project) = self.selected(Pane::Projects) {
+                    self.project = project.to_string();
+                    self.selected[Pane::Instances.index()] = 0;
+                    self.selected[Pane::Disks.index()] = 0;
+                    self.focus = Pane::Instances;
+                    return Action::Refresh;
+                }
+            }
+            Key::Char(c @ ('s' | 'x' | 'b' | 'c')) => {
+                let instance = match self.selected(Pane::Instances) {
+                    Some(instance) if focus == Pane::Instances => instance.to_string(),
+                    _ => {
+                        self.status = "Select an instance first".to_string();
+                        return Action::None;
+                    }
+                };
+
+                match c {
+                    's' => return Action::Instance(InstanceAction::Start, instance),
+                    'c' => return Action::Console(instance),
+                    _ => {
+                        let action = if c == 'x' {
+                            InstanceAction::Stop
+                        } else {
+                            InstanceAction::Reboot
+                        };
+                        self.status = format!("Press y to {} instance {}", action, instance);
+                        self.confirm = Some((action, instance));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Action::None
+    }
+
+    /// Render the dashboard as `height` lines of at most `width` characters.
+    fn render(&self, cs: &crate::colors::ColorScheme, width: usize, height: usize) -> Vec<String> {
+        let mut lines = Vec::with_capacity(height);
+
+        let title = format!(
+            "Oxide · {} · {} · refreshed {}",
+            self.organization,
+            if self.project.is_empty() {
+                "no project"
+            } else {
+                self.project.as_str()
+            },
+            if self.refreshed.is_empty() {
+                "never"
+            } else {
+                self.refreshed.as_str()
+            }
+        );
+        lines.push(cs.bold(&fit(&title, width)));
+
+        let body = height.saturating_sub(2);
+        let left = PROJECTS_WIDTH.min(width / 4);
+        let right = width.saturating_sub(left + 3);
+
+        let projects = self.render_pane(cs, Pane::Projects, left, body);
+        let mut panes = Vec::with_capacity(body);
+        let third = body / 3;
+        panes.extend(self.render_pane(cs, Pane::Instances, right, body - 2 * third));
+        panes.extend(self.render_pane(cs, Pane::Disks, right, third));
+        panes.extend(self.render_pane(cs, Pane::Sagas, right, third));
+
+        for (l, r) in projects.into_iter().zip(panes) {
+            lines.push(format!("{} {} {}", l, cs.gray("│"), r));
+        }
+
+        let footer = if self.status.is_empty() {
+            cs.gray(&fit(
+                "tab: pane  ↑↓: select  enter: open project  s/x/b: start/stop/reboot  c: console  r: refresh  q: quit",
+                width,
+            ))
+        } else {
+            // The status might be colored already, so it is not truncated.
+            self.status.to_string()
+        };
+        if height > 1 {
+            lines.push(footer);
+        }
+
+        lines.truncate(height);
+        lines
+    }
+
+    /// Render a pane as `height` lines of `width` characters: a title, the column
+    /// headers and as many rows as fit, scrolled to keep the selected row visible.
+    fn render_pane(&self, cs: &crate::colors::ColorScheme, pane: Pane, width: usize, height: usize) -> Vec<String> {
+        let rows = self.rows(pane);
+        let focused = self.focus == pane;
+        let mut lines = Vec::with_capacity(height);
+
+        let title = fit(&format!("{} ({})", pane.title(), rows.len()), width);
+        lines.push(if focused {
+            cs.cyan(&cs.bold(&title))
+        } else {
+            cs.bold(&title)
+        });
+
+        // Leave room for the selection marker in front of every row.
+        let widths = column_widths(pane.headers(), rows, width.saturating_sub(2));
+        let format_row = |cells: &[String], marker: &str, state: Option<usize>| {
+            let mut line = marker.to_string();
+            for (i, w) in widths.iter().enumerate() {
+                let cell = fit(cells.get(i).map(|s| s.as_str()).unwrap_or_default(), *w);
+                line.push_str(&if state == Some(i) { state_color(cs, &cell) } else { cell });
+                if i + 1 < widths.len() {
+                    line.push_str("  ");
+                }
+            }
+            line.push_str(&" ".repeat(width.saturating_sub(2 + widths.iter().sum::<usize>() + 2 * (widths.len() - 1))));
+            line
+        };
+
+        let headers: Vec<String> = pane.headers().iter().map(|h| h.to_uppercase()).collect();
+        lines.push(cs.gray(&format_row(&headers, "  ", None)));
+
+        let visible = height.saturating_sub(2);
+        let selected = self.selected[pane.index()];
+        let offset = (selected + 1).saturating_sub(visible);
+        for (i, row) in rows.iter().enumerate().skip(offset).take(visible) {
+            if i == selected && focused {
+                lines.push(cs.bold(&format_row(row, "› ", pane.state_column())));
+            } else {
+                lines.push(format_row(row, "  ", pane.state_column()));
+            }
+        }
+
+        while lines.len() < height {
+            lines.push(" ".repeat(width));
+        }
+        lines.truncate(height);
+        lines
+    }
+}
+
+/// The width of every column, shrinking the widest columns until they fit in `width`
+/// with two spaces between columns.
+fn column_widths(headers: &[&str], rows: &[Vec<String>], width: usize) -> Vec<usize> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+
+    let gaps = 2 * headers.len().saturating_sub(1);
+    while widths.iter().sum::<usize>() + gaps > width {
+        let (i, w) = widths
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, w)| **w)
+            .map(|(i, w)| (i, *w))
+            .unwrap_or_default();
+        if w <= 1 {
+            break;
+        }
+
+        widths[i] -= 1;
+    }
+
+    widths
+}
+
+/// Pad or truncate `s` to exactly `width` characters.
+fn fit(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    if len > width {
+        s.chars()
+            .take(width.saturating_sub(1))
+            .chain(std::iter::once('…'))
+            .take(width)
+            .collect()
+    } else {
+        format!("{}{}", s, " ".repeat(width - len))
+    }
+}
+
+fn state_color(cs: &crate::colors::ColorScheme, state: &str) -> String {
+    match state.trim() {
+        "running" | "attached" | "succeeded" => cs.green(state),
+        "failed" | "faulted" | "destroyed" => cs.red(state),
+        "stopped" | "detached" => state.to_string(),
+        _ => cs.yellow(state),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::cmd::Command;
+
+    fn dashboard() -> Dashboard {
+        let mut dashboard = Dashboard::new("maze-war", "prod");
+        dashboard.set_rows(Pane::Projects, vec![vec!["dev".to_string()], vec!["prod".to_string()]]);
+        dashboard.set_rows(
+            Pane::Instances,
+            vec![
+                vec![
+                    "db".to_string(),
+                    "running".to_string(),
+                    "4".to_string(),
+                    "8.00 GiB".to_string(),
+                    "db".to_string(),
+                ],
+                vec![
+                    "web".to_string(),
+                    "stopped".to_string(),
+                    "2".to_string(),
+                    "2.00 GiB".to_string(),
+                    "web".to_string(),
+                ],
+            ],
+        );
+        dashboard.refreshed = "12:00:00".to_string();
+        dashboard
+    }
+
+    #[test]
+    fn test_dashboard_parse_keys() {
+        assert_eq!(
+            parse_keys(b"j\x1b[A\x1b[B\tq\r\x03\x1b[Z\x01"),
+            vec![
+                Key::Char('j'),
+                Key::Up,
+                Key::Down,
+                Key::Tab,
+                Key::Char('q'),
+                Key::Enter,
+                Key::CtrlC,
+                Key::BackTab,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dashboard_handle_key() {
+        let mut dashboard = dashboard();
+
+        assert_eq!(dashboard.selected(Pane::Instances), Some("db"));
+        assert_eq!(dashboard.handle_key(Key::Down), Action::None);
+        assert_eq!(dashboard.handle_key(Key::Down), Action::None);
+        assert_eq!(dashboard.selected(Pane::Instances), Some("web"));
+
+        assert_eq!(
+            dashboard.handle_key(Key::Char('s')),
+            Action::Instance(InstanceAction::Start, "web".to_string())
+        );
+
+        // Stopping needs to be confirmed.
+        assert_eq!(dashboard.handle_key(Key::Char('x')), Action::None);
+        assert_eq!(dashboard.status, "Press y to stop instance web");
+        assert_eq!(
+            dashboard.handle_key(Key::Char('y')),
+            Action::Instance(InstanceAction::Stop, "web".to_string())
+        );
+        assert_eq!(dashboard.handle_key(Key::Char('b')), Action::None);
+        assert_eq!(dashboard.handle_key(Key::Char('n')), Action::None);
+        assert_eq!(dashboard.status, "Did not reboot instance web");
+
+        assert_eq!(dashboard.handle_key(Key::Char('c')), Action::Console("web".to_string()));
+
+        // Opening another project.
+        assert_eq!(dashboard.handle_key(Key::BackTab), Action::None);
+        assert_eq!(dashboard.focus, Pane::Projects);
+        assert_eq!(dashboard.handle_key(Key::Char('c')), Action::None);
+        assert_eq!(dashboard.status, "Select an instance first");
+        assert_eq!(dashboard.handle_key(Key::Up), Action::None);
+        assert_eq!(dashboard.handle_key(Key::Enter), Action::Refresh);
+        assert_eq!(dashboard.project, "dev");
+        assert_eq!(dashboard.focus, Pane::Instances);
+        assert_eq!(dashboard.selected(Pane::Instances), Some("db"));
+
+        assert_eq!(dashboard.handle_key(Key::Char('q')), Action::Quit);
+    }
+
+    #[test]
+    fn test_dashboard_set_rows_keeps_selection() {
+        let mut dashboard = dashboard();
+        dashboard.handle_key(Key::Down);
+
+        dashboard.set_rows(
+            Pane::Instances,
+            vec![vec!["api".to_string()], vec!["db".to_string()], vec!["web".to_string()]],
+        );
+        assert_eq!(dashboard.selected(Pane::Instances), Some("web"));
+
+        dashboard.set_rows(Pane::Instances, vec![vec!["api".to_string()]]);
+        assert_eq!(dashboard.selected(Pane::Instances), Some("api"));
+    }
+
+    #[test]
+    fn test_dashboard_render() {
+        let cs = crate::colors::ColorScheme::new(false, false, false);
+        let lines = dashboard().render(&cs, 80, 12);
+
+        assert_eq!(lines.len(), 12);
+        for line in &lines[..11] {
+            assert_eq!(line.chars().count(), 80, "{:?}", line);
+        }
+
+        assert_eq!(lines[0].trim_end(), "Oxide · maze-war · prod · refreshed 12:00:00");
+        assert_eq!(lines[1].trim_end(), "Projects (2)         │ Instances (2)");
+        assert_eq!(
+            lines[2].trim_end(),
+            "  NAME               │   NAME  STATE    NCPUS  MEMORY    HOSTNAME"
+        );
+        assert_eq!(
+            lines[3].trim_end(),
+            "  dev                │ › db    running  4      8.00 GiB  db"
+        );
+        assert_eq!(
+            lines[4].trim_end(),
+            "  prod               │   web   stopped  2      2.00 GiB  web"
+        );
+        assert!(lines[11].starts_with("tab: pane"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cmd_dashboard_requires_tty() {
+        let cmd = CmdDashboard {
+            project: "".to_string(),
+            organization: "maze-war".to_string(),
+            refresh: Duration::from_secs(5),
+        };
+
+        let mut config = crate::config::new_blank_config().unwrap();
+        let mut c = crate::config_from_env::EnvConfig::inherit_env(&mut config);
+        let (io, _stdout_path, _stderr_path) = crate::iostreams::IoStreams::test();
+        let mut ctx = crate::context::Context {
+            config: &mut c,
+            io,
+            debug: false,
+        };
+
+        let err = cmd.run(&mut ctx).await.unwrap_err();
+        assert_eq!(err.to_string(), "Stdout must be a TTY to use the dashboard.");
+    }
+}
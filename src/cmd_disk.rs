@@ -1,6 +1,6 @@
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
 use cli_macro::crud_gen;
 
@@ -39,18 +39,19 @@ impl crate::cmd::Command for CmdDisk {
     }
 }
 
-// TODO: on attach we could do it interactively and list the instances in the project
-// as a selection list.
 /// Attach a disk to an instance.
+///
+/// Without a disk or an instance, pick them from the detached disks and the stopped
+/// instances of the project.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
 pub struct CmdDiskAttach {
     /// The disk to attach. Can be an ID or name.
-    #[clap(name = "disk", required = true)]
+    #[clap(name = "disk", default_value = "")]
     disk: String,
 
     /// The instance to attach the disk to. Can be an ID or name.
-    #[clap(name = "instance", required = true)]
+    #[clap(name = "instance", default_value = "")]
     instance: String,
 
     /// The project that holds the disk and instance.
@@ -60,35 +61,103 @@ pub struct CmdDiskAttach {
     /// The organization that holds the project.
     #[clap(long, short, required = true, env = "OXIDE_ORG")]
     pub organization: String,
+
+    /// Wait for the disk to be attached.
+    #[clap(long)]
+    pub wait: bool,
+
+    /// How long to wait for the disk to be attached, e.g. `90s` or `5m`. Use `0` to
+    /// wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check the state of the disk, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
 }
 
 #[async_trait::async_trait]
 impl crate::cmd::Command for CmdDiskAttach {
     async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        if (self.disk.is_empty() || self.instance.is_empty()) && !ctx.io.can_prompt() {
+            return Err(anyhow!("[disk] and [instance] required in non-interactive mode"));
+        }
+
         let client = ctx.api_client("")?;
 
         let full_name = format!("{}/{}", self.organization, self.project);
 
+        let disk = if self.disk.is_empty() {
+            let mut disks = Vec::new();
+            for disk in client
+                .disks()
+                .get_all(&self.organization, &self.project, Default::default())
+                .await?
+            {
+                if crate::cmd_wait::disk_state(&disk.state)? == "detached" {
+                    disks.push(disk.name.to_string());
+                }
+            }
+            let index = select("Select a disk to attach:", &disks, "detached disks", &full_name)?;
+            disks.swap_remove(index)
+        } else {
+            self.disk.to_string()
+        };
+
+        let instance = if self.instance.is_empty() {
+            let mut instances = client
+                .instances()
+                .get_all(&self.organization, &self.project, Default::default())
+                .await?
+                .into_iter()
+                .filter(|instance| instance.run_state.to_string() == "stopped")
+                .map(|instance| instance.name.to_string())
+                .collect::<Vec<_>>();
+            let index = select(
+                &format!("Select an instance to attach {} to:", disk),
+                &instances,
+                "stopped instances",
+                &full_name,
+            )?;
+            instances.swap_remove(index)
+        } else {
+            self.instance.to_string()
+        };
+
         // Attach the disk.
         client
             .instances()
             .disks_attach(
-                &self.instance,
+                &instance,
+                &self.organization,
+                &self.project,
+                &oxide_api::types::DiskIdentifier { name: disk.to_string() },
+            )
+            .await?;
+
+        if self.wait {
+            wait_for_disk_state(
+                ctx,
                 &self.organization,
                 &self.project,
-                &oxide_api::types::DiskIdentifier {
-                    name: self.disk.to_string(),
+                &disk,
+                "attached",
+                &crate::wait::WaitOptions {
+                    timeout: self.timeout,
+                    poll_interval: self.poll_interval,
                 },
             )
             .await?;
+        }
 
         let cs = ctx.io.color_scheme();
         writeln!(
             ctx.io.out,
             "{} Attached disk {} to instance {} in project {}",
             cs.success_icon(),
-            self.disk,
-            self.instance,
+            disk,
+            instance,
             full_name
         )?;
 
@@ -97,15 +166,19 @@ impl crate::cmd::Command for CmdDiskAttach {
 }
 
 /// Detach a disk from an instance.
+///
+/// Without a disk, pick it from the disks attached to the instance, or to any
+/// instance of the project. Without an instance, the disk is detached from the
+/// instance it is attached to.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
 pub struct CmdDiskDetach {
     /// The disk to detach. Can be an ID or name.
-    #[clap(name = "disk", required = true)]
+    #[clap(name = "disk", default_value = "")]
     disk: String,
 
     /// The instance to detach the disk from. Can be an ID or name.
-    #[clap(name = "instance", required = true)]
+    #[clap(name = "instance", default_value = "")]
     instance: String,
 
     /// The project that holds the disk and instance.
@@ -115,35 +188,124 @@ pub struct CmdDiskDetach {
     /// The organization that holds the project.
     #[clap(long, short, required = true, env = "OXIDE_ORG")]
     pub organization: String,
+
+    /// Wait for the disk to be detached.
+    #[clap(long)]
+    pub wait: bool,
+
+    /// How long to wait for the disk to be detached, e.g. `90s` or `5m`. Use `0` to
+    /// wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check the state of the disk, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
 }
 
 #[async_trait::async_trait]
 impl crate::cmd::Command for CmdDiskDetach {
     async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        if self.disk.is_empty() && !ctx.io.can_prompt() {
+            return Err(anyhow!("[disk] required in non-interactive mode"));
+        }
+
         let client = ctx.api_client("")?;
 
         let full_name = format!("{}/{}", self.organization, self.project);
 
+        let mut instances = HashMap::new();
+        if self.disk.is_empty() || self.instance.is_empty() {
+            for instance in client
+                .instances()
+                .get_all(&self.organization, &self.project, Default::default())
+                .await?
+            {
+                instances.insert(instance.id.to_string(), instance.name.to_string());
+            }
+        }
+
+        let disk = if self.disk.is_empty() {
+            let mut disks = Vec::new();
+            let mut labels = Vec::new();
+            for disk in client
+                .disks()
+                .get_all(&self.organization, &self.project, Default::default())
+                .await?
+            {
+                if crate::cmd_wait::disk_state(&disk.state)? != "attached" {
+                    continue;
+                }
+                let attached_to = attached_instance(&disk.state)?.and_then(|id| Some((instances.get(&id)?, id)));
+                match attached_to {
+                    Some((name, id)) if self.instance.is_empty() || *name == self.instance || id == self.instance => {
+                        labels.push(format!("{} (attached to {})", disk.name, name));
+                        disks.push(disk.name.to_string());
+                    }
+                    _ => {}
+                }
+            }
+            let index = select("Select a disk to detach:", &labels, "attached disks", &full_name)?;
+            disks.swap_remove(index)
+        } else {
+            self.disk.to_string()
+        };
+
+        let instance = if self.instance.is_empty() {
+            let state = client
+                .disks()
+                .get(&disk, &self.organization, &self.project)
+                .await?
+                .state;
+            match attached_instance(&state)?.and_then(|id| instances.remove(&id)) {
+                Some(name) => name,
+                None => {
+                    return Err(anyhow!(
+                        "disk {} is `{}`, it is not attached to an instance of {}",
+                        disk,
+                        crate::cmd_wait::disk_state(&state)?,
+                        full_name
+                    ))
+                }
+            }
+        } else {
+            self.instance.to_string()
+        };
+
         // Detach the disk.
         client
             .instances()
             .disks_detach(
-                &self.instance,
+                &instance,
                 &self.organization,
                 &self.project,
-                &oxide_api::types::DiskIdentifier {
-                    name: self.disk.to_string(),
+                &oxide_api::types::DiskIdentifier { name: disk.to_string() },
+            )
+            .await?;
+
+        if self.wait {
+            wait_for_disk_state(
+                ctx,
+                &self.organization,
+                &self.project,
+                &disk,
+                "detached",
+                &crate::wait::WaitOptions {
+                    timeout: self.timeout,
+                    poll_interval: self.poll_interval,
                 },
             )
             .await?;
+        }
 
         let cs = ctx.io.color_scheme();
         writeln!(
             ctx.io.out,
             "{} Detached disk {} from instance {} in project {}",
             cs.success_icon_with_color(ansi_term::Color::Red),
-            self.disk,
-            self.instance,
+            disk,
+            instance,
             full_name
         )?;
 
@@ -151,18 +313,209 @@ impl crate::cmd::Command for CmdDiskDetach {
     }
 }
 
-/// Edit disk settings.
+/// Pick one of `items`, which are `what` in the project `full_name`, returning its
+/// index.
+fn select(prompt: &str, items: &[String], what: &str, full_name: &str) -> Result<usize> {
+    if items.is_empty() {
+        return Err(anyhow!("there are no {} in {}", what, full_name));
+    }
+
+    dialoguer::Select::new()
+        .with_prompt(prompt)
+        .items(items)
+        .interact()
+        .map_err(|err| anyhow!("prompt failed: {}", err))
+}
+
+/// The ID of the instance a disk in `state` is attached to, if any.
+fn attached_instance(state: &oxide_api::types::DiskState) -> Result<Option<String>> {
+    let value = serde_json::to_value(state)?;
+    Ok(value
+        .get("instance")
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string()))
+}
+
+/// Preview edits to disk settings.
+///
+/// The settings of the disk are opened as YAML in the editor, see `oxide config set
+/// editor`, and the ones that were changed are printed.
+///
+/// The Oxide API cannot update disks yet, so nothing is changed on the rack, and
+/// the command fails once it printed the changes.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
-pub struct CmdDiskEdit {}
+pub struct CmdDiskEdit {
+    /// The disk to edit. Can be an ID or name.
+    #[clap(name = "disk", required = true)]
+    pub disk: String,
+
+    /// The project that holds the disk.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+}
 
 #[async_trait::async_trait]
 impl crate::cmd::Command for CmdDiskEdit {
-    async fn run(&self, _ctx: &mut crate::context::Context) -> Result<()> {
-        todo!()
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let client = ctx.api_client("")?;
+
+        let disk = client
+            .disks()
+            .get(&self.disk, &self.organization, &self.project)
+            .await?;
+        let fields = serde_json::from_value(serde_json::json!({
+            "description": disk.description,
+            "size": disk.size,
+        }))?;
+
+        let changes = crate::edit::edit_fields(
+            ctx,
+            &format!(
+                "Preview edits to disk {} in {}/{}.\n\
+                 The Oxide API cannot update disks yet, so the changed fields are only printed.",
+                self.disk, self.organization, self.project
+            ),
+            &fields,
+        )?;
+        if changes.is_empty() {
+            writeln!(ctx.io.out, "No changes to disk {}", self.disk)?;
+            return Ok(());
+        }
+
+        writeln!(ctx.io.out, "Disk {} would change:", self.disk)?;
+        write!(ctx.io.out, "{}", crate::edit::preview(&fields, &changes))?;
+
+        // Until the API can update disks, make sure scripts do not take this for success.
+        Err(anyhow!("the Oxide API cannot update disks yet, nothing was changed"))
     }
 }
 
+/// The source of a new disk, given with `--from-image`, `--from-snapshot` or `--blank`,
+/// and the sizes it allows.
+#[derive(Debug, Clone)]
+pub struct SizedDiskSource {
+    pub source: oxide_api::types::DiskSource,
+    /// The size of the image or snapshot, the disk cannot be smaller.
+    pub min_size: oxide_api::types::ByteCount,
+    /// The size of the disk must be a multiple of this, when we know it.
+    pub block_size: Option<oxide_api::types::ByteCount>,
+}
+
+impl SizedDiskSource {
+    pub async fn resolve(
+        ctx: &mut crate::context::Context<'_>,
+        organization: &str,
+        project: &str,
+        from_image: Option<&str>,
+        from_snapshot: Option<&str>,
+        blank: Option<oxide_api::types::BlockSize>,
+    ) -> Result<Option<Self>> {
+        if let Some(block_size) = blank {
+            return Ok(Some(SizedDiskSource {
+                source: oxide_api::types::DiskSource::Blank { block_size },
+                min_size: 0,
+                block_size: Some(block_size),
+            }));
+        }
+
+        if from_image.is_none() && from_snapshot.is_none() {
+            return Ok(None);
+        }
+        let client = ctx.api_client("")?;
+
+        if let Some(image) = from_image {
+            let image = client.images().get(image, organization, project).await?;
+            return Ok(Some(SizedDiskSource {
+                source: oxide_api::types::DiskSource::Image {
+                    image_id: image.id.to_string(),
+                },
+                min_size: image.size,
+                block_size: Some(image.block_size),
+            }));
+        }
+
+        if let Some(snapshot) = from_snapshot {
+            let snapshot = client.snapshots().get(organization, project, snapshot).await?;
+            // Snapshots have the block size of their disk, if it is still around.
+            let block_size = client
+                .disks()
+                .get_all(organization, project, Default::default())
+                .await?
+                .into_iter()
+                .find(|disk| disk.id == snapshot.disk_id)
+                .map(|disk| disk.block_size);
+            return Ok(Some(SizedDiskSource {
+                source: oxide_api::types::DiskSource::Snapshot {
+                    snapshot_id: snapshot.id.to_string(),
+                },
+                min_size: snapshot.size,
+                block_size,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Check that a disk of `size` bytes can be created from this source.
+    pub fn check_size(&self, size: oxide_api::types::ByteCount) -> Result<()> {
+        if size < self.min_size {
+            return Err(anyhow!(
+                "the disk cannot be smaller than its source, {} ({} bytes)",
+                crate::progress::format_bytes(self.min_size as u128),
+                self.min_size
+            ));
+        }
+
+        if let Some(block_size) = self.block_size {
+            if block_size > 0 && size % block_size != 0 {
+                return Err(anyhow!(
+                    "the size of the disk, {} bytes, must be a multiple of the block size, {} bytes",
+                    size,
+                    block_size
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wait for a disk to be in `state`, e.g. `detached` once it is created.
+pub async fn wait_for_disk_state(
+    ctx: &mut crate::context::Context<'_>,
+    organization: &str,
+    project: &str,
+    disk: &str,
+    state: &str,
+    opts: &crate::wait::WaitOptions,
+) -> Result<()> {
+    let client = ctx.api_client("")?;
+    let client = &client;
+    crate::wait::poll_with_spinner(
+        ctx,
+        opts,
+        &format!("disk {} to be `{}`", disk, state),
+        &format!("Disk {} is `{}`", disk, state),
+        || async move {
+            let current = crate::cmd_wait::disk_state(&client.disks().get(disk, organization, project).await?.state)?;
+
+            Ok(if current == state {
+                crate::wait::Poll::Done
+            } else if current == "faulted" || current == "destroyed" {
+                crate::wait::Poll::Failed(format!("disk {} is `{}`, it will never be `{}`", disk, current, state))
+            } else {
+                crate::wait::Poll::Pending(current)
+            })
+        },
+    )
+    .await
+}
+
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
@@ -193,6 +546,10 @@ mod test {
                     description: "hi hi".to_string(),
                     size: Default::default(),
                     disk_source: disk_source.clone(),
+                    from_image: None,
+                    from_snapshot: None,
+                    blank: false,
+                    block_size: 512,
                 }),
 
                 stdin: "".to_string(),
@@ -208,6 +565,10 @@ mod test {
                     description: "foo bar".to_string(),
                     size: Default::default(),
                     disk_source: disk_source.clone(),
+                    from_image: None,
+                    from_snapshot: None,
+                    blank: false,
+                    block_size: 512,
                 }),
 
                 stdin: "".to_string(),
@@ -223,6 +584,10 @@ mod test {
                     description: "balla".to_string(),
                     size: Default::default(),
                     disk_source: disk_source.clone(),
+                    from_image: None,
+                    from_snapshot: None,
+                    blank: false,
+                    block_size: 512,
                 }),
 
                 stdin: "".to_string(),
@@ -238,6 +603,10 @@ mod test {
                     description: "".to_string(),
                     size: Default::default(),
                     disk_source: disk_source.clone(),
+                    from_image: None,
+                    from_snapshot: None,
+                    blank: false,
+                    block_size: 512,
                 }),
 
                 stdin: "".to_string(),
@@ -253,6 +622,10 @@ mod test {
                     description: "blah blah".to_string(),
                     size: Default::default(),
                     disk_source: disk_source.clone(),
+                    from_image: None,
+                    from_snapshot: None,
+                    blank: false,
+                    block_size: 512,
                 }),
 
                 stdin: "".to_string(),
@@ -277,9 +650,13 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_disk::SubCommand::Delete(crate::cmd_disk::CmdDiskDelete {
-                    disk: "things".to_string(),
+                    disk: vec!["things".to_string()],
                     organization: "".to_string(),
                     project: "".to_string(),
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
                     confirm: false,
                 }),
 
@@ -295,6 +672,7 @@ mod test {
                     project: "".to_string(),
                     paginate: false,
                     format: None,
+                    watch: None,
                     sort_by: Default::default(),
                 }),
 
@@ -344,4 +722,25 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_check_size() {
+        let source = crate::cmd_disk::SizedDiskSource {
+            source: oxide_api::types::DiskSource::Image {
+                image_id: "some image".to_string(),
+            },
+            min_size: 2 * 1024 * 1024 * 1024,
+            block_size: Some(4096),
+        };
+
+        assert!(source.check_size(20 * 1024 * 1024 * 1024).is_ok());
+        assert_eq!(
+            source.check_size(1024 * 1024 * 1024).unwrap_err().to_string(),
+            "the disk cannot be smaller than its source, 2.00 GiB (2147483648 bytes)"
+        );
+        assert_eq!(
+            source.check_size(2 * 1024 * 1024 * 1024 + 512).unwrap_err().to_string(),
+            "the size of the disk, 2147484160 bytes, must be a multiple of the block size, 4096 bytes"
+        );
+    }
 }
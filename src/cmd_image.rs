@@ -1,4 +1,6 @@
-use anyhow::Result;
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
 use cli_macro::crud_gen;
 
@@ -16,6 +18,7 @@ pub struct CmdImage {
 #[derive(Parser, Debug, Clone)]
 enum SubCommand {
     Global(crate::cmd_image_global::CmdImageGlobal),
+    Import(CmdImageImport),
 }
 
 #[async_trait::async_trait]
@@ -27,6 +30,227 @@ impl crate::cmd::Command for CmdImage {
             SubCommand::List(cmd) => cmd.run(ctx).await,
             SubCommand::View(cmd) => cmd.run(ctx).await,
             SubCommand::Global(cmd) => cmd.run(ctx).await,
+            SubCommand::Import(cmd) => cmd.run(ctx).await,
         }
     }
 }
+
+/// Import a disk image from a local file.
+///
+/// The rack creates images from URLs, so the file is served over HTTP from this
+/// machine until the rack has downloaded all of it: the rack must be able to reach
+/// this machine at `--address`.
+///
+/// Images are raw disk images, qcow2 images are converted to raw images with
+/// `qemu-img` first.
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdImageImport {
+    /// The disk image to import, a raw or qcow2 image.
+    #[clap(name = "file", required = true)]
+    pub file: std::path::PathBuf,
+
+    /// The name of the image to create. Defaults to the name of the file, without
+    /// its extension.
+    #[clap(long, short)]
+    pub name: Option<String>,
+
+    /// The project that holds the image.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The description for the image.
+    #[clap(long = "description", short = 'D', default_value = "")]
+    pub description: String,
+
+    /// The block size of the image in bytes.
+    #[clap(long, short, default_value = "512", possible_values = &["512", "2048", "4096"])]
+    pub block_size: oxide_api::types::BlockSize,
+
+    /// The SHA-256 checksum the file must have, e.g. the one published with a
+    /// downloaded image.
+    #[clap(long)]
+    pub sha256: Option<String>,
+
+    /// The address the rack can reach this machine at. Defaults to the address of
+    /// the network interface used to reach the Oxide API.
+    #[clap(long)]
+    pub address: Option<std::net::IpAddr>,
+
+    /// The port to serve the image on. Defaults to any free port.
+    #[clap(long, default_value_t = 0)]
+    pub port: u16,
+
+    /// How long to wait for the rack to download the image, e.g. `30m`. Use `0` to
+    /// wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdImageImport {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let name = match &self.name {
+            Some(name) => name.to_string(),
+            None => self
+                .file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .ok_or_else(|| anyhow!("{} is not a file", self.file.display()))?,
+        };
+
+        if let Some(sha256) = &self.sha256 {
+            let digest = checksum(ctx, &self.file)?;
+            if !digest.eq_ignore_ascii_case(sha256) {
+                bail!(
+                    "SHA-256 checksum mismatch: {} is {}, expected {}",
+                    self.file.display(),
+                    digest,
+                    sha256
+                );
+            }
+        }
+
+        let raw_copy = match crate::image::ImageFormat::detect(&self.file)? {
+            crate::image::ImageFormat::Raw => None,
+            crate::image::ImageFormat::Qcow2 => {
+                let handle = ctx
+                    .io
+                    .start_process_indicator_with_label(&format!(" Converting {} to a raw image", self.file.display()));
+                let raw_copy = crate::image::RawCopy::convert(&self.file).await;
+                if let Some(handle) = handle {
+                    handle.done();
+                }
+                Some(raw_copy?)
+            }
+        };
+        let raw = raw_copy.as_ref().map(|copy| copy.path()).unwrap_or(&self.file);
+
+        let size = std::fs::metadata(raw)?.len();
+        if size == 0 || size % self.block_size as u64 != 0 {
+            bail!(
+                "the image is {} bytes, which is not a multiple of the block size, {} bytes",
+                size,
+                self.block_size
+            );
+        }
+        // What the rack reports when it has the image, a qcow2 image has another one.
+        let digest = match (&self.sha256, &raw_copy) {
+            (Some(sha256), None) => sha256.to_lowercase(),
+            _ => checksum(ctx, raw)?,
+        };
+
+        let address = match self.address {
+            Some(address) => address,
+            None => crate::image::local_address(&ctx.config.default_host()?)?,
+        };
+        let listener = tokio::net::TcpListener::bind((address, self.port)).await?;
+        // Only the rack is told where the image is.
+        let url_path = format!("/{}/{}.raw", uuid::Uuid::new_v4().simple(), name);
+        let url = format!("http://{}{}", listener.local_addr()?, url_path);
+
+        let (served_tx, mut served_rx) = tokio::sync::watch::channel(0);
+        let server = tokio::spawn(crate::image::serve(
+            listener,
+            raw.to_path_buf(),
+            url_path,
+            size,
+            ctx.io.start_progress_bar("Uploading", Some(size)),
+            served_tx,
+        ));
+
+        let client = ctx.api_client("")?;
+        let image = client
+            .images()
+            .post(
+                &self.organization,
+                &self.project,
+                &oxide_api::types::ImageCreate {
+                    block_size: self.block_size,
+                    description: self.description.to_string(),
+                    name: name.to_string(),
+                    source: oxide_api::types::ImageSource::Url { url },
+                },
+            )
+            .await;
+        let image = match image {
+            Ok(image) => serde_json::to_value(image)?,
+            Err(err) => {
+                server.abort();
+                return Err(err.into());
+            }
+        };
+
+        let downloaded = async {
+            while *served_rx.borrow() < size {
+                if served_rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        };
+        let timed_out = if self.timeout.is_zero() {
+            downloaded.await;
+            false
+        } else {
+            tokio::time::timeout(self.timeout, downloaded).await.is_err()
+        };
+        server.abort();
+
+        // Either way the image is of no use, it points at a URL that no longer answers.
+        let failure = if timed_out {
+            Some(format!(
+                "timed out after {:?} waiting for the rack to download the image",
+                self.timeout
+            ))
+        } else {
+            match image["digest"]["value"].as_str() {
+                Some(reported) if image["digest"]["type"] == "sha256" && !reported.eq_ignore_ascii_case(&digest) => {
+                    Some(format!(
+                        "SHA-256 checksum mismatch: the rack got an image with {}, expected {}",
+                        reported, digest
+                    ))
+                }
+                _ => None,
+            }
+        };
+        if let Some(failure) = failure {
+            return match client.images().delete(&name, &self.organization, &self.project).await {
+                Ok(_) => Err(anyhow!("{}, the image {} was removed", failure, name)),
+                Err(err) => Err(anyhow!(
+                    "{}, and removing the image {} failed, remove it with `oxide image delete {}`: {}",
+                    failure,
+                    name,
+                    name,
+                    err
+                )),
+            };
+        }
+
+        let cs = ctx.io.color_scheme();
+        writeln!(
+            ctx.io.out,
+            "{} Imported image {} in {}/{}",
+            cs.success_icon(),
+            name,
+            self.organization,
+            self.project
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The SHA-256 checksum of a file, with a progress bar since images can be large.
+fn checksum(ctx: &mut crate::context::Context<'_>, path: &std::path::Path) -> Result<String> {
+    let file = std::fs::File::open(path).map_err(|e| anyhow!("opening {}: {}", path.display(), e))?;
+    let size = file.metadata()?.len();
+
+    crate::update::sha256_digest(crate::image::ProgressReader {
+        inner: std::io::BufReader::with_capacity(1024 * 1024, file),
+        progress: ctx.io.start_progress_bar("Checksumming", Some(size)),
+    })
+}
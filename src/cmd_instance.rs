@@ -1,4 +1,4 @@
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 
 use anyhow::{anyhow, Result};
 use clap::Parser;
@@ -19,9 +19,14 @@ pub struct CmdInstance {
 }]
 #[derive(Parser, Debug, Clone)]
 enum SubCommand {
+    Clone(CmdInstanceClone),
+    #[clap(alias = "scp")]
+    Cp(CmdInstanceCp),
     Disks(CmdInstanceDisks),
     Edit(CmdInstanceEdit),
+    Inventory(CmdInstanceInventory),
     Ssh(CmdInstanceSsh),
+    SshConfig(CmdInstanceSshConfig),
     Start(CmdInstanceStart),
     Stop(CmdInstanceStop),
     Reboot(CmdInstanceReboot),
@@ -32,13 +37,17 @@ enum SubCommand {
 impl crate::cmd::Command for CmdInstance {
     async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
         match &self.subcmd {
+            SubCommand::Clone(cmd) => cmd.run(ctx).await,
+            SubCommand::Cp(cmd) => cmd.run(ctx).await,
             SubCommand::Create(cmd) => cmd.run(ctx).await,
             SubCommand::Delete(cmd) => cmd.run(ctx).await,
             SubCommand::Disks(cmd) => cmd.run(ctx).await,
             SubCommand::Edit(cmd) => cmd.run(ctx).await,
+            SubCommand::Inventory(cmd) => cmd.run(ctx).await,
             SubCommand::List(cmd) => cmd.run(ctx).await,
             SubCommand::Serial(cmd) => cmd.run(ctx).await,
             SubCommand::Ssh(cmd) => cmd.run(ctx).await,
+            SubCommand::SshConfig(cmd) => cmd.run(ctx).await,
             SubCommand::Start(cmd) => cmd.run(ctx).await,
             SubCommand::Stop(cmd) => cmd.run(ctx).await,
             SubCommand::Reboot(cmd) => cmd.run(ctx).await,
@@ -47,6 +56,524 @@ impl crate::cmd::Command for CmdInstance {
     }
 }
 
+/// The starting point printed by `oxide instance create --emit-template`.
+pub const INSTANCE_TEMPLATE: &str = r#"# An instance for `oxide instance create --from-file`. The fields are those of the
+# body of the request, see `InstanceCreate` in the API reference.
+
+# The name of the instance, unless one is given on the command line.
+name: my-instance
+description: My instance
+hostname: my-instance
+# The number of CPUs, and the memory in bytes.
+ncpus: 2
+memory: 4294967296
+# Whether to start the instance once it is created.
+start: true
+
+# The disks to create for the instance, or to attach to it.
+disks:
+  # A disk created from an image: the ID of a project image, with the `image`
+  # type, or of a global image, with the `global_image` type.
+  - type: create
+    name: my-instance-boot
+    description: The boot disk of my-instance
+    size: 10737418240
+    disk_source:
+      type: global_image
+      image_id: 00000000-0000-0000-0000-000000000000
+  # A blank disk, the block size is 512, 2048 or 4096.
+  - type: create
+    name: my-instance-data
+    description: The data disk of my-instance
+    size: 107374182400
+    disk_source:
+      type: blank
+      block_size: 4096
+  # An existing disk.
+  # - type: attach
+  #   name: my-disk
+
+# The network interfaces: `default` for one in the default VPC subnet, `none`, or
+# `create` with the interfaces to create, the first one is the primary interface.
+network_interfaces:
+  type: default
+# network_interfaces:
+#   type: create
+#   params:
+#     - name: net0
+#       description: The primary interface of my-instance
+#       vpc_name: default
+#       subnet_name: default
+#       # Assigned from the subnet, unless given.
+#       ip: 172.30.0.10
+
+# The external IP addresses, from the IP pool with this name, or from any pool
+# without `pool_name`.
+external_ips:
+  - type: ephemeral
+    pool_name: default
+
+# The base64 encoded user data for cloud-init. `--user-data` and `--cloud-config`
+# take it from a file instead.
+# user_data: ""
+"#;
+
+impl CmdInstanceCreate {
+    /// Create the instance from the file of `--from-file`.
+    async fn create_from_file(&self, ctx: &mut crate::context::Context<'_>, path: &std::path::Path) -> Result<()> {
+        let text = if path == std::path::Path::new("-") {
+            let mut text = String::new();
+            ctx.io.stdin.read_to_string(&mut text)?;
+            text
+        } else {
+            std::fs::read_to_string(path).map_err(|e| anyhow!("reading {}: {}", path.display(), e))?
+        };
+        let mut body: serde_json::Value =
+            serde_yaml::from_str(&text).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+        if let Some(object) = body.as_object_mut() {
+            if !self.instance.is_empty() {
+                object.insert("name".to_string(), serde_json::json!(self.instance));
+            }
+
+            if self.user_data.is_some() || self.cloud_config.is_some() || !self.ssh_keys.is_empty() {
+                let user_data = crate::cloud_init::user_data(
+                    ctx,
+                    self.user_data.as_deref(),
+                    self.cloud_config.as_deref(),
+                    &self.ssh_keys,
+                )
+                .await?;
+                object.insert("user_data".to_string(), serde_json::json!(user_data));
+            }
+        }
+
+        let errors = crate::schema::validate("InstanceCreate", &body)?;
+        if !errors.is_empty() {
+            return Err(anyhow!(
+                "{} is not a valid instance:\n{}",
+                path.display(),
+                errors
+                    .iter()
+                    .map(|error| format!("  {}", error))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+
+        crate::schema::apply_defaults("InstanceCreate", &mut body)?;
+        let body: oxide_api::types::InstanceCreate =
+            serde_json::from_value(body).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+        let client = ctx.api_client("")?;
+        client
+            .instances()
+            .post(&self.organization, &self.project, &body)
+            .await?;
+
+        if !self.no_wait {
+            let status = if body.start {
+                oxide_api::types::InstanceState::Running
+            } else {
+                oxide_api::types::InstanceState::Stopped
+            };
+
+            InstanceDetails {
+                host: "".to_string(),
+                organization: self.organization.to_string(),
+                project: self.project.to_string(),
+                instance: body.name.to_string(),
+            }
+            .wait_for_state(
+                ctx,
+                status,
+                &crate::wait::WaitOptions {
+                    timeout: self.timeout,
+                    poll_interval: self.poll_interval,
+                },
+            )
+            .await?;
+        }
+
+        let cs = ctx.io.color_scheme();
+        writeln!(
+            ctx.io.out,
+            "{} Created instance {} in {}/{}",
+            cs.success_icon(),
+            body.name,
+            self.organization,
+            self.project
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Clone an instance.
+///
+/// Each disk attached to the instance is snapshotted, and a new disk is created
+/// from each snapshot. The new instance gets these disks, and the same number of
+/// CPUs, memory and network interfaces as the original. It is started if the
+/// original is running.
+///
+/// The API does not tell in which order the disks of the original were attached,
+/// so the new disks are attached in the order of their names. If that is not the
+/// order of the original, the clone may boot from another disk.
+///
+/// The names of the new disks and the hostname are those of the original with its
+/// name replaced by the new one, e.g. the disk `web-boot` of `web` becomes
+/// `web2-boot` for `web2`. The snapshots are left behind and have the names of the
+/// new disks.
+///
+/// With `--to-project` the instance is created in another project of the same
+/// organization, which must have VPCs and subnets with the names of those the
+/// network interfaces of the original are in.
+///
+/// If any step fails, everything created so far is deleted again.
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdInstanceClone {
+    /// The instance to clone. Can be an ID or name.
+    #[clap(name = "instance", required = true)]
+    pub instance: String,
+
+    /// The name of the new instance.
+    #[clap(name = "new-instance", required = true)]
+    pub new_instance: String,
+
+    /// The project that holds the instance.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The project to create the new instance in, instead of the project of the
+    /// original.
+    #[clap(long)]
+    pub to_project: Option<String>,
+
+    /// How long to wait for each step, e.g. `90s` or `5m`. Use `0` to wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check on each step, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
+}
+
+/// A resource created while cloning an instance, deleted again if cloning fails.
+enum Cloned {
+    Snapshot(String),
+    Disk(String),
+    Instance(String),
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdInstanceClone {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let mut cloned = Vec::new();
+        if let Err(err) = self.clone_instance(ctx, &mut cloned).await {
+            // Whatever happens while cleaning up, the error of the clone is the one to report.
+            if let Err(clean_up_err) = self.clean_up(ctx, cloned).await {
+                let cs = ctx.io.color_scheme();
+                writeln!(
+                    ctx.io.err_out,
+                    "{} Could not clean up after the failed clone: {}",
+                    cs.warning_icon(),
+                    clean_up_err
+                )
+                .ok();
+            }
+            return Err(err);
+        }
+
+        let cs = ctx.io.color_scheme();
+        writeln!(
+            ctx.io.out,
+            "{} Cloned instance {} to {} in {}/{}",
+            cs.success_icon(),
+            self.instance,
+            self.new_instance,
+            self.organization,
+            self.to_project()
+        )?;
+
+        Ok(())
+    }
+}
+
+impl CmdInstanceClone {
+    fn to_project(&self) -> &str {
+        self.to_project.as_deref().unwrap_or(&self.project)
+    }
+
+    /// Clone the instance, recording what was created in `cloned` as we go.
+    async fn clone_instance(&self, ctx: &mut crate::context::Context<'_>, cloned: &mut Vec<Cloned>) -> Result<()> {
+        let client = ctx.api_client("")?;
+        let opts = crate::wait::WaitOptions {
+            timeout: self.timeout,
+            poll_interval: self.poll_interval,
+        };
+
+        let instance = client
+            .instances()
+            .get(&self.instance, &self.organization, &self.project)
+            .await?;
+        // The API only lists the disks of an instance by name, not in the order they
+        // were attached.
+        let disks = client
+            .instances()
+            .disks_get_all(
+                &self.instance,
+                &self.organization,
+                &self.project,
+                oxide_api::types::NameSortMode::NameAscending,
+            )
+            .await?;
+        let mut interfaces = client
+            .instances()
+            .network_interfaces_get_all(
+                &self.instance,
+                &self.organization,
+                &self.project,
+                oxide_api::types::NameSortMode::NameAscending,
+            )
+            .await?;
+        // The first interface is the primary one.
+        interfaces.sort_by_key(|interface| !interface.primary);
+
+        // The interfaces only know the IDs of their VPCs and subnets, but new ones
+        // are created by name.
+        let mut subnets = HashMap::new();
+        if !interfaces.is_empty() {
+            for vpc in client
+                .vpcs()
+                .get_all(&self.organization, &self.project, Default::default())
+                .await?
+            {
+                for subnet in client
+                    .subnets()
+                    .get_all(&self.organization, &self.project, Default::default(), &vpc.name)
+                    .await?
+                {
+                    subnets.insert(subnet.id.to_string(), (vpc.name.to_string(), subnet.name.to_string()));
+                }
+            }
+        }
+
+        let mut network_interfaces = Vec::new();
+        for interface in &interfaces {
+            let (vpc, subnet) = subnets
+                .get(&interface.subnet_id)
+                .ok_or_else(|| anyhow!("the subnet of network interface {} was not found", interface.name))?;
+            network_interfaces.push(serde_json::json!({
+                "name": interface.name,
+                "description": interface.description,
+                "vpc_name": vpc,
+                "subnet_name": subnet,
+            }));
+        }
+
+        let mut new_disks = Vec::new();
+        for disk in &disks {
+            let name = cloned_name(&disk.name, &instance.name, &self.new_instance);
+
+            client
+                .snapshots()
+                .post(
+                    &self.organization,
+                    &self.project,
+                    &oxide_api::types::SnapshotCreate {
+                        description: format!("The snapshot of disk {} to clone instance {}", disk.name, instance.name),
+                        disk: disk.name.to_string(),
+                        name: name.to_string(),
+                    },
+                )
+                .await?;
+            cloned.push(Cloned::Snapshot(name.to_string()));
+            crate::cmd_snapshot::wait_for_snapshot(ctx, &self.organization, &self.project, &name, &opts).await?;
+
+            let snapshot = client.snapshots().get(&self.organization, &self.project, &name).await?;
+            client
+                .disks()
+                .post(
+                    &self.organization,
+                    self.to_project(),
+                    &oxide_api::types::DiskCreate {
+                        description: disk.description.to_string(),
+                        disk_source: oxide_api::types::DiskSource::Snapshot {
+                            snapshot_id: snapshot.id.to_string(),
+                        },
+                        name: name.to_string(),
+                        size: disk.size,
+                    },
+                )
+                .await?;
+            cloned.push(Cloned::Disk(name.to_string()));
+            crate::cmd_disk::wait_for_disk_state(ctx, &self.organization, self.to_project(), &name, "detached", &opts)
+                .await?;
+
+            new_disks.push(serde_json::json!({ "type": "attach", "name": name }));
+        }
+
+        let hostname = if instance.hostname.contains(&instance.name) {
+            instance.hostname.replace(&instance.name, &self.new_instance)
+        } else {
+            self.new_instance.to_string()
+        };
+        let start = instance.run_state == oxide_api::types::InstanceState::Running;
+        let mut body = serde_json::json!({
+            "name": self.new_instance,
+            "description": instance.description,
+            "hostname": hostname,
+            "ncpus": instance.ncpus,
+            "memory": instance.memory,
+            "start": start,
+            "disks": new_disks,
+            "network_interfaces": if network_interfaces.is_empty() {
+                serde_json::json!({ "type": "none" })
+            } else {
+                serde_json::json!({ "type": "create", "params": network_interfaces })
+            },
+        });
+        crate::schema::apply_defaults("InstanceCreate", &mut body)?;
+        let body: oxide_api::types::InstanceCreate = serde_json::from_value(body)?;
+
+        client
+            .instances()
+            .post(&self.organization, self.to_project(), &body)
+            .await?;
+        cloned.push(Cloned::Instance(self.new_instance.to_string()));
+
+        InstanceDetails {
+            host: "".to_string(),
+            organization: self.organization.to_string(),
+            project: self.to_project().to_string(),
+            instance: self.new_instance.to_string(),
+        }
+        .wait_for_state(
+            ctx,
+            if start {
+                oxide_api::types::InstanceState::Running
+            } else {
+                oxide_api::types::InstanceState::Stopped
+            },
+            &opts,
+        )
+        .await
+    }
+
+    /// Delete what was created before cloning failed, newest first. Resources that
+    /// cannot be deleted are reported so they can be deleted by hand.
+    async fn clean_up(&self, ctx: &mut crate::context::Context<'_>, cloned: Vec<Cloned>) -> Result<()> {
+        if cloned.is_empty() {
+            return Ok(());
+        }
+
+        let client = ctx.api_client("")?;
+        let opts = crate::wait::WaitOptions {
+            timeout: self.timeout,
+            poll_interval: self.poll_interval,
+        };
+
+        let handle = ctx
+            .io
+            .start_process_indicator_with_label(" Cleaning up after the failed clone");
+
+        let mut failed = Vec::new();
+        for resource in cloned.iter().rev() {
+            let (what, result) = match resource {
+                Cloned::Snapshot(name) => (
+                    format!("snapshot {} in {}/{}", name, self.organization, self.project),
+                    client
+                        .snapshots()
+                        .delete(&self.organization, &self.project, name)
+                        .await
+                        .map_err(anyhow::Error::from),
+                ),
+                Cloned::Disk(name) => (
+                    format!("disk {} in {}/{}", name, self.organization, self.to_project()),
+                    client
+                        .disks()
+                        .delete(name, &self.organization, self.to_project())
+                        .await
+                        .map_err(anyhow::Error::from),
+                ),
+                Cloned::Instance(name) => {
+                    let instance = InstanceDetails {
+                        host: "".to_string(),
+                        organization: self.organization.to_string(),
+                        project: self.to_project().to_string(),
+                        instance: name.to_string(),
+                    };
+
+                    // The instance may have been started, and has to be stopped
+                    // before it can be deleted.
+                    let result = async {
+                        let _ = client
+                            .instances()
+                            .stop(name, &self.organization, self.to_project())
+                            .await;
+                        instance
+                            .poll_state(&client, &oxide_api::types::InstanceState::Stopped, &opts, |_| {})
+                            .await?;
+                        client
+                            .instances()
+                            .delete(name, &self.organization, self.to_project())
+                            .await?;
+                        Ok::<(), anyhow::Error>(())
+                    }
+                    .await;
+
+                    (
+                        format!("instance {} in {}/{}", name, self.organization, self.to_project()),
+                        result,
+                    )
+                }
+            };
+
+            if let Err(err) = result {
+                failed.push(format!("{}: {}", what, err));
+            }
+        }
+
+        if let Some(handle) = handle {
+            if failed.is_empty() {
+                handle.done();
+            } else {
+                handle.error();
+            }
+        }
+
+        if !failed.is_empty() {
+            let cs = ctx.io.color_scheme();
+            writeln!(
+                ctx.io.err_out,
+                "{} Could not clean up after the failed clone, delete these by hand:",
+                cs.warning_icon()
+            )?;
+            for failed in failed {
+                writeln!(ctx.io.err_out, "  {}", failed)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The name of the copy of `name`, a resource of the instance `instance`, for the
+/// clone `clone`.
+fn cloned_name(name: &str, instance: &str, clone: &str) -> String {
+    if name.contains(instance) {
+        name.replacen(instance, clone, 1)
+    } else {
+        format!("{}-{}", clone, name)
+    }
+}
+
 /// List the disks attached to an instance.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
@@ -89,34 +616,257 @@ impl crate::cmd::Command for CmdInstanceDisks {
     }
 }
 
-/// Edit instance settings.
+/// Preview edits to instance settings.
+///
+/// The settings of the instance are opened as YAML in the editor, see `oxide config
+/// set editor`, and the ones that were changed are printed.
+///
+/// The Oxide API cannot update instances yet, so nothing is changed on the rack, and
+/// the command fails once it printed the changes.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
-pub struct CmdInstanceEdit {}
+pub struct CmdInstanceEdit {
+    /// The instance to edit. Can be an ID or name.
+    #[clap(name = "instance", required = true)]
+    pub instance: String,
+
+    /// The project that holds the instance.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+}
 
 #[async_trait::async_trait]
 impl crate::cmd::Command for CmdInstanceEdit {
-    async fn run(&self, _ctx: &mut crate::context::Context) -> Result<()> {
-        println!("Not implemented yet in omicron.");
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let client = ctx.api_client("")?;
+
+        let instance = client
+            .instances()
+            .get(&self.instance, &self.organization, &self.project)
+            .await?;
+        let fields = serde_json::from_value(serde_json::json!({
+            "description": instance.description,
+            "hostname": instance.hostname,
+            "memory": instance.memory,
+            "ncpus": instance.ncpus,
+        }))?;
+
+        let changes = crate::edit::edit_fields(
+            ctx,
+            &format!(
+                "Preview edits to instance {} in {}/{}.\n\
+                 The Oxide API cannot update instances yet, so the changed fields are only printed.",
+                self.instance, self.organization, self.project
+            ),
+            &fields,
+        )?;
+        if changes.is_empty() {
+            writeln!(ctx.io.out, "No changes to instance {}", self.instance)?;
+            return Ok(());
+        }
+
+        writeln!(ctx.io.out, "Instance {} would change:", self.instance)?;
+        write!(ctx.io.out, "{}", crate::edit::preview(&fields, &changes))?;
+
+        // Until the API can update instances, make sure scripts do not take this for success.
+        Err(anyhow!(
+            "the Oxide API cannot update instances yet, nothing was changed"
+        ))
+    }
+}
+
+/// Print an Ansible inventory of the instances.
+///
+/// Instances are grouped by project, e.g. `project_prod`, and by state, e.g.
+/// `state_running`. Their variables include `ansible_host`, their external IP address
+/// or else their private one, and `oxide_hostname`, `oxide_ncpus`, `oxide_memory`,
+/// `oxide_external_ip` and `oxide_private_ip`.
+///
+/// This follows the protocol of Ansible inventory scripts, so that the inventory
+/// is always up to date:
+///
+///     $ cat inventory.sh
+///     #!/bin/sh
+///     exec oxide instance inventory --organization maze-war --project prod "$@"
+///     $ ansible -i inventory.sh state_running -m ping
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdInstanceInventory {
+    /// The project that holds the instances. Defaults to all the projects of the
+    /// organization, the hosts are then named `<instance>.<project>`.
+    #[clap(long, short, default_value = "")]
+    pub project: String,
+
+    /// The organization that holds the projects.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The format of the inventory, `ansible-json` or `ini`.
+    #[clap(long, short, default_value = "ansible-json")]
+    pub format: crate::inventory::InventoryFormat,
+
+    /// Print the whole inventory. This is what happens without `--host` too.
+    #[clap(long, conflicts_with = "host")]
+    pub list: bool,
+
+    /// Print the variables of a single host, as JSON.
+    #[clap(long)]
+    pub host: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdInstanceInventory {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let client = ctx.api_client("")?;
+
+        let projects = if self.project.is_empty() {
+            client
+                .projects()
+                .get_all(&self.organization, Default::default())
+                .await?
+                .into_iter()
+                .map(|project| project.name)
+                .collect()
+        } else {
+            vec![self.project.to_string()]
+        };
+
+        let mut hosts = Vec::new();
+        for project in &projects {
+            let instances = client
+                .instances()
+                .get_all(&self.organization, project, Default::default())
+                .await?;
+
+            for instance in instances {
+                let external_ip = client
+                    .instances()
+                    .external_ip_list(&instance.name, &self.organization, project)
+                    .await?
+                    .items
+                    .first()
+                    .map(|ip| ip.ip.to_string());
+                let private_ip = client
+                    .instances()
+                    .network_interfaces_get_all(
+                        &instance.name,
+                        &self.organization,
+                        project,
+                        oxide_api::types::NameSortMode::NameAscending,
+                    )
+                    .await?
+                    .into_iter()
+                    .find(|interface| interface.primary)
+                    .map(|interface| interface.ip.to_string());
+
+                let mut vars = serde_json::Map::new();
+                if let Some(ip) = external_ip.as_ref().or(private_ip.as_ref()) {
+                    vars.insert("ansible_host".to_string(), serde_json::json!(ip));
+                }
+                vars.insert("oxide_external_ip".to_string(), serde_json::json!(external_ip));
+                vars.insert("oxide_hostname".to_string(), serde_json::json!(instance.hostname));
+                vars.insert("oxide_id".to_string(), serde_json::json!(instance.id));
+                vars.insert("oxide_memory".to_string(), serde_json::json!(instance.memory));
+                vars.insert("oxide_ncpus".to_string(), serde_json::json!(instance.ncpus));
+                vars.insert("oxide_private_ip".to_string(), serde_json::json!(private_ip));
+                vars.insert("oxide_project".to_string(), serde_json::json!(project));
+                vars.insert(
+                    "oxide_run_state".to_string(),
+                    serde_json::json!(instance.run_state.to_string()),
+                );
+
+                hosts.push(crate::inventory::Host {
+                    name: if self.project.is_empty() {
+                        format!("{}.{}", instance.name, project)
+                    } else {
+                        instance.name.to_string()
+                    },
+                    groups: vec![
+                        crate::inventory::group_name("project", project),
+                        crate::inventory::group_name("state", &instance.run_state.to_string()),
+                    ],
+                    vars,
+                });
+            }
+        }
+
+        if let Some(name) = &self.host {
+            // Ansible expects an empty object for hosts it does not know about.
+            let vars = match hosts.into_iter().find(|host| &host.name == name) {
+                Some(host) => host.vars,
+                None => Default::default(),
+            };
+            writeln!(ctx.io.out, "{}", serde_json::to_string_pretty(&vars)?)?;
+            return Ok(());
+        }
+
+        match self.format {
+            crate::inventory::InventoryFormat::AnsibleJson => writeln!(
+                ctx.io.out,
+                "{}",
+                serde_json::to_string_pretty(&crate::inventory::to_json(&hosts))?
+            )?,
+            crate::inventory::InventoryFormat::Ini => write!(ctx.io.out, "{}", crate::inventory::to_ini(&hosts))?,
+        }
+
         Ok(())
     }
 }
 
-/// Start an instance.
+/// Start one or more instances.
+///
+/// Pass one or more names, or select the instances to start with `--all`,
+/// `--match` or `--from-stdin`.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
 pub struct CmdInstanceStart {
-    /// The instance to start. Can be an ID or name.
-    #[clap(name = "instance", required = true)]
-    instance: String,
+    /// The instances to start. Can be an ID or name.
+    #[clap(name = "instance", multiple_values = true)]
+    pub instance: Vec<String>,
 
-    /// The project that holds the instance.
+    /// The project that holds the instances.
     #[clap(long, short, required = true)]
     pub project: String,
 
     /// The organization that holds the project.
     #[clap(long, short, required = true, env = "OXIDE_ORG")]
     pub organization: String,
+
+    /// Start all instances in the project.
+    #[clap(long)]
+    pub all: bool,
+
+    /// Start instances whose name matches a glob (e.g. `web-*`), or a regular
+    /// expression wrapped in slashes (e.g. `/^web-[0-9]+$/`).
+    #[clap(long = "match")]
+    pub selector: Option<String>,
+
+    /// Read additional instance names or IDs from stdin, one per line.
+    #[clap(long)]
+    pub from_stdin: bool,
+
+    /// The maximum number of instances to start concurrently.
+    #[clap(long, default_value_t = crate::bulk::DEFAULT_PARALLEL)]
+    pub parallel: usize,
+
+    /// How long to wait for the instances to be started, e.g. `90s` or `5m`.
+    /// Use `0` to wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check the state of the instances, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
+
+    /// Return once the start request is accepted, without waiting for the instances
+    /// to be started.
+    #[clap(long)]
+    pub no_wait: bool,
 }
 
 #[async_trait::async_trait]
@@ -124,31 +874,111 @@ impl crate::cmd::Command for CmdInstanceStart {
     async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
         let client = ctx.api_client("")?;
 
+        let names = select_instances(
+            ctx,
+            &client,
+            &self.organization,
+            &self.project,
+            &self.instance,
+            self.all,
+            &self.selector,
+            self.from_stdin,
+        )
+        .await?;
+
         let full_name = format!("{}/{}", self.organization, self.project);
+        let opts = crate::wait::WaitOptions {
+            timeout: self.timeout,
+            poll_interval: self.poll_interval,
+        };
 
-        // Name the future to start the instance.
-        let instances = client.instances();
-        let start_instance = instances.start(&self.instance, &self.organization, &self.project);
+        if names.len() == 1 {
+            let instance = &names[0];
 
-        // And another to wait for the instance to be started.
-        let instance_state = InstanceDetails {
-            host: "".to_string(),
-            instance: self.instance.to_string(),
-            organization: self.organization.to_string(),
-            project: self.project.to_string(),
-        };
-        let state_change = instance_state.wait_for_state(ctx, oxide_api::types::InstanceState::Running);
+            // Start the instance. This is sent before we start waiting so that
+            // interrupting the wait never cancels the request itself.
+            client
+                .instances()
+                .start(instance, &self.organization, &self.project)
+                .await?;
+
+            let cs = ctx.io.color_scheme();
+            if self.no_wait {
+                writeln!(
+                    ctx.io.out,
+                    "{} Requested start of instance {} in {}",
+                    cs.success_icon(),
+                    instance,
+                    full_name
+                )?;
 
-        // Concurrently send the start request and wait for the instance to be started,
-        // bail out if either fails.
-        tokio::try_join!(start_instance, state_change)?;
+                return Ok(());
+            }
+
+            // Wait for the instance to be started.
+            let instance_state = InstanceDetails {
+                host: "".to_string(),
+                instance: instance.to_string(),
+                organization: self.organization.to_string(),
+                project: self.project.to_string(),
+            };
+
+            instance_state
+                .wait_for_state(ctx, oxide_api::types::InstanceState::Running, &opts)
+                .await?;
+
+            writeln!(
+                ctx.io.out,
+                "{} Started instance {} in {}",
+                cs.success_icon(),
+                instance,
+                full_name
+            )?;
+
+            return Ok(());
+        }
+
+        let handle = ctx
+            .io
+            .start_process_indicator_with_label(&format!(" Starting {} instances", names.len()));
+
+        let results = crate::bulk::run(&names, self.parallel, |name| {
+            let client = &client;
+            let opts = &opts;
+            async move {
+                client
+                    .instances()
+                    .start(&name, &self.organization, &self.project)
+                    .await?;
+
+                if self.no_wait {
+                    return Ok(());
+                }
+
+                InstanceDetails {
+                    host: "".to_string(),
+                    instance: name,
+                    organization: self.organization.to_string(),
+                    project: self.project.to_string(),
+                }
+                .poll_state(client, &oxide_api::types::InstanceState::Running, opts, |_| {})
+                .await
+            }
+        })
+        .await;
+
+        if let Some(handle) = handle {
+            handle.done();
+        }
+
+        crate::bulk::write_summary(ctx, "started", "instances", results)?;
 
         let cs = ctx.io.color_scheme();
         writeln!(
             ctx.io.out,
-            "{} Started instance {} in {}",
+            "{} Started {} instances in {}",
             cs.success_icon(),
-            self.instance,
+            names.len(),
             full_name
         )?;
 
@@ -156,15 +986,18 @@ impl crate::cmd::Command for CmdInstanceStart {
     }
 }
 
-/// Stop an instance.
+/// Stop one or more instances.
+///
+/// Pass one or more names, or select the instances to stop with `--all`,
+/// `--match` or `--from-stdin`.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
 pub struct CmdInstanceStop {
-    /// The instance to stop. Can be an ID or name.
-    #[clap(name = "instance", required = true)]
-    instance: String,
+    /// The instances to stop. Can be an ID or name.
+    #[clap(name = "instance", multiple_values = true)]
+    pub instance: Vec<String>,
 
-    /// The project that holds the instance.
+    /// The project that holds the instances.
     #[clap(long, short, required = true)]
     pub project: String,
 
@@ -172,6 +1005,38 @@ pub struct CmdInstanceStop {
     #[clap(long, short, required = true, env = "OXIDE_ORG")]
     pub organization: String,
 
+    /// Stop all instances in the project.
+    #[clap(long)]
+    pub all: bool,
+
+    /// Stop instances whose name matches a glob (e.g. `web-*`), or a regular
+    /// expression wrapped in slashes (e.g. `/^web-[0-9]+$/`).
+    #[clap(long = "match")]
+    pub selector: Option<String>,
+
+    /// Read additional instance names or IDs from stdin, one per line.
+    #[clap(long)]
+    pub from_stdin: bool,
+
+    /// The maximum number of instances to stop concurrently.
+    #[clap(long, default_value_t = crate::bulk::DEFAULT_PARALLEL)]
+    pub parallel: usize,
+
+    /// How long to wait for the instances to be stopped, e.g. `90s` or `5m`.
+    /// Use `0` to wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check the state of the instances, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
+
+    /// Return once the stop request is accepted, without waiting for the instances
+    /// to be stopped.
+    #[clap(long)]
+    pub no_wait: bool,
+
     /// Confirm stop without prompting.
     #[clap(long)]
     pub confirm: bool,
@@ -186,49 +1051,116 @@ impl crate::cmd::Command for CmdInstanceStop {
 
         let client = ctx.api_client("")?;
 
+        let names = select_instances(
+            ctx,
+            &client,
+            &self.organization,
+            &self.project,
+            &self.instance,
+            self.all,
+            &self.selector,
+            self.from_stdin,
+        )
+        .await?;
+
         let full_name = format!("{}/{}", self.organization, self.project);
+        let opts = crate::wait::WaitOptions {
+            timeout: self.timeout,
+            poll_interval: self.poll_interval,
+        };
 
         // Confirm stop.
         if !self.confirm {
-            if let Err(err) = dialoguer::Input::<String>::new()
-                .with_prompt(format!("Type {} to confirm stop:", self.instance))
-                .validate_with(|input: &String| -> Result<(), &str> {
-                    if input.trim() == self.instance {
-                        Ok(())
-                    } else {
-                        Err("mismatched confirmation")
-                    }
-                })
-                .interact_text()
-            {
-                return Err(anyhow!("prompt failed: {}", err));
+            crate::bulk::confirm(ctx, "stop", "instances", &names)?;
+        }
+
+        if names.len() == 1 {
+            let instance = &names[0];
+
+            // Stop the instance.
+            client
+                .instances()
+                .stop(instance, &self.organization, &self.project)
+                .await?;
+
+            if self.no_wait {
+                let cs = ctx.io.color_scheme();
+                writeln!(
+                    ctx.io.out,
+                    "{} Requested stop of instance {} in {}",
+                    cs.failure_icon_with_color(ansi_term::Color::Green),
+                    instance,
+                    full_name
+                )?;
+
+                return Ok(());
             }
+
+            // Wait for the instance to be stopped.
+            let instance_state = InstanceDetails {
+                host: "".to_string(),
+                instance: instance.to_string(),
+                organization: self.organization.to_string(),
+                project: self.project.to_string(),
+            };
+
+            instance_state
+                .wait_for_state(ctx, oxide_api::types::InstanceState::Stopped, &opts)
+                .await?;
+
+            let cs = ctx.io.color_scheme();
+            writeln!(
+                ctx.io.out,
+                "{} Stopped instance {} in {}",
+                cs.failure_icon_with_color(ansi_term::Color::Green),
+                instance,
+                full_name
+            )?;
+
+            return Ok(());
         }
 
-        // Stop the instance.
-        client
-            .instances()
-            .stop(&self.instance, &self.organization, &self.project)
-            .await?;
+        let handle = ctx
+            .io
+            .start_process_indicator_with_label(&format!(" Stopping {} instances", names.len()));
+
+        let results = crate::bulk::run(&names, self.parallel, |name| {
+            let client = &client;
+            let opts = &opts;
+            async move {
+                client
+                    .instances()
+                    .stop(&name, &self.organization, &self.project)
+                    .await?;
+
+                if self.no_wait {
+                    return Ok(());
+                }
 
-        // Wait for the instance to be stopped.
-        let instance_state = InstanceDetails {
-            host: "".to_string(),
-            instance: self.instance.to_string(),
-            organization: self.organization.to_string(),
-            project: self.project.to_string(),
-        };
+                InstanceDetails {
+                    host: "".to_string(),
+                    instance: name,
+                    organization: self.organization.to_string(),
+                    project: self.project.to_string(),
+                }
+                .poll_state(client, &oxide_api::types::InstanceState::Stopped, opts, |_| {})
+                .await
+            }
+        })
+        .await;
 
-        instance_state
-            .wait_for_state(ctx, oxide_api::types::InstanceState::Stopped)
-            .await?;
+        if let Some(handle) = handle {
+            handle.done();
+        }
+
+        crate::bulk::write_summary(ctx, "stopped", "instances", results)?;
 
         let cs = ctx.io.color_scheme();
         writeln!(
             ctx.io.out,
-            "{} Stopped instance {} in {}",
+            "{} Stopped {} instances in {}",
             cs.failure_icon_with_color(ansi_term::Color::Green),
-            self.instance,
+            names.len(),
             full_name
         )?;
 
@@ -236,15 +1168,18 @@ impl crate::cmd::Command for CmdInstanceStop {
     }
 }
 
-/// Reboot an instance.
+/// Reboot one or more instances.
+///
+/// Pass one or more names, or select the instances to reboot with `--all`,
+/// `--match` or `--from-stdin`.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
 pub struct CmdInstanceReboot {
-    /// The instance to reboot. Can be an ID or name.
-    #[clap(name = "instance", required = true)]
-    instance: String,
+    /// The instances to reboot. Can be an ID or name.
+    #[clap(name = "instance", multiple_values = true)]
+    pub instance: Vec<String>,
 
-    /// The project that holds the instance.
+    /// The project that holds the instances.
     #[clap(long, short, required = true)]
     pub project: String,
 
@@ -252,6 +1187,38 @@ pub struct CmdInstanceReboot {
     #[clap(long, short, required = true, env = "OXIDE_ORG")]
     pub organization: String,
 
+    /// Reboot all instances in the project.
+    #[clap(long)]
+    pub all: bool,
+
+    /// Reboot instances whose name matches a glob (e.g. `web-*`), or a regular
+    /// expression wrapped in slashes (e.g. `/^web-[0-9]+$/`).
+    #[clap(long = "match")]
+    pub selector: Option<String>,
+
+    /// Read additional instance names or IDs from stdin, one per line.
+    #[clap(long)]
+    pub from_stdin: bool,
+
+    /// The maximum number of instances to reboot concurrently.
+    #[clap(long, default_value_t = crate::bulk::DEFAULT_PARALLEL)]
+    pub parallel: usize,
+
+    /// How long to wait for the instances to be running again, e.g. `90s` or `5m`.
+    /// Use `0` to wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check the state of the instances, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
+
+    /// Return once the reboot request is accepted, without waiting for the instances
+    /// to be running again.
+    #[clap(long)]
+    pub no_wait: bool,
+
     /// Confirm reboot without prompting.
     #[clap(long)]
     pub confirm: bool,
@@ -266,49 +1233,116 @@ impl crate::cmd::Command for CmdInstanceReboot {
 
         let client = ctx.api_client("")?;
 
+        let names = select_instances(
+            ctx,
+            &client,
+            &self.organization,
+            &self.project,
+            &self.instance,
+            self.all,
+            &self.selector,
+            self.from_stdin,
+        )
+        .await?;
+
         let full_name = format!("{}/{}", self.organization, self.project);
+        let opts = crate::wait::WaitOptions {
+            timeout: self.timeout,
+            poll_interval: self.poll_interval,
+        };
 
         // Confirm reboot.
         if !self.confirm {
-            if let Err(err) = dialoguer::Input::<String>::new()
-                .with_prompt(format!("Type {} to confirm reboot:", self.instance))
-                .validate_with(|input: &String| -> Result<(), &str> {
-                    if input.trim() == self.instance {
-                        Ok(())
-                    } else {
-                        Err("mismatched confirmation")
-                    }
-                })
-                .interact_text()
-            {
-                return Err(anyhow!("prompt failed: {}", err));
+            crate::bulk::confirm(ctx, "reboot", "instances", &names)?;
+        }
+
+        if names.len() == 1 {
+            let instance = &names[0];
+
+            // Reboot the instance.
+            client
+                .instances()
+                .reboot(instance, &self.organization, &self.project)
+                .await?;
+
+            if self.no_wait {
+                let cs = ctx.io.color_scheme();
+                writeln!(
+                    ctx.io.out,
+                    "{} Requested reboot of instance {} in {}",
+                    cs.success_icon(),
+                    instance,
+                    full_name
+                )?;
+
+                return Ok(());
             }
+
+            // Wait for the instance to be started.
+            let instance_state = InstanceDetails {
+                host: "".to_string(),
+                instance: instance.to_string(),
+                organization: self.organization.to_string(),
+                project: self.project.to_string(),
+            };
+
+            instance_state
+                .wait_for_state(ctx, oxide_api::types::InstanceState::Running, &opts)
+                .await?;
+
+            let cs = ctx.io.color_scheme();
+            writeln!(
+                ctx.io.out,
+                "{} Rebooted instance {} in {}",
+                cs.success_icon(),
+                instance,
+                full_name
+            )?;
+
+            return Ok(());
         }
 
-        // Reboot the instance.
-        client
-            .instances()
-            .reboot(&self.instance, &self.organization, &self.project)
-            .await?;
+        let handle = ctx
+            .io
+            .start_process_indicator_with_label(&format!(" Rebooting {} instances", names.len()));
+
+        let results = crate::bulk::run(&names, self.parallel, |name| {
+            let client = &client;
+            let opts = &opts;
+            async move {
+                client
+                    .instances()
+                    .reboot(&name, &self.organization, &self.project)
+                    .await?;
+
+                if self.no_wait {
+                    return Ok(());
+                }
 
-        // Wait for the instance to be started.
-        let instance_state = InstanceDetails {
-            host: "".to_string(),
-            instance: self.instance.to_string(),
-            organization: self.organization.to_string(),
-            project: self.project.to_string(),
-        };
+                InstanceDetails {
+                    host: "".to_string(),
+                    instance: name,
+                    organization: self.organization.to_string(),
+                    project: self.project.to_string(),
+                }
+                .poll_state(client, &oxide_api::types::InstanceState::Running, opts, |_| {})
+                .await
+            }
+        })
+        .await;
 
-        instance_state
-            .wait_for_state(ctx, oxide_api::types::InstanceState::Running)
-            .await?;
+        if let Some(handle) = handle {
+            handle.done();
+        }
+
+        crate::bulk::write_summary(ctx, "rebooted", "instances", results)?;
 
         let cs = ctx.io.color_scheme();
         writeln!(
             ctx.io.out,
-            "{} Rebooted instance {} in {}",
+            "{} Rebooted {} instances in {}",
             cs.success_icon(),
-            self.instance,
+            names.len(),
             full_name
         )?;
 
@@ -316,63 +1350,135 @@ impl crate::cmd::Command for CmdInstanceReboot {
     }
 }
 
+/// Resolve the instances selected by name, `--all`, `--match` and `--from-stdin`
+/// into a de-duplicated list of names.
+#[allow(clippy::too_many_arguments)]
+async fn select_instances(
+    ctx: &mut crate::context::Context<'_>,
+    client: &oxide_api::Client,
+    organization: &str,
+    project: &str,
+    instances: &[String],
+    all: bool,
+    selector: &Option<String>,
+    from_stdin: bool,
+) -> Result<Vec<String>> {
+    let listed = if all || selector.is_some() {
+        client
+            .instances()
+            .get_all(organization, project, Default::default())
+            .await?
+            .into_iter()
+            .map(|instance| instance.name.to_string())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let from_stdin = if from_stdin {
+        crate::bulk::read_names(&mut ctx.io.stdin)?
+    } else {
+        Vec::new()
+    };
+
+    let names = crate::bulk::targets(instances, listed, all, selector, from_stdin)?;
+    if names.is_empty() {
+        return Err(anyhow!(
+            "no instances selected, pass one or more names, --all, --match or --from-stdin"
+        ));
+    }
+
+    Ok(names)
+}
+
+/// An instance we can wait on.
 #[derive(Debug, Clone, PartialEq)]
-struct InstanceDetails {
-    host: String,
-    organization: String,
-    project: String,
-    instance: String,
+pub(crate) struct InstanceDetails {
+    pub host: String,
+    pub organization: String,
+    pub project: String,
+    pub instance: String,
 }
 
 impl InstanceDetails {
-    async fn wait_for_state(
+    /// Wait for the instance to reach `status`, showing a spinner while we do.
+    pub async fn wait_for_state(
         &self,
         ctx: &mut crate::context::Context<'_>,
         status: oxide_api::types::InstanceState,
+        opts: &crate::wait::WaitOptions,
     ) -> Result<()> {
         // Start the progress bar.
         let handle = ctx
             .io
-            .start_process_indicator_with_label(&format!(
-                " Waiting for instance status to be `{}`",
-                status
-            ));
+            .start_process_indicator_with_label(&format!(" Waiting for instance status to be `{}`", status));
 
         let client = ctx.api_client(&self.host)?;
 
-        // TODO: we should probably time out here eventually with an error.
-        let mut last_state = None;
-        loop {
-            let instance = client
-                .instances()
-                .get(&self.instance, &self.organization, &self.project)
-                .await?;
-
-            if status == instance.run_state {
-                break;
-            }
-
-            if last_state.as_ref() != Some(&instance.run_state) {
+        let result = self
+            .poll_state(&client, &status, opts, |state| {
                 if let Some(handle) = &handle {
-                    handle.text(format!(
-                        " Waiting for instance status to be `{}` [{}]",
-                        status, instance.run_state
-                    ));
+                    handle.text(format!(" Waiting for instance status to be `{}` [{}]", status, state));
                 }
-                last_state = Some(instance.run_state);
-            }
-
-            // Back off a bit.
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-        }
+            })
+            .await;
 
         // End the progress bar.
         if let Some(handle) = handle {
-            handle.text(format!("Instance status now `{}`", status));
-            handle.done();
+            if result.is_ok() {
+                handle.text(format!("Instance status now `{}`", status));
+                handle.done();
+            } else {
+                handle.error();
+            }
         }
 
-        Ok(())
+        result
+    }
+
+    /// Poll the instance until it reaches `status`, calling `on_change` whenever the
+    /// observed state changes along the way.
+    ///
+    /// Waiting stops with an error as soon as the instance ends up `failed` or
+    /// `destroyed`, since it will never get to `status` from there.
+    pub async fn poll_state<F>(
+        &self,
+        client: &oxide_api::Client,
+        status: &oxide_api::types::InstanceState,
+        opts: &crate::wait::WaitOptions,
+        on_change: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str),
+    {
+        let what = format!("instance {} to be `{}`", self.instance, status);
+
+        crate::wait::poll(
+            opts,
+            &what,
+            || async move {
+                let instance = client
+                    .instances()
+                    .get(&self.instance, &self.organization, &self.project)
+                    .await?;
+
+                Ok(if *status == instance.run_state {
+                    crate::wait::Poll::Done
+                } else if matches!(
+                    instance.run_state,
+                    oxide_api::types::InstanceState::Failed | oxide_api::types::InstanceState::Destroyed
+                ) {
+                    crate::wait::Poll::Failed(format!(
+                        "instance {} is `{}`, it will never be `{}`",
+                        self.instance, instance.run_state, status
+                    ))
+                } else {
+                    crate::wait::Poll::Pending(instance.run_state.to_string())
+                })
+            },
+            on_change,
+        )
+        .await
     }
 }
 
@@ -416,55 +1522,281 @@ impl crate::cmd::Command for CmdInstanceSsh {
     async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
         let client = ctx.api_client("")?;
 
-        // Generate a key to use for ssh-ing into the instance.
-        // We default to ed25519 here, since its a nice thing.
-        writeln!(ctx.io.out, "Generating a temporary ssh key...")?;
-        /* let key = thrussh_keys::key::KeyPair::generate_ed25519().unwrap();
-                let pubkey = key.clone_public_key();
+        // Resolve the address first, there is no point in creating a key for an
+        // instance we cannot reach.
+        let ip = crate::ssh::external_ip(&client, &self.organization, &self.project, &self.instance).await?;
 
-                writeln!(
-                    ctx.io.out,
-                    "Temporary public key has fingerprint `{}`",
-                    pubkey.fingerprint()
-                )?;
+        // Generate a key to use for ssh-ing into the instance, and add it to our Oxide
+        // user's authorized keys.
+        writeln!(ctx.io.err_out, "Adding a temporary ssh key to your user account...")?;
+        let key = crate::ssh::TemporaryKey::create(&client).await?;
 
-                writeln!(
-                    ctx.io.out,
-                    "Temporary bytes are `ssh-ed25519 {}`",
-                    pubkey.public_key_base64()
-                )?;
+        let result = async {
+            let mut ssh = tokio::process::Command::new("ssh");
+            ssh.args(crate::ssh::args(&key, &self.ssh_flags)?)
+                .arg(format!("{}@{}", self.user, ip))
+                .args(&self.args);
 
-                println!("ARGS: {:?}", self.args);
+            crate::ssh::run(&mut ssh).await
+        }
+        .await;
 
-                // TODO: Add our pubkey to our Oxide user's authorized_keys.
-                writeln!(ctx.io.out, "Adding temporary ssh key to your user account...")?;
-        */
-        // TODO: We need to get the instance IP address.
-        let _instance = client
-            .instances()
-            .get(&self.instance, &self.organization, &self.project)
-            .await?;
+        // Whatever happened, remove the key from our Oxide user's authorized keys.
+        // This makes it act as a temporary key.
+        writeln!(
+            ctx.io.err_out,
+            "Cleaning up the temporary ssh key from your user account..."
+        )?;
+        // Failing to do so must not hide how ssh did.
+        if let Err(err) = key.delete(&client).await {
+            let cs = ctx.io.color_scheme();
+            writeln!(ctx.io.err_out, "{} {}", cs.warning_icon(), err)?;
+        }
 
-        // Wrap the ssh command in a shell.
-        std::process::Command::new("ssh")
-            //.arg(host)
-            .args(&self.args)
-            .stdout(std::process::Stdio::inherit())
-            .stdin(std::process::Stdio::inherit())
-            .stderr(std::process::Stdio::inherit())
-            .output()?;
+        match result? {
+            0 => Ok(()),
+            code => Err(anyhow!("ssh exited with status {}", code)),
+        }
+    }
+}
 
-        // TODO: When we are done, we need to remove our key from our Oxide user's authorized keys.
-        // This makes it act as a temporary key.
+/// Print an OpenSSH configuration for the running instances.
+///
+/// Each instance gets a `Host` entry with its external IP address, so that **ssh(1)**,
+/// and the tools built on it, e.g. VS Code or Ansible, can connect to it by name:
+///
+///     $ oxide instance ssh-config --project prod > ~/.ssh/oxide_config
+///     $ echo "Include ~/.ssh/oxide_config" >> ~/.ssh/config
+///     $ ssh web-1
+///
+/// With `--update`, the entries are written to a block of the given file instead,
+/// between marker comments. Running it again replaces the entries written for the
+/// same project, and leaves the rest of the file alone.
+///
+/// Unlike `oxide instance ssh`, this does not add a temporary key to your account,
+/// add the public key matching `--identity-file` with `oxide ssh-key create` first.
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdInstanceSshConfig {
+    /// The project that holds the instances. Defaults to all the projects of the
+    /// organization, the hosts are then named `<instance>.<project>`.
+    #[clap(long, short, default_value = "")]
+    pub project: String,
+
+    /// The organization that holds the projects.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The ssh user. This defaults to `$USER` on the host the command is run on.
+    #[clap(long, short, required = true, env = "USER")]
+    pub user: String,
+
+    /// The private key to authenticate with.
+    #[clap(long, short, default_value = "~/.ssh/id_ed25519")]
+    pub identity_file: String,
+
+    /// The file to write the entries to, e.g. `~/.ssh/config`, instead of printing them.
+    #[clap(long)]
+    pub update: Option<std::path::PathBuf>,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdInstanceSshConfig {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let client = ctx.api_client("")?;
+
+        let projects = if self.project.is_empty() {
+            client
+                .projects()
+                .get_all(&self.organization, Default::default())
+                .await?
+                .into_iter()
+                .map(|project| project.name)
+                .collect()
+        } else {
+            vec![self.project.to_string()]
+        };
+
+        let mut entries = Vec::new();
+        for project in &projects {
+            let instances = client
+                .instances()
+                .get_all(&self.organization, project, Default::default())
+                .await?;
+
+            for instance in instances {
+                if instance.run_state != oxide_api::types::InstanceState::Running {
+                    continue;
+                }
+
+                let ips = client
+                    .instances()
+                    .external_ip_list(&instance.name, &self.organization, project)
+                    .await?;
+                let ip = match ips.items.first() {
+                    Some(ip) => ip.ip.to_string(),
+                    None => {
+                        writeln!(
+                            ctx.io.err_out,
+                            "Skipping instance {}, it does not have an external IP address",
+                            instance.name
+                        )?;
+                        continue;
+                    }
+                };
+
+                let host = if self.project.is_empty() {
+                    format!("{}.{}", instance.name, project)
+                } else {
+                    instance.name.to_string()
+                };
+                entries.push(crate::ssh::config_entry(&host, &ip, &self.user, &self.identity_file));
+            }
+        }
+        let block = entries.join("\n");
+
+        let path = match &self.update {
+            Some(path) => path,
+            None => {
+                write!(ctx.io.out, "{}", block)?;
+                return Ok(());
+            }
+        };
+
+        let marker = if self.project.is_empty() {
+            format!("oxide instance ssh-config {}", self.organization)
+        } else {
+            format!("oxide instance ssh-config {}/{}", self.organization, self.project)
+        };
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(anyhow!("could not read {}: {}", path.display(), err)),
+        };
+        let updated = crate::ssh::update_block(&contents, &marker, &block);
+
+        let cs = ctx.io.color_scheme();
+        if updated == contents {
+            writeln!(ctx.io.out, "{} {} is up to date", cs.success_icon(), path.display())?;
+            return Ok(());
+        }
+
+        std::fs::write(path, updated).map_err(|err| anyhow!("could not write {}: {}", path.display(), err))?;
         writeln!(
             ctx.io.out,
-            "Cleaning up the temporary ssh key from your user account..."
+            "{} Updated {} with {} hosts",
+            cs.success_icon(),
+            path.display(),
+            entries.len()
         )?;
 
         Ok(())
     }
 }
 
+/// Copy files between the local host and an instance.
+///
+/// One of the paths is on the instance, written `<instance>:<path>`:
+///
+///     $ oxide instance cp app.toml web-1:/etc/app/
+///     $ oxide instance cp -r web-1:/var/log/app ./logs
+///
+/// Like `oxide instance ssh`, this authenticates with a temporary ssh key added to
+/// your user account for the duration of the copy, and connects to the external IP
+/// address of the instance. Files are copied with `cat` over **ssh(1)**, directories
+/// with `tar`, so both need to be installed on the instance.
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdInstanceCp {
+    /// The file or directory to copy.
+    #[clap(name = "source", required = true)]
+    pub source: String,
+
+    /// Where to copy it. Directories are copied into this directory, which is
+    /// created if it does not exist.
+    #[clap(name = "destination", required = true)]
+    pub destination: String,
+
+    /// The project that holds the instance.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The ssh user. This defaults to `$USER` on the host the command is run on.
+    #[clap(long, short, required = true, env = "USER")]
+    pub user: String,
+
+    /// Copy directories and their contents.
+    #[clap(long, short)]
+    pub recursive: bool,
+
+    /// Additional flags to be passed to **ssh(1)**. It is recommended that flags
+    /// be passed using an assignment operator and quotes.
+    /// Example: `--ssh-flag "-o ConnectTimeout=5"`.
+    #[clap(long = "ssh-flag", multiple_occurrences = true, required = false)]
+    pub ssh_flags: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdInstanceCp {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let source = crate::ssh::Location::parse(&self.source);
+        let destination = crate::ssh::Location::parse(&self.destination);
+
+        let instance = match (source.instance(), destination.instance()) {
+            (Some(instance), None) | (None, Some(instance)) => instance,
+            _ => {
+                return Err(anyhow!(
+                    "exactly one of the paths must be on an instance, written `<instance>:<path>`"
+                ))
+            }
+        };
+
+        if let crate::ssh::Location::Local(path) = &source {
+            if std::fs::metadata(path)?.is_dir() && !self.recursive {
+                return Err(anyhow!("{} is a directory, use -r to copy it", path.display()));
+            }
+        }
+
+        let client = ctx.api_client("")?;
+
+        let ip = crate::ssh::external_ip(&client, &self.organization, &self.project, instance).await?;
+
+        writeln!(ctx.io.err_out, "Adding a temporary ssh key to your user account...")?;
+        let key = crate::ssh::TemporaryKey::create(&client).await?;
+
+        let copy = async {
+            let mut ssh = tokio::process::Command::new("ssh");
+            ssh.args(crate::ssh::args(&key, &self.ssh_flags)?)
+                .arg(format!("{}@{}", self.user, ip));
+
+            crate::ssh::copy(&ctx.io, ssh, &source, &destination, self.recursive).await
+        };
+
+        // Stopping the copy kills ssh, and tar, so that we can clean up.
+        let result = tokio::select! {
+            result = copy => result,
+            _ = tokio::signal::ctrl_c() => Err(anyhow!("interrupted")),
+        };
+
+        writeln!(
+            ctx.io.err_out,
+            "Cleaning up the temporary ssh key from your user account..."
+        )?;
+        // Failing to do so must not hide how the copy did.
+        if let Err(err) = key.delete(&client).await {
+            let cs = ctx.io.color_scheme();
+            writeln!(ctx.io.err_out, "{} {}", cs.warning_icon(), err)?;
+        }
+
+        result
+    }
+}
+
 /// Read the buffered data from an instance's serial console.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment, trailing_var_arg = true)]
@@ -494,11 +1826,61 @@ pub struct CmdInstanceSerial {
     #[clap(long, short)]
     pub continuous: bool,
 
+    /// Write the output to this file instead of stdout. The file is appended to.
+    #[clap(long)]
+    pub output: Option<std::path::PathBuf>,
+
+    /// Start a new output file once it reaches this size, e.g. `10MiB`. The previous
+    /// files are kept as `<output>.1`, the most recent, `<output>.2`, and so on.
+    #[clap(long, requires = "output", parse(try_from_str = crate::serial::parse_size))]
+    pub rotate_size: Option<u64>,
+
+    /// How many of the previous output files to keep with `--rotate-size`.
+    #[clap(long, default_value_t = crate::serial::DEFAULT_ROTATE_KEEP)]
+    pub rotate_keep: usize,
+
+    /// Prefix each line with the time it was received.
+    #[clap(long)]
+    pub timestamps: bool,
+
+    /// Remove the ANSI escape sequences, e.g. colors, from the output.
+    #[clap(long)]
+    pub strip_ansi: bool,
+
+    /// Stop successfully once a line of the output matches this regular expression,
+    /// e.g. `login:` to wait for an instance to boot.
+    #[clap(long, requires = "continuous")]
+    pub until: Option<regex::Regex>,
+
     /// Whether to connect interactively (read/write) to the running instance's serial console.
-    /// (NOTE: ignores --byte-offset, --max-bytes, and --continuous)
+    /// (NOTE: ignores --byte-offset, --max-bytes, --continuous, and the output options)
+    /// Type `~?` at the start of a line for the escape sequences, e.g. `~.` to disconnect.
+    /// They, and what Ctrl-C does, can be changed with the `serial_escape_char` and
+    /// `serial_ctrl_c` config.
     #[cfg(unix)]
     #[clap(long, short)]
     pub interactive: bool,
+
+    /// Record the interactive session to this file, in the asciicast v2 format. Play it
+    /// back with `asciinema play`.
+    #[cfg(unix)]
+    #[clap(long, requires = "interactive")]
+    pub record: Option<std::path::PathBuf>,
+
+    /// Stay connected when the instance reboots, or stops: wait for it to be running
+    /// again and reconnect to its serial console. Without `--interactive` this implies
+    /// `--continuous`, and the output of each boot is read from its start.
+    #[clap(long)]
+    pub follow: bool,
+
+    /// Run a script against the serial console, without a terminal, e.g. to log in and
+    /// check that an image boots. One command per line: `expect <regex>`, `capture <regex>`
+    /// to also print the output before the match, `send <line>`, `write <text>` to send
+    /// without a carriage return, `sleep <duration>` and `timeout <duration>` for the
+    /// following expects, 30s by default. Fails when an expect times out.
+    #[cfg(unix)]
+    #[clap(long, conflicts_with = "interactive")]
+    pub script: Option<std::path::PathBuf>,
 }
 
 #[async_trait::async_trait]
@@ -508,6 +1890,10 @@ impl crate::cmd::Command for CmdInstanceSerial {
         if self.interactive {
             return self.websock_stream_tty(ctx).await;
         }
+        #[cfg(unix)]
+        if let Some(script) = &self.script {
+            return self.run_script(ctx, script).await;
+        }
 
         let client = ctx.api_client("")?;
 
@@ -521,9 +1907,19 @@ impl crate::cmd::Command for CmdInstanceSerial {
             None => most_recent = Some(16384),
         }
 
+        let mut file;
+        let out: &mut (dyn std::io::Write + Send) = match &self.output {
+            Some(path) => {
+                file = crate::serial::RotatingFile::open(path, self.rotate_size, self.rotate_keep)?;
+                &mut file
+            }
+            None => &mut ctx.io.out,
+        };
+        let mut out = crate::serial::Output::new(out, self.timestamps, self.strip_ansi, self.until.clone());
+
         let mut cont = true;
         while cont {
-            let output = client
+            let result = client
                 .instances()
                 .serial_get(
                     from_start,
@@ -533,11 +1929,38 @@ impl crate::cmd::Command for CmdInstanceSerial {
                     &self.organization,
                     &self.project,
                 )
-                .await?;
+                .await;
+
+            // With `--follow`, the console going away while the instance reboots or is
+            // stopped, or its output starting over, is the end of a boot.
+            let output = match result {
+                Ok(output) if self.follow && from_start.map_or(false, |from| output.last_byte_offset < from) => None,
+                Ok(output) => Some(output),
+                Err(err) if self.follow => {
+                    if self.is_running(&client).await? {
+                        return Err(err.into());
+                    }
+                    None
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let output = match output {
+                Some(output) => output,
+                None => {
+                    if self.wait_for_next_boot(&client, &mut out).await? {
+                        break;
+                    }
+                    most_recent = None;
+                    from_start = Some(0);
+                    continue;
+                }
+            };
 
-            std::io::stdout().write_all(&output.data)?;
+            if out.write(&output.data)? {
+                break;
+            }
 
-            cont = self.continuous;
+            cont = self.continuous || self.follow;
             most_recent = None;
             from_start = Some(output.last_byte_offset);
 
@@ -546,12 +1969,58 @@ impl crate::cmd::Command for CmdInstanceSerial {
             }
         }
 
-        println!("\x1b[0m");
+        // Reset the colors the instance left the terminal with.
+        if self.output.is_none() && !self.strip_ansi {
+            writeln!(ctx.io.out, "\x1b[0m")?;
+        }
 
         Ok(())
     }
 }
 
+impl CmdInstanceSerial {
+    async fn is_running(&self, client: &oxide_api::Client) -> Result<bool> {
+        let instance = client
+            .instances()
+            .get(&self.instance, &self.organization, &self.project)
+            .await?;
+
+        Ok(instance.run_state == oxide_api::types::InstanceState::Running)
+    }
+
+    /// Wait for the instance to be running again, then mark the start of its new output.
+    /// Returns whether the marker matched `--until`.
+    async fn wait_for_next_boot<W: std::io::Write>(
+        &self,
+        client: &oxide_api::Client,
+        out: &mut crate::serial::Output<W>,
+    ) -> Result<bool> {
+        let details = InstanceDetails {
+            host: "".to_string(),
+            organization: self.organization.to_string(),
+            project: self.project.to_string(),
+            instance: self.instance.to_string(),
+        };
+        // However long it takes, Ctrl-C stops the waiting.
+        let opts = crate::wait::WaitOptions {
+            timeout: std::time::Duration::ZERO,
+            ..Default::default()
+        };
+        details
+            .poll_state(client, &oxide_api::types::InstanceState::Running, &opts, |_| {})
+            .await?;
+
+        Ok(out.write(
+            format!(
+                "\r\n[---------- {} is running again at {} ----------]\r\n",
+                self.instance,
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+            )
+            .as_bytes(),
+        )?)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
@@ -566,6 +2035,21 @@ mod test {
         want_err: String,
     }
 
+    #[test]
+    fn test_instance_template() {
+        let template: serde_json::Value = serde_yaml::from_str(crate::cmd_instance::INSTANCE_TEMPLATE).unwrap();
+        let errors = crate::schema::validate("InstanceCreate", &template).unwrap();
+
+        assert_eq!(errors, vec![]);
+    }
+
+    #[test]
+    fn test_cloned_name() {
+        assert_eq!(crate::cmd_instance::cloned_name("web", "web", "web2"), "web2");
+        assert_eq!(crate::cmd_instance::cloned_name("web-boot", "web", "web2"), "web2-boot");
+        assert_eq!(crate::cmd_instance::cloned_name("data", "web", "web2"), "web2-data");
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_cmd_instance() {
         let tests: Vec<TestItem> = vec![
@@ -581,9 +2065,16 @@ mod test {
                     hostname: "holla".to_string(),
                     network_interfaces: Default::default(),
                     disks: Default::default(),
-                    user_data: "some data".to_string(),
+                    user_data: None,
+                    cloud_config: None,
+                    ssh_keys: Default::default(),
+                    from_file: None,
+                    emit_template: false,
                     external_ips: Vec::from(["mypool".to_string()]),
                     start: true,
+                    timeout: Default::default(),
+                    poll_interval: Default::default(),
+                    no_wait: false,
                 }),
 
                 stdin: "".to_string(),
@@ -602,9 +2093,16 @@ mod test {
                     hostname: "holla".to_string(),
                     network_interfaces: Default::default(),
                     disks: Default::default(),
-                    user_data: "some data".to_string(),
+                    user_data: None,
+                    cloud_config: None,
+                    ssh_keys: Default::default(),
+                    from_file: None,
+                    emit_template: false,
                     external_ips: Vec::from(["mypool".to_string()]),
                     start: true,
+                    timeout: Default::default(),
+                    poll_interval: Default::default(),
+                    no_wait: false,
                 }),
 
                 stdin: "".to_string(),
@@ -623,9 +2121,16 @@ mod test {
                     hostname: "holla".to_string(),
                     network_interfaces: Default::default(),
                     disks: Default::default(),
-                    user_data: "some data".to_string(),
+                    user_data: None,
+                    cloud_config: None,
+                    ssh_keys: Default::default(),
+                    from_file: None,
+                    emit_template: false,
                     external_ips: Vec::from(["mypool".to_string()]),
                     start: true,
+                    timeout: Default::default(),
+                    poll_interval: Default::default(),
+                    no_wait: false,
                 }),
 
                 stdin: "".to_string(),
@@ -644,9 +2149,16 @@ mod test {
                     hostname: "".to_string(),
                     network_interfaces: Default::default(),
                     disks: Default::default(),
-                    user_data: "some data".to_string(),
+                    user_data: None,
+                    cloud_config: None,
+                    ssh_keys: Default::default(),
+                    from_file: None,
+                    emit_template: false,
                     external_ips: Vec::from(["mypool".to_string()]),
                     start: true,
+                    timeout: Default::default(),
+                    poll_interval: Default::default(),
+                    no_wait: false,
                 }),
 
                 stdin: "".to_string(),
@@ -665,9 +2177,16 @@ mod test {
                     hostname: "sup".to_string(),
                     network_interfaces: Default::default(),
                     disks: Default::default(),
-                    user_data: "some data".to_string(),
+                    user_data: None,
+                    cloud_config: None,
+                    ssh_keys: Default::default(),
+                    from_file: None,
+                    emit_template: false,
                     external_ips: Vec::from(["mypool".to_string()]),
                     start: true,
+                    timeout: Default::default(),
+                    poll_interval: Default::default(),
+                    no_wait: false,
                 }),
 
                 stdin: "".to_string(),
@@ -686,9 +2205,16 @@ mod test {
                     hostname: "sup".to_string(),
                     network_interfaces: Default::default(),
                     disks: Default::default(),
-                    user_data: "some data".to_string(),
+                    user_data: None,
+                    cloud_config: None,
+                    ssh_keys: Default::default(),
+                    from_file: None,
+                    emit_template: false,
                     external_ips: Vec::from(["mypool".to_string()]),
                     start: true,
+                    timeout: Default::default(),
+                    poll_interval: Default::default(),
+                    no_wait: false,
                 }),
 
                 stdin: "".to_string(),
@@ -698,9 +2224,13 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_instance::SubCommand::Delete(crate::cmd_instance::CmdInstanceDelete {
-                    instance: "things".to_string(),
+                    instance: vec!["things".to_string()],
                     organization: "".to_string(),
                     project: "".to_string(),
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
                     confirm: false,
                 }),
 
@@ -716,6 +2246,7 @@ mod test {
                     project: "".to_string(),
                     paginate: false,
                     format: None,
+                    watch: None,
                     sort_by: Default::default(),
                 }),
 
@@ -723,6 +2254,38 @@ mod test {
                 want_out: "".to_string(),
                 want_err: "--limit must be greater than 0".to_string(),
             },
+            TestItem {
+                name: "cp no instance".to_string(),
+                cmd: crate::cmd_instance::SubCommand::Cp(crate::cmd_instance::CmdInstanceCp {
+                    source: "app.toml".to_string(),
+                    destination: "/etc/app.toml".to_string(),
+                    organization: "".to_string(),
+                    project: "".to_string(),
+                    user: "root".to_string(),
+                    recursive: false,
+                    ssh_flags: Default::default(),
+                }),
+
+                stdin: "".to_string(),
+                want_out: "".to_string(),
+                want_err: "exactly one of the paths must be on an instance, written `<instance>:<path>`".to_string(),
+            },
+            TestItem {
+                name: "cp directory without --recursive".to_string(),
+                cmd: crate::cmd_instance::SubCommand::Cp(crate::cmd_instance::CmdInstanceCp {
+                    source: ".".to_string(),
+                    destination: "web-1:/tmp".to_string(),
+                    organization: "".to_string(),
+                    project: "".to_string(),
+                    user: "root".to_string(),
+                    recursive: false,
+                    ssh_flags: Default::default(),
+                }),
+
+                stdin: "".to_string(),
+                want_out: "".to_string(),
+                want_err: ". is a directory, use -r to copy it".to_string(),
+            },
         ];
 
         let mut config = crate::config::new_blank_config().unwrap();
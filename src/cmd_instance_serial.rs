@@ -1,8 +1,9 @@
 use std::{mem::swap, os::unix::io::AsRawFd, time::Duration};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use futures::{SinkExt, StreamExt};
 use http::HeaderMap;
+use parse_display::{Display, FromStr};
 use reqwest::ClientBuilder;
 use tokio_tungstenite::{
     tungstenite::protocol::{Message, Role},
@@ -13,41 +14,73 @@ mod nexus_client {
     progenitor::generate_api!(spec = "spec-serial.json", interface = Builder,);
 }
 
+/// Open a websocket to the serial console of an instance.
+pub(crate) async fn connect(
+    ctx: &mut crate::context::Context<'_>,
+    organization: &str,
+    project: &str,
+    instance: &str,
+) -> Result<WebSocketStream<reqwest::Upgraded>> {
+    // shenanigans to get the info we need to construct a progenitor-client
+    let reqw = ctx
+        .api_client("")?
+        .request_raw(http::Method::GET, "", None)
+        .await?
+        .build()?;
+
+    let base = reqw.url().as_str();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        reqw.headers().get(http::header::AUTHORIZATION).unwrap().to_owned(),
+    );
+
+    let reqw_client = ClientBuilder::new()
+        .connect_timeout(Duration::new(60, 0))
+        .default_headers(headers)
+        .http1_only() // HTTP2 does not support websockets
+        .build()?;
+
+    let nexus_client = nexus_client::Client::new_with_client(base, reqw_client);
+
+    let upgraded = nexus_client
+        .instance_serial_console_stream()
+        .organization_name(organization.to_owned())
+        .project_name(project.to_owned())
+        .instance_name(instance.to_owned())
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .into_inner();
+
+    Ok(WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await)
+}
+
 impl super::cmd_instance::CmdInstanceSerial {
     pub(crate) async fn websock_stream_tty(&self, ctx: &mut crate::context::Context<'_>) -> Result<()> {
-        // shenanigans to get the info we need to construct a progenitor-client
-        let reqw = ctx
-            .api_client("")?
-            .request_raw(http::Method::GET, "", None)
-            .await?
-            .build()?;
-
-        let base = reqw.url().as_str();
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            http::header::AUTHORIZATION,
-            reqw.headers().get(http::header::AUTHORIZATION).unwrap().to_owned(),
-        );
-
-        let reqw_client = ClientBuilder::new()
-            .connect_timeout(Duration::new(60, 0))
-            .default_headers(headers)
-            .http1_only() // HTTP2 does not support websockets
-            .build()?;
-
-        let nexus_client = nexus_client::Client::new_with_client(base, reqw_client);
-
-        let upgraded = nexus_client
-            .instance_serial_console_stream()
-            .organization_name(self.organization.to_owned())
-            .project_name(self.project.to_owned())
-            .instance_name(self.instance.to_owned())
-            .send()
-            .await
-            .map_err(|e| anyhow::anyhow!("{}", e))?
-            .into_inner();
+        let ctrl_c = match ctx.config.get("", "serial_ctrl_c") {
+            Ok(value) => value
+                .parse::<CtrlC>()
+                .map_err(|_| anyhow!("invalid serial_ctrl_c `{}`, expected `send` or `disconnect`", value))?,
+            Err(_) => CtrlC::Send,
+        };
+        let escape = parse_escape_char(
+            &ctx.config
+                .get("", "serial_escape_char")
+                .unwrap_or_else(|_| crate::serial::DEFAULT_ESCAPE_CHAR.to_string()),
+        )?;
+
+        let (width, height) = (ctx.io.terminal_width(), ctx.io.terminal_height());
+        let mut recording = match &self.record {
+            Some(path) => Some(crate::serial::Recording::new(
+                std::fs::File::create(path)?,
+                width,
+                height,
+            )?),
+            None => None,
+        };
 
-        let mut ws = WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await;
+        let mut ws = connect(ctx, &self.organization, &self.project, &self.instance).await?;
 
         let mut stdin: Box<dyn std::io::Read + Send + Sync> = Box::new(std::io::empty());
         let mut stdout: Box<dyn std::io::Write + Send + Sync> = Box::new(std::io::sink());
@@ -66,7 +99,7 @@ impl super::cmd_instance::CmdInstanceSerial {
         // is not cancel safe! Meaning reads from tokio::io::stdin are not cancel
         // safe. Spawn a separate task to read and put bytes onto this channel.
         let (stdintx, stdinrx) = tokio::sync::mpsc::channel(16);
-        let (wstx, mut wsrx) = tokio::sync::mpsc::channel(16);
+        let (inputtx, mut inputrx) = tokio::sync::mpsc::channel(16);
 
         tokio::spawn(async move {
             let mut inbuf = [0u8; 1024];
@@ -81,47 +114,204 @@ impl super::cmd_instance::CmdInstanceSerial {
             }
         });
 
-        tokio::spawn(async move { stdin_to_websockets_task(stdinrx, wstx).await });
+        let escapes = Escapes::new(escape, ctrl_c);
+        tokio::spawn(async move { stdin_to_websockets_task(stdinrx, inputtx, escapes).await });
 
         loop {
-            tokio::select! {
-                c = wsrx.recv() => {
-                    match c {
-                        None => {
-                            // channel is closed
-                            break;
+            let disconnected = loop {
+                tokio::select! {
+                    c = inputrx.recv() => {
+                        match c {
+                            None | Some(Input::Disconnect) => {
+                                // channel is closed
+                                break true;
+                            }
+                            Some(Input::Data(c)) => {
+                                ws.send(Message::Binary(c)).await?;
+                            },
+                            Some(Input::Help) => say(&mut stdout, &help(escape.unwrap_or(b'~')))?,
+                            Some(Input::Break) => {
+                                say(&mut stdout, "[sending a break is not supported by the serial console API yet]")?;
+                            }
+                            Some(Input::ToggleRecording) => match &mut recording {
+                                Some(recording) if recording.is_paused() => {
+                                    recording.resume();
+                                    say(&mut stdout, "[recording resumed]")?;
+                                }
+                                Some(recording) => {
+                                    recording.pause();
+                                    say(&mut stdout, "[recording paused]")?;
+                                }
+                                None => {
+                                    let path = format!(
+                                        "{}-{}.cast",
+                                        self.instance,
+                                        chrono::Local::now().format("%Y%m%d-%H%M%S")
+                                    );
+                                    recording = Some(crate::serial::Recording::new(
+                                        std::fs::File::create(&path)?,
+                                        width,
+                                        height,
+                                    )?);
+                                    say(&mut stdout, &format!("[recording to {}]", path))?;
+                                }
+                            },
+                        }
+                    }
+                    msg = ws.next() => {
+                        match msg {
+                            Some(Ok(Message::Binary(input))) => {
+                                tokio::task::block_in_place(|| {
+                                    stdout.write_all(&input)?;
+                                    stdout.flush()?;
+                                    Ok::<(), std::io::Error>(())
+                                })?;
+
+                                if let Some(recording) = &mut recording {
+                                    recording.output(&input)?;
+                                }
+                            }
+                            Some(Ok(Message::Close(..))) | None => break false,
+                            _ => continue,
                         }
-                        Some(c) => {
-                            ws.send(Message::Binary(c)).await?;
-                        },
                     }
                 }
-                msg = ws.next() => {
-                    match msg {
-                        Some(Ok(Message::Binary(input))) => {
-                            tokio::task::block_in_place(|| {
-                                stdout.write_all(&input)?;
-                                stdout.flush()?;
-                                Ok::<(), std::io::Error>(())
-                            })?;
+            };
+
+            if disconnected || !self.follow {
+                break;
+            }
+
+            say(
+                &mut stdout,
+                &format!(
+                    "[the serial console of {} closed, waiting for the instance to be running again]",
+                    self.instance
+                ),
+            )?;
+            ws = tokio::select! {
+                ws = self.reconnect(ctx) => ws?,
+                _ = until_disconnect(&mut inputrx) => break,
+            };
+            say(
+                &mut stdout,
+                &format!(
+                    "[---------- reconnected at {} ----------]",
+                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+                ),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Run a `--script` against the serial console. Unlike interactive sessions, this
+    /// does not need a terminal.
+    pub(crate) async fn run_script(&self, ctx: &mut crate::context::Context<'_>, path: &std::path::Path) -> Result<()> {
+        let script = std::fs::read_to_string(path).map_err(|e| anyhow!("reading {}: {}", path.display(), e))?;
+        let script = crate::serial_script::Script::parse(&script).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+        let mut ws = connect(ctx, &self.organization, &self.project, &self.instance).await?;
+        let mut pending = crate::serial_script::Pending::default();
+
+        for (line, command) in script.commands {
+            match command {
+                crate::serial_script::Command::Send(data) => ws.send(Message::Binary(data)).await?,
+                crate::serial_script::Command::Sleep(duration) => tokio::time::sleep(duration).await,
+                crate::serial_script::Command::Expect {
+                    regex,
+                    timeout,
+                    capture,
+                } => {
+                    let deadline = tokio::time::Instant::now() + timeout;
+                    let before = loop {
+                        if let Some(before) = pending.find(&regex) {
+                            break before;
+                        }
+
+                        match tokio::time::timeout_at(deadline, ws.next()).await {
+                            Ok(Some(Ok(Message::Binary(data)))) => pending.push(&data),
+                            Ok(Some(Ok(Message::Close(..)))) | Ok(None) => {
+                                return Err(anyhow!(
+                                    "{}:{}: the serial console closed while waiting for `{}`",
+                                    path.display(),
+                                    line,
+                                    regex
+                                ));
+                            }
+                            Ok(Some(Ok(_))) => continue,
+                            Ok(Some(Err(e))) => return Err(e.into()),
+                            Err(_) => {
+                                return Err(anyhow!(
+                                    "{}:{}: timed out after {:?} waiting for `{}`, the last output was:\n{}",
+                                    path.display(),
+                                    line,
+                                    timeout,
+                                    regex,
+                                    pending.tail(5)
+                                ));
+                            }
+                        }
+                    };
+
+                    if capture {
+                        let before = String::from_utf8_lossy(&before).replace('\r', "");
+                        write!(ctx.io.out, "{}", before)?;
+                        if !before.is_empty() && !before.ends_with('\n') {
+                            writeln!(ctx.io.out)?;
                         }
-                        Some(Ok(Message::Close(..))) | None => break,
-                        _ => continue,
                     }
                 }
             }
         }
 
+        ws.close(None).await.ok();
+
         Ok(())
     }
+
+    /// Wait for the instance to be running again, e.g. after a reboot, then reconnect
+    /// to its serial console.
+    async fn reconnect(&self, ctx: &mut crate::context::Context<'_>) -> Result<WebSocketStream<reqwest::Upgraded>> {
+        let client = ctx.api_client("")?;
+        let details = crate::cmd_instance::InstanceDetails {
+            host: "".to_string(),
+            organization: self.organization.to_string(),
+            project: self.project.to_string(),
+            instance: self.instance.to_string(),
+        };
+        // However long it takes, the user can always disconnect with the escape sequence.
+        let opts = crate::wait::WaitOptions {
+            timeout: Duration::ZERO,
+            ..Default::default()
+        };
+
+        let mut attempts = 0;
+        loop {
+            details
+                .poll_state(&client, &oxide_api::types::InstanceState::Running, &opts, |_| {})
+                .await?;
+
+            // The console can close before the instance leaves the running state, give
+            // it a chance to.
+            match connect(ctx, &self.organization, &self.project, &self.instance).await {
+                Ok(ws) => return Ok(ws),
+                Err(err) if attempts >= RECONNECT_ATTEMPTS => return Err(err),
+                Err(_) => {
+                    attempts += 1;
+                    tokio::time::sleep(RECONNECT_INTERVAL).await;
+                }
+            }
+        }
+    }
 }
 
 /// Guard object that will set the terminal to raw mode and restore it
 /// to its previous state when it's dropped
-struct RawTermiosGuard(libc::c_int, libc::termios);
+pub(crate) struct RawTermiosGuard(libc::c_int, libc::termios);
 
 impl RawTermiosGuard {
-    fn stdio_guard() -> Result<RawTermiosGuard, std::io::Error> {
+    pub(crate) fn stdio_guard() -> Result<RawTermiosGuard, std::io::Error> {
         let fd = std::io::stdout().as_raw_fd();
         let termios = unsafe {
             let mut curr_termios = std::mem::zeroed();
@@ -153,63 +343,177 @@ impl Drop for RawTermiosGuard {
     }
 }
 
-async fn stdin_to_websockets_task(
-    mut stdinrx: tokio::sync::mpsc::Receiver<Vec<u8>>,
-    wstx: tokio::sync::mpsc::Sender<Vec<u8>>,
-) {
-    // next_raw must live outside loop, because Ctrl-A should work across
+/// How many times `--follow` tries to reconnect to an instance that looks running
+/// before giving up, and how long it waits between attempts.
+const RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What Ctrl-C does in interactive sessions, configured with `serial_ctrl_c`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromStr, Display)]
+#[display(style = "lowercase")]
+pub(crate) enum CtrlC {
+    /// Send it to the instance, e.g. to interrupt a program.
+    Send,
+    /// Disconnect. Ctrl-A then sends the next character as is, e.g. Ctrl-A Ctrl-C
+    /// sends a Ctrl-C.
+    Disconnect,
+}
+
+/// Parse the `serial_escape_char` config, a single character or `none`.
+fn parse_escape_char(value: &str) -> Result<Option<u8>> {
+    match value.as_bytes() {
+        b"none" => Ok(None),
+        [c] if c.is_ascii() => Ok(Some(*c)),
+        _ => Err(anyhow!(
+            "invalid serial_escape_char `{}`, expected a single character or `none`",
+            value
+        )),
+    }
+}
+
+fn help(escape: u8) -> String {
+    let escape = escape as char;
+    [
+        "Supported escape sequences:".to_string(),
+        format!(" {}.   - disconnect", escape),
+        format!(" {}b   - send a break", escape),
+        format!(" {}r   - start, pause or resume recording the session", escape),
+        format!(" {}?   - this message", escape),
+        format!(" {}{}   - send the escape character by typing it twice", escape, escape),
+        "(Note that escapes are only recognized immediately after newline.)".to_string(),
+    ]
+    .join("\r\n")
+}
+
+/// Write a message from us, rather than the instance, on its own line.
+fn say(stdout: &mut dyn std::io::Write, message: &str) -> std::io::Result<()> {
+    tokio::task::block_in_place(|| {
+        write!(stdout, "\r\n{}\r\n", message)?;
+        stdout.flush()
+    })
+}
+
+/// What the user typed in an interactive session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Input {
+    /// Bytes to send to the instance.
+    Data(Vec<u8>),
+    Help,
+    Break,
+    ToggleRecording,
+    Disconnect,
+}
+
+/// Splits what the user types into the bytes to send to the instance and escape
+/// sequences. Like with ssh, the escape character is only recognized at the start of
+/// a line.
+struct Escapes {
+    escape: Option<u8>,
+    ctrl_c: CtrlC,
+    at_line_start: bool,
+    after_escape: bool,
+    // next_raw must live outside feed, because Ctrl-A should work across
     // multiple inbuf reads.
-    let mut next_raw = false;
+    next_raw: bool,
+}
 
-    loop {
-        let inbuf = if let Some(inbuf) = stdinrx.recv().await {
-            inbuf
-        } else {
-            continue;
-        };
+impl Escapes {
+    fn new(escape: Option<u8>, ctrl_c: CtrlC) -> Self {
+        Escapes {
+            escape,
+            ctrl_c,
+            at_line_start: true,
+            after_escape: false,
+            next_raw: false,
+        }
+    }
 
-        // Put bytes from inbuf to outbuf, but don't send Ctrl-A unless
-        // next_raw is true.
+    fn feed(&mut self, inbuf: &[u8]) -> Vec<Input> {
+        let mut inputs = Vec::new();
         let mut outbuf = Vec::with_capacity(inbuf.len());
 
-        let mut exit = false;
-        for c in inbuf {
-            match c {
-                // Ctrl-A means send next one raw
-                b'\x01' => {
-                    if next_raw {
-                        // Ctrl-A Ctrl-A should be sent as Ctrl-A
+        for &c in inbuf {
+            let command = if self.after_escape {
+                self.after_escape = false;
+
+                match c {
+                    b'.' => Some(Input::Disconnect),
+                    b'?' => Some(Input::Help),
+                    b'b' => Some(Input::Break),
+                    b'r' => Some(Input::ToggleRecording),
+                    c if Some(c) == self.escape => {
                         outbuf.push(c);
-                        next_raw = false;
-                    } else {
-                        next_raw = true;
+                        None
                     }
-                }
-                b'\x03' => {
-                    if !next_raw {
-                        // Exit on non-raw Ctrl-C
-                        exit = true;
-                        break;
-                    } else {
-                        // Otherwise send Ctrl-C
-                        outbuf.push(c);
-                        next_raw = false;
+                    c => {
+                        // Not an escape sequence after all, send both.
+                        outbuf.extend([self.escape.unwrap(), c]);
+                        None
                     }
                 }
-                _ => {
-                    outbuf.push(c);
-                    next_raw = false;
+            } else if self.at_line_start && Some(c) == self.escape && !self.next_raw {
+                self.after_escape = true;
+                continue;
+            } else if self.ctrl_c == CtrlC::Disconnect && !self.next_raw && c == b'\x01' {
+                // Ctrl-A means send next one raw
+                self.next_raw = true;
+                continue;
+            } else if self.ctrl_c == CtrlC::Disconnect && !self.next_raw && c == b'\x03' {
+                // Exit on non-raw Ctrl-C
+                Some(Input::Disconnect)
+            } else {
+                outbuf.push(c);
+                self.next_raw = false;
+                None
+            };
+
+            match command {
+                Some(command) => {
+                    // Send what we have before acting on the escape sequence.
+                    if !outbuf.is_empty() {
+                        inputs.push(Input::Data(std::mem::take(&mut outbuf)));
+                    }
+
+                    let disconnect = command == Input::Disconnect;
+                    inputs.push(command);
+                    if disconnect {
+                        return inputs;
+                    }
+
+                    self.at_line_start = true;
                 }
+                None => self.at_line_start = c == b'\r' || c == b'\n',
             }
         }
 
-        // Send what we have, even if there's a Ctrl-C at the end.
         if !outbuf.is_empty() {
-            wstx.send(outbuf).await.unwrap();
+            inputs.push(Input::Data(outbuf));
         }
 
-        if exit {
-            break;
+        inputs
+    }
+}
+
+/// Wait until the user disconnects, ignoring whatever else they type.
+async fn until_disconnect(inputrx: &mut tokio::sync::mpsc::Receiver<Input>) {
+    while let Some(input) = inputrx.recv().await {
+        if input == Input::Disconnect {
+            return;
+        }
+    }
+}
+
+async fn stdin_to_websockets_task(
+    mut stdinrx: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    inputtx: tokio::sync::mpsc::Sender<Input>,
+    mut escapes: Escapes,
+) {
+    while let Some(inbuf) = stdinrx.recv().await {
+        for input in escapes.feed(&inbuf) {
+            let disconnect = input == Input::Disconnect;
+            if inputtx.send(input).await.is_err() || disconnect {
+                return;
+            }
         }
     }
 }
@@ -274,7 +578,16 @@ mod test {
             max_bytes: None,
             byte_offset: None,
             continuous: false,
+            output: None,
+            rotate_size: None,
+            rotate_keep: crate::serial::DEFAULT_ROTATE_KEEP,
+            timestamps: false,
+            strip_ansi: false,
+            until: None,
             interactive: true,
+            record: None,
+            follow: false,
+            script: None,
         };
         let mut config = crate::config::new_blank_config().unwrap();
         let mut c = crate::config_from_env::EnvConfig::inherit_env(&mut config);
@@ -292,4 +605,29 @@ mod test {
         assert!(stderr.is_empty());
         assert_eq!(stdout, "");
     }
+
+    #[test]
+    fn test_escapes() {
+        use super::{CtrlC, Escapes, Input};
+
+        let mut escapes = Escapes::new(Some(b'~'), CtrlC::Send);
+        assert_eq!(
+            escapes.feed(b"~?ls ~/\x03\r~"),
+            vec![Input::Help, Input::Data(b"ls ~/\x03\r".to_vec())]
+        );
+        assert_eq!(
+            escapes.feed(b"~\r~r~b"),
+            vec![Input::Data(b"~\r".to_vec()), Input::ToggleRecording, Input::Break]
+        );
+        assert_eq!(
+            escapes.feed(b"\n~x\n~."),
+            vec![Input::Data(b"\n~x\n".to_vec()), Input::Disconnect]
+        );
+
+        let mut escapes = Escapes::new(None, CtrlC::Disconnect);
+        assert_eq!(
+            escapes.feed(b"~.\x01\x03\x01\x01x\x03ignored"),
+            vec![Input::Data(b"~.\x03\x01x".to_vec()), Input::Disconnect]
+        );
+    }
 }
@@ -116,7 +116,12 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_org::SubCommand::Delete(crate::cmd_org::CmdOrganizationDelete {
-                    organization: "things".to_string(),
+                    organization: vec!["things".to_string()],
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
+                    recursive: false,
                     confirm: false,
                 }),
 
@@ -131,6 +136,7 @@ mod test {
                     limit: 0,
                     paginate: false,
                     format: None,
+                    watch: None,
                 }),
 
                 stdin: "".to_string(),
@@ -144,6 +150,7 @@ mod test {
                     limit: 30,
                     paginate: true,
                     format: Some(crate::types::FormatOutput::Json),
+                    watch: None,
                 }),
 
                 stdin: "".to_string(),
@@ -87,8 +87,13 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_project::SubCommand::Delete(crate::cmd_project::CmdProjectDelete {
-                    project: "things".to_string(),
+                    project: vec!["things".to_string()],
                     organization: "".to_string(),
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
+                    recursive: false,
                     confirm: false,
                 }),
 
@@ -104,6 +109,7 @@ mod test {
                     organization: "".to_string(),
                     paginate: false,
                     format: None,
+                    watch: None,
                 }),
 
                 stdin: "".to_string(),
@@ -190,11 +190,15 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_route::SubCommand::Delete(crate::cmd_route::CmdRouteDelete {
-                    route: "things".to_string(),
+                    route: vec!["things".to_string()],
                     organization: "".to_string(),
                     project: "".to_string(),
                     vpc: "things".to_string(),
                     router: "blah".to_string(),
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
                     confirm: false,
                 }),
 
@@ -213,6 +217,7 @@ mod test {
                     router: "blah".to_string(),
                     paginate: false,
                     format: None,
+                    watch: None,
                 }),
 
                 stdin: "".to_string(),
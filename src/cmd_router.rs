@@ -121,10 +121,14 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_router::SubCommand::Delete(crate::cmd_router::CmdRouterDelete {
-                    router: "things".to_string(),
+                    router: vec!["things".to_string()],
                     organization: "".to_string(),
                     project: "".to_string(),
                     vpc: "things".to_string(),
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
                     confirm: false,
                 }),
 
@@ -142,6 +146,7 @@ mod test {
                     project: "".to_string(),
                     paginate: false,
                     format: None,
+                    watch: None,
                 }),
 
                 stdin: "".to_string(),
@@ -1,8 +1,12 @@
-use anyhow::Result;
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
 use cli_macro::crud_gen;
 
 /// Create, list, view, and delete snapshots.
+///
+/// Additionally, restore snapshots to new disks and prune old snapshots.
 #[derive(Parser, Debug, Clone)]
 #[clap(verbatim_doc_comment)]
 pub struct CmdSnapshot {
@@ -14,7 +18,10 @@ pub struct CmdSnapshot {
     tag = "snapshots",
 }]
 #[derive(Parser, Debug, Clone)]
-enum SubCommand {}
+enum SubCommand {
+    Prune(CmdSnapshotPrune),
+    Restore(CmdSnapshotRestore),
+}
 
 #[async_trait::async_trait]
 impl crate::cmd::Command for CmdSnapshot {
@@ -23,7 +30,351 @@ impl crate::cmd::Command for CmdSnapshot {
             SubCommand::Create(cmd) => cmd.run(ctx).await,
             SubCommand::Delete(cmd) => cmd.run(ctx).await,
             SubCommand::List(cmd) => cmd.run(ctx).await,
+            SubCommand::Prune(cmd) => cmd.run(ctx).await,
+            SubCommand::Restore(cmd) => cmd.run(ctx).await,
             SubCommand::View(cmd) => cmd.run(ctx).await,
         }
     }
 }
+
+/// Restore a snapshot to a new disk.
+///
+/// The disk is created from the snapshot, with the size of the snapshot unless
+/// `--size` asks for a larger one, and is ready to be attached to an instance.
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdSnapshotRestore {
+    /// The snapshot to restore. Can be an ID or name.
+    #[clap(name = "snapshot", required = true)]
+    pub snapshot: String,
+
+    /// The name of the disk to create.
+    #[clap(long, required = true)]
+    pub new_disk: String,
+
+    /// The project that holds the snapshot, and the new disk.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The description for the disk.
+    #[clap(long = "description", short = 'D', default_value = "")]
+    pub description: String,
+
+    /// The size of the disk, e.g. `20GiB`. Defaults to the size of the snapshot.
+    #[clap(long, parse(try_from_str = crate::types::parse_byte_count))]
+    pub size: Option<oxide_api::types::ByteCount>,
+
+    /// How long to wait for the disk to be created, e.g. `90s` or `5m`. Use `0` to
+    /// wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check the state of the disk, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
+
+    /// Return once the disk is created, without waiting for it to be ready.
+    #[clap(long)]
+    pub no_wait: bool,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdSnapshotRestore {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let client = ctx.api_client("")?;
+
+        let snapshot = client
+            .snapshots()
+            .get(&self.organization, &self.project, &self.snapshot)
+            .await?;
+
+        let size = self.size.unwrap_or(snapshot.size);
+        if size < snapshot.size {
+            bail!("the disk cannot be smaller than the snapshot, {} bytes", snapshot.size);
+        }
+
+        client
+            .disks()
+            .post(
+                &self.organization,
+                &self.project,
+                &oxide_api::types::DiskCreate {
+                    description: self.description.to_string(),
+                    disk_source: oxide_api::types::DiskSource::Snapshot {
+                        snapshot_id: snapshot.id.to_string(),
+                    },
+                    name: self.new_disk.to_string(),
+                    size,
+                },
+            )
+            .await?;
+
+        if !self.no_wait {
+            crate::cmd_disk::wait_for_disk_state(
+                ctx,
+                &self.organization,
+                &self.project,
+                &self.new_disk,
+                "detached",
+                &crate::wait::WaitOptions {
+                    timeout: self.timeout,
+                    poll_interval: self.poll_interval,
+                },
+            )
+            .await?;
+        }
+
+        let cs = ctx.io.color_scheme();
+        writeln!(
+            ctx.io.out,
+            "{} Restored snapshot {} to disk {} in {}/{}",
+            cs.success_icon(),
+            self.snapshot,
+            self.new_disk,
+            self.organization,
+            self.project
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Delete old snapshots.
+///
+/// The snapshots whose name matches `--match` are sorted from the newest to the
+/// oldest. The newest `--keep-last` are kept, and so are the ones taken less than
+/// `--older-than` ago, the others are deleted. This is meant for retention in cron
+/// jobs, e.g. to keep a week of nightly snapshots, and at least the last three:
+///
+///     $ oxide snapshot prune --match 'nightly-*' --keep-last 3 --older-than 7d \
+///         -o my-org -p my-project --confirm
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdSnapshotPrune {
+    /// Prune the snapshots whose name matches a glob (e.g. `nightly-*`), or a
+    /// regular expression wrapped in slashes (e.g. `/^nightly-[0-9]+$/`).
+    #[clap(long = "match", required = true)]
+    pub selector: String,
+
+    /// Keep this many of the newest matching snapshots.
+    #[clap(long)]
+    pub keep_last: Option<usize>,
+
+    /// Only delete the snapshots taken longer ago than this, e.g. `12h` or `7d`.
+    #[clap(long, parse(try_from_str = crate::wait::parse_duration))]
+    pub older_than: Option<std::time::Duration>,
+
+    /// The project that holds the snapshots.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// Print the snapshots that would be deleted, without deleting them.
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// The maximum number of delete requests to run concurrently.
+    #[clap(long, default_value_t = crate::bulk::DEFAULT_PARALLEL)]
+    pub parallel: usize,
+
+    /// Confirm deletion without prompting.
+    #[clap(long)]
+    pub confirm: bool,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdSnapshotPrune {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        if self.keep_last.is_none() && self.older_than.is_none() {
+            bail!("pass --keep-last, --older-than or both, to say which snapshots to keep");
+        }
+        if !ctx.io.can_prompt() && !self.confirm && !self.dry_run {
+            bail!("--confirm required when not running interactively");
+        }
+
+        let selector = crate::bulk::compile_selector(&self.selector)?;
+        let client = ctx.api_client("")?;
+
+        let mut snapshots = Vec::new();
+        for snapshot in client
+            .snapshots()
+            .get_all(&self.organization, &self.project, Default::default())
+            .await?
+        {
+            if selector.is_match(&snapshot.name) {
+                // Through JSON, like `crate::cmd_wait::disk_state`, since the field is
+                // optional in the API types.
+                let created: Option<chrono::DateTime<chrono::Utc>> =
+                    serde_json::from_value(serde_json::to_value(&snapshot.time_created)?)?;
+                let created = created.ok_or_else(|| anyhow!("snapshot {} has no creation time", snapshot.name))?;
+                snapshots.push((snapshot.name.to_string(), created));
+            }
+        }
+
+        let names = select_for_pruning(snapshots, self.keep_last, self.older_than, chrono::Utc::now())?;
+
+        let full_name = format!("{}/{}", self.organization, self.project);
+        let cs = ctx.io.color_scheme();
+        if names.is_empty() {
+            writeln!(
+                ctx.io.out,
+                "{} No snapshots to prune in {}",
+                cs.success_icon(),
+                full_name
+            )?;
+            return Ok(());
+        }
+
+        if self.dry_run {
+            writeln!(ctx.io.out, "Would delete {} snapshots from {}:", names.len(), full_name)?;
+            for name in &names {
+                writeln!(ctx.io.out, "  - {}", name)?;
+            }
+            return Ok(());
+        }
+
+        if !self.confirm {
+            crate::bulk::confirm(ctx, "deletion", "snapshots", &names)?;
+        }
+
+        let results = crate::bulk::run(&names, self.parallel, |name| {
+            let client = &client;
+            async move {
+                client
+                    .snapshots()
+                    .delete(&self.organization, &self.project, &name)
+                    .await?;
+                Ok(())
+            }
+        })
+        .await;
+        crate::bulk::write_summary(ctx, "deleted", "snapshots", results)?;
+
+        let cs = ctx.io.color_scheme();
+        writeln!(
+            ctx.io.out,
+            "{} Pruned {} snapshots from {}",
+            cs.success_icon_with_color(ansi_term::Color::Red),
+            names.len(),
+            full_name
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The names of the snapshots to prune, from the newest to the oldest: all but the
+/// newest `keep_last`, and of those only the ones taken before `now - older_than`.
+fn select_for_pruning(
+    mut snapshots: Vec<(String, chrono::DateTime<chrono::Utc>)>,
+    keep_last: Option<usize>,
+    older_than: Option<std::time::Duration>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<String>> {
+    let cutoff = match older_than {
+        Some(older_than) => Some(now - chrono::Duration::from_std(older_than)?),
+        None => None,
+    };
+
+    snapshots.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(snapshots
+        .into_iter()
+        .skip(keep_last.unwrap_or(0))
+        .filter(|(_, created)| cutoff.map(|cutoff| *created < cutoff).unwrap_or(true))
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Wait for a snapshot to show up with its size.
+///
+/// Snapshots have no state in the API, and the rack creates them with their size,
+/// so today this returns as soon as the snapshot exists.
+pub async fn wait_for_snapshot(
+    ctx: &mut crate::context::Context<'_>,
+    organization: &str,
+    project: &str,
+    snapshot: &str,
+    opts: &crate::wait::WaitOptions,
+) -> Result<()> {
+    let client = ctx.api_client("")?;
+    let client = &client;
+    crate::wait::poll_with_spinner(
+        ctx,
+        opts,
+        &format!("snapshot {} to be taken", snapshot),
+        &format!("Snapshot {} taken", snapshot),
+        || async move {
+            let snapshot = client.snapshots().get(organization, project, snapshot).await?;
+
+            Ok(if snapshot.size > 0 {
+                crate::wait::Poll::Done
+            } else {
+                crate::wait::Poll::Pending("snapshotting".to_string())
+            })
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_select_for_pruning() {
+        let now = chrono::Utc.ymd(2022, 9, 10).and_hms(1, 0, 0);
+        let snapshots = (1..=9)
+            .map(|day| {
+                (
+                    format!("nightly-{}", day),
+                    chrono::Utc.ymd(2022, 9, day).and_hms(2, 0, 0),
+                )
+            })
+            .collect::<Vec<_>>();
+        let week = Some(std::time::Duration::from_secs(7 * 24 * 60 * 60));
+
+        assert_eq!(
+            select_for_pruning(snapshots.clone(), Some(7), None, now).unwrap(),
+            vec!["nightly-2", "nightly-1"]
+        );
+        assert_eq!(
+            select_for_pruning(snapshots.clone(), None, week, now).unwrap(),
+            vec!["nightly-2", "nightly-1"]
+        );
+        // Both have to agree to delete a snapshot.
+        assert_eq!(
+            select_for_pruning(snapshots.clone(), Some(8), week, now).unwrap(),
+            vec!["nightly-1"]
+        );
+        assert_eq!(
+            select_for_pruning(
+                snapshots.clone(),
+                Some(3),
+                Some(std::time::Duration::from_secs(60 * 60)),
+                now
+            )
+            .unwrap(),
+            vec![
+                "nightly-6",
+                "nightly-5",
+                "nightly-4",
+                "nightly-3",
+                "nightly-2",
+                "nightly-1"
+            ]
+        );
+        assert!(select_for_pruning(snapshots, Some(20), None, now).unwrap().is_empty());
+    }
+}
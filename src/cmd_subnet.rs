@@ -1,8 +1,14 @@
-use std::io::Write;
+use std::{
+    collections::HashMap,
+    io::Write,
+    net::{IpAddr, Ipv4Addr},
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
 use cli_macro::crud_gen;
+use ipnetwork::{Ipv4Network, Ipv6Network};
+use tabled::Tabled;
 
 /// Create, list, edit, view, and delete subnets.
 #[derive(Parser, Debug, Clone)]
@@ -16,7 +22,9 @@ pub struct CmdSubnet {
     tag = "subnets",
 }]
 #[derive(Parser, Debug, Clone)]
-enum SubCommand {}
+enum SubCommand {
+    Usage(CmdSubnetUsage),
+}
 
 #[async_trait::async_trait]
 impl crate::cmd::Command for CmdSubnet {
@@ -26,11 +34,313 @@ impl crate::cmd::Command for CmdSubnet {
             SubCommand::Delete(cmd) => cmd.run(ctx).await,
             SubCommand::Edit(cmd) => cmd.run(ctx).await,
             SubCommand::List(cmd) => cmd.run(ctx).await,
+            SubCommand::Usage(cmd) => cmd.run(ctx).await,
             SubCommand::View(cmd) => cmd.run(ctx).await,
         }
     }
 }
 
+/// Show the addresses allocated in a subnet.
+///
+/// The network interfaces in the subnet are listed with their addresses, followed
+/// by how much of the IPv4 block of the subnet is still free, leaving out the
+/// addresses the rack reserves in every subnet.
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdSubnetUsage {
+    /// The subnet to show the usage of.
+    #[clap(name = "subnet", required = true)]
+    pub subnet: String,
+
+    /// The VPC that holds the subnet.
+    #[clap(long, short, required = true)]
+    pub vpc: String,
+
+    /// The project that holds the VPC.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// Output format.
+    #[clap(long, short)]
+    pub format: Option<crate::types::FormatOutput>,
+}
+
+/// An address allocated to a network interface in a subnet.
+#[derive(Debug, Clone, serde::Serialize, Tabled)]
+pub struct Allocation {
+    pub ip: String,
+    pub instance: String,
+    pub interface: String,
+    pub primary: bool,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdSubnetUsage {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let client = ctx.api_client("")?;
+
+        let subnet = client
+            .subnets()
+            .get(&self.organization, &self.project, &self.subnet, &self.vpc)
+            .await?;
+        let interfaces = client
+            .subnets()
+            .network_interfaces_get_all(
+                &self.organization,
+                &self.project,
+                oxide_api::types::NameSortMode::NameAscending,
+                &self.subnet,
+                &self.vpc,
+            )
+            .await?;
+
+        // Network interfaces only know the ID of their instance.
+        let instances: HashMap<String, String> = client
+            .instances()
+            .get_all(&self.organization, &self.project, Default::default())
+            .await?
+            .into_iter()
+            .map(|instance| (instance.id.to_string(), instance.name.to_string()))
+            .collect();
+
+        let mut allocations = interfaces
+            .iter()
+            .map(|interface| Allocation {
+                ip: interface.ip.to_string(),
+                instance: instances
+                    .get(&interface.instance_id)
+                    .cloned()
+                    .unwrap_or_else(|| interface.instance_id.to_string()),
+                interface: interface.name.to_string(),
+                primary: interface.primary,
+            })
+            .collect::<Vec<_>>();
+        allocations.sort_by_key(|allocation| allocation.ip.parse::<IpAddr>().ok());
+
+        let format = ctx.format(&self.format)?;
+        ctx.io.write_output_for_vec(&format, &allocations)?;
+
+        // Keep the output of the other formats parseable.
+        let ips = allocations
+            .iter()
+            .filter_map(|allocation| allocation.ip.parse().ok())
+            .collect::<Vec<_>>();
+        let usage = usage(&subnet.ipv4_block.0, &ips);
+        if format == crate::types::FormatOutput::Table {
+            writeln!(ctx.io.out, "{}", usage)?;
+        } else {
+            writeln!(ctx.io.err_out, "{}", usage)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of addresses the rack reserves in every subnet: the first five,
+/// starting with the network address and the gateway, and the broadcast address.
+const RESERVED_ADDRESSES: u64 = 6;
+
+/// Describe how much of `block` is allocated to the addresses in `ips`.
+fn usage(block: &Ipv4Network, ips: &[IpAddr]) -> String {
+    let size = block.size() as u64;
+    let allocated = ips
+        .iter()
+        .filter(|ip| match ip {
+            IpAddr::V4(ip) => block.contains(*ip),
+            IpAddr::V6(_) => false,
+        })
+        .count() as u64;
+    let reserved = RESERVED_ADDRESSES.min(size);
+
+    format!(
+        "{} of {} IPv4 addresses in {} allocated ({:.1}%), {} reserved, {} free",
+        allocated,
+        size,
+        block,
+        allocated as f64 * 100.0 / size as f64,
+        reserved,
+        size.saturating_sub(reserved + allocated)
+    )
+}
+
+/// The private address ranges of RFC 1918, which the IPv4 blocks of subnets are
+/// allocated from.
+const PRIVATE_RANGES: [(Ipv4Addr, u8); 3] = [
+    (Ipv4Addr::new(10, 0, 0, 0), 8),
+    (Ipv4Addr::new(172, 16, 0, 0), 12),
+    (Ipv4Addr::new(192, 168, 0, 0), 16),
+];
+
+/// Parse the prefix length of `--next-free`, e.g. `/24` or `24`.
+pub fn parse_prefix_len(s: &str) -> Result<u8> {
+    let prefix_len = s
+        .trim_start_matches('/')
+        .parse::<u8>()
+        .map_err(|_| anyhow!("invalid prefix length `{}`, e.g. `/24`", s))?;
+    if prefix_len > 32 {
+        bail!("invalid prefix length `{}`, it cannot be more than 32", s);
+    }
+
+    Ok(prefix_len)
+}
+
+/// The IPv6 prefix of a VPC and the blocks of the subnets in it.
+#[derive(Debug, Clone, PartialEq)]
+struct VpcBlocks {
+    ipv6_prefix: Ipv6Network,
+    subnets: Vec<(String, Ipv4Network, Ipv6Network)>,
+}
+
+impl VpcBlocks {
+    async fn get(ctx: &mut crate::context::Context<'_>, organization: &str, project: &str, vpc: &str) -> Result<Self> {
+        if organization.is_empty() || project.is_empty() || vpc.is_empty() {
+            bail!("the organization, project and VPC are needed to check the blocks of the subnet");
+        }
+
+        let client = ctx.api_client("")?;
+
+        let ipv6_prefix = client.vpcs().get(organization, project, vpc).await?.ipv6_prefix.0;
+        let subnets = client
+            .subnets()
+            .get_all(organization, project, Default::default(), vpc)
+            .await?
+            .into_iter()
+            .map(|subnet| (subnet.name.to_string(), subnet.ipv4_block.0, subnet.ipv6_block.0))
+            .collect();
+
+        Ok(VpcBlocks { ipv6_prefix, subnets })
+    }
+
+    /// Check the blocks of a new subnet: they have to be in the private ranges or the
+    /// prefix of the VPC, and cannot overlap with the blocks of the other subnets.
+    fn check(&self, ipv4_block: Option<&Ipv4Network>, ipv6_block: Option<&Ipv6Network>) -> Result<()> {
+        if let Some(block) = ipv4_block {
+            if block.ip() != block.network() {
+                bail!(
+                    "{} is not the start of a block, did you mean {}/{}?",
+                    block,
+                    block.network(),
+                    block.prefix()
+                );
+            }
+            if !PRIVATE_RANGES.iter().any(|(ip, prefix)| {
+                *prefix <= block.prefix() && Ipv4Network::new(*ip, *prefix).unwrap().contains(block.ip())
+            }) {
+                bail!(
+                    "{} is not in a private address range: 10.0.0.0/8, 172.16.0.0/12 or 192.168.0.0/16",
+                    block
+                );
+            }
+            if let Some((name, other, _)) = self
+                .subnets
+                .iter()
+                .find(|(_, other, _)| other.contains(block.ip()) || block.contains(other.ip()))
+            {
+                bail!("{} overlaps with the IPv4 block of subnet {}, {}", block, name, other);
+            }
+        }
+
+        if let Some(block) = ipv6_block {
+            if block.prefix() < self.ipv6_prefix.prefix() || !self.ipv6_prefix.contains(block.ip()) {
+                bail!("{} is not in the IPv6 prefix of the VPC, {}", block, self.ipv6_prefix);
+            }
+            if let Some((name, _, other)) = self
+                .subnets
+                .iter()
+                .find(|(_, _, other)| other.contains(block.ip()) || block.contains(other.ip()))
+            {
+                bail!("{} overlaps with the IPv6 block of subnet {}, {}", block, name, other);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The first IPv4 block with `prefix_len` that does not overlap with any subnet.
+    /// The ranges the subnets are already in are tried first, from the first subnet
+    /// on, so that the blocks of a VPC stay together.
+    fn next_free(&self, prefix_len: u8) -> Result<Ipv4Network> {
+        let mut ranges = PRIVATE_RANGES
+            .iter()
+            .map(|(ip, prefix)| Ipv4Network::new(*ip, *prefix).unwrap())
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|range| !self.subnets.iter().any(|(_, block, _)| range.contains(block.ip())));
+
+        let size = 1u64 << (32 - prefix_len);
+        for range in ranges.iter().filter(|range| range.prefix() <= prefix_len) {
+            let end = u32::from(range.broadcast()) as u64;
+
+            let mut starts = self
+                .subnets
+                .iter()
+                .filter(|(_, block, _)| range.contains(block.ip()))
+                .map(|(_, block, _)| u32::from(block.network()) as u64 / size * size)
+                .min()
+                .into_iter()
+                .collect::<Vec<_>>();
+            starts.push(u32::from(range.network()) as u64);
+
+            for mut start in starts {
+                while start + size - 1 <= end {
+                    let candidate = Ipv4Network::new(Ipv4Addr::from(start as u32), prefix_len)?;
+                    match self
+                        .subnets
+                        .iter()
+                        .find(|(_, block, _)| block.contains(candidate.ip()) || candidate.contains(block.ip()))
+                    {
+                        None => return Ok(candidate),
+                        // Skip past the block in the way, to the next aligned candidate.
+                        Some((_, block, _)) => {
+                            let next = (u32::from(block.broadcast()) as u64 + 1).max(start + size);
+                            start = (next + size - 1) / size * size;
+                        }
+                    }
+                }
+            }
+        }
+
+        Err(anyhow!(
+            "there is no free /{} IPv4 block left in the private ranges",
+            prefix_len
+        ))
+    }
+}
+
+/// Allocate the block of `--next-free`.
+pub async fn next_free_block(
+    ctx: &mut crate::context::Context<'_>,
+    organization: &str,
+    project: &str,
+    vpc: &str,
+    prefix_len: u8,
+) -> Result<oxide_api::types::Ipv4Net> {
+    let block = VpcBlocks::get(ctx, organization, project, vpc)
+        .await?
+        .next_free(prefix_len)?;
+
+    writeln!(ctx.io.err_out, "Allocated the IPv4 block {}", block)?;
+
+    Ok(oxide_api::types::Ipv4Net(block))
+}
+
+/// Check the blocks of a new subnet against the VPC and the other subnets in it.
+pub async fn check_blocks(
+    ctx: &mut crate::context::Context<'_>,
+    organization: &str,
+    project: &str,
+    vpc: &str,
+    ipv4_block: Option<&oxide_api::types::Ipv4Net>,
+    ipv6_block: Option<&oxide_api::types::Ipv6Net>,
+) -> Result<()> {
+    VpcBlocks::get(ctx, organization, project, vpc)
+        .await?
+        .check(ipv4_block.map(|block| &block.0), ipv6_block.map(|block| &block.0))
+}
+
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
@@ -45,6 +355,89 @@ mod test {
         want_err: String,
     }
 
+    fn vpc_blocks() -> crate::cmd_subnet::VpcBlocks {
+        crate::cmd_subnet::VpcBlocks {
+            ipv6_prefix: "fd00:1122:3344::/48".parse().unwrap(),
+            subnets: vec![
+                (
+                    "default".to_string(),
+                    "172.30.0.0/22".parse().unwrap(),
+                    "fd00:1122:3344:100::/64".parse().unwrap(),
+                ),
+                (
+                    "db".to_string(),
+                    "172.30.5.0/24".parse().unwrap(),
+                    "fd00:1122:3344:101::/64".parse().unwrap(),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_check_blocks() {
+        let blocks = vpc_blocks();
+        let check = |ipv4: Option<&str>, ipv6: Option<&str>| {
+            blocks
+                .check(
+                    ipv4.map(|block| block.parse().unwrap()).as_ref(),
+                    ipv6.map(|block| block.parse().unwrap()).as_ref(),
+                )
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(check(Some("172.30.4.0/24"), Some("fd00:1122:3344:102::/64")), Ok(()));
+        assert_eq!(
+            check(Some("172.30.4.1/24"), None),
+            Err("172.30.4.1/24 is not the start of a block, did you mean 172.30.4.0/24?".to_string())
+        );
+        assert_eq!(
+            check(Some("8.8.8.0/24"), None),
+            Err(
+                "8.8.8.0/24 is not in a private address range: 10.0.0.0/8, 172.16.0.0/12 or 192.168.0.0/16".to_string()
+            )
+        );
+        assert_eq!(
+            check(Some("172.30.2.0/24"), None),
+            Err("172.30.2.0/24 overlaps with the IPv4 block of subnet default, 172.30.0.0/22".to_string())
+        );
+        assert_eq!(
+            check(None, Some("fd00:1122:3345::/64")),
+            Err("fd00:1122:3345::/64 is not in the IPv6 prefix of the VPC, fd00:1122:3344::/48".to_string())
+        );
+        assert_eq!(
+            check(None, Some("fd00:1122:3344:101::/64")),
+            Err(
+                "fd00:1122:3344:101::/64 overlaps with the IPv6 block of subnet db, fd00:1122:3344:101::/64"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_next_free_block() {
+        let blocks = vpc_blocks();
+
+        assert_eq!(blocks.next_free(24).unwrap().to_string(), "172.30.4.0/24");
+        assert_eq!(blocks.next_free(23).unwrap().to_string(), "172.30.6.0/23");
+        assert_eq!(blocks.next_free(20).unwrap().to_string(), "172.30.16.0/20");
+        assert_eq!(blocks.next_free(10).unwrap().to_string(), "10.0.0.0/10");
+
+        assert_eq!(crate::cmd_subnet::parse_prefix_len("/24").unwrap(), 24);
+        assert_eq!(crate::cmd_subnet::parse_prefix_len("24").unwrap(), 24);
+        assert!(crate::cmd_subnet::parse_prefix_len("/33").is_err());
+    }
+
+    #[test]
+    fn test_usage() {
+        assert_eq!(
+            crate::cmd_subnet::usage(
+                &"172.30.0.0/22".parse().unwrap(),
+                &["172.30.0.5".parse().unwrap(), "fd00:1122:3344:100::5".parse().unwrap()]
+            ),
+            "1 of 1024 IPv4 addresses in 172.30.0.0/22 allocated (0.1%), 6 reserved, 1017 free"
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_cmd_subnet() {
         let ipv4_block =
@@ -61,6 +454,7 @@ mod test {
                     vpc: "".to_string(),
                     ipv4_block: Some(ipv4_block),
                     ipv6_block: Default::default(),
+                    next_free: None,
                 }),
 
                 stdin: "".to_string(),
@@ -77,6 +471,7 @@ mod test {
                     vpc: "foo bar".to_string(),
                     ipv4_block: Some(ipv4_block),
                     ipv6_block: Default::default(),
+                    next_free: None,
                 }),
 
                 stdin: "".to_string(),
@@ -93,6 +488,7 @@ mod test {
                     vpc: "blah".to_string(),
                     ipv4_block: Some(ipv4_block),
                     ipv6_block: Default::default(),
+                    next_free: None,
                 }),
 
                 stdin: "".to_string(),
@@ -109,6 +505,7 @@ mod test {
                     vpc: "blah".to_string(),
                     ipv4_block: Some(ipv4_block),
                     ipv6_block: Default::default(),
+                    next_free: None,
                 }),
 
                 stdin: "".to_string(),
@@ -125,6 +522,7 @@ mod test {
                     vpc: "".to_string(),
                     ipv4_block: Some(ipv4_block),
                     ipv6_block: Default::default(),
+                    next_free: None,
                 }),
 
                 stdin: "".to_string(),
@@ -134,10 +532,14 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_subnet::SubCommand::Delete(crate::cmd_subnet::CmdSubnetDelete {
-                    subnet: "things".to_string(),
+                    subnet: vec!["things".to_string()],
                     organization: "".to_string(),
                     project: "".to_string(),
                     vpc: "things".to_string(),
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
                     confirm: false,
                 }),
 
@@ -155,6 +557,7 @@ mod test {
                     project: "".to_string(),
                     paginate: false,
                     format: None,
+                    watch: None,
                 }),
 
                 stdin: "".to_string(),
@@ -1,4 +1,4 @@
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 
 use anyhow::Result;
 use clap::Parser;
@@ -16,7 +16,9 @@ pub struct CmdVpc {
     tag = "vpcs",
 }]
 #[derive(Parser, Debug, Clone)]
-enum SubCommand {}
+enum SubCommand {
+    Describe(CmdVpcDescribe),
+}
 
 #[async_trait::async_trait]
 impl crate::cmd::Command for CmdVpc {
@@ -24,6 +26,7 @@ impl crate::cmd::Command for CmdVpc {
         match &self.subcmd {
             SubCommand::Create(cmd) => cmd.run(ctx).await,
             SubCommand::Delete(cmd) => cmd.run(ctx).await,
+            SubCommand::Describe(cmd) => cmd.run(ctx).await,
             SubCommand::Edit(cmd) => cmd.run(ctx).await,
             SubCommand::List(cmd) => cmd.run(ctx).await,
             SubCommand::View(cmd) => cmd.run(ctx).await,
@@ -31,6 +34,170 @@ impl crate::cmd::Command for CmdVpc {
     }
 }
 
+/// Describe a VPC.
+///
+/// Print the subnets of the VPC with the network interfaces in them, the routers
+/// with their routes, and the firewall rules, as a tree.
+///
+/// With `--format dot` or `--format mermaid` a diagram of the subnets, instances
+/// and route targets is printed instead, e.g. for design documents:
+///
+///     $ oxide vpc describe default --format dot | dot -Tsvg > default.svg
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdVpcDescribe {
+    /// The VPC to describe. Can be an ID or name.
+    #[clap(name = "vpc", required = true)]
+    pub vpc: String,
+
+    /// The project that holds the VPC.
+    #[clap(long, short, required = true)]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, required = true, env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The format to print the VPC in, `tree`, `dot` or `mermaid`.
+    #[clap(long, short, default_value = "tree")]
+    pub format: crate::topology::TopologyFormat,
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdVpcDescribe {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        let client = ctx.api_client("")?;
+
+        let vpc = client.vpcs().get(&self.organization, &self.project, &self.vpc).await?;
+
+        // Network interfaces only know the ID of their instance.
+        let instances: HashMap<String, String> = client
+            .instances()
+            .get_all(&self.organization, &self.project, Default::default())
+            .await?
+            .into_iter()
+            .map(|instance| (instance.id.to_string(), instance.name.to_string()))
+            .collect();
+
+        let mut subnets = Vec::new();
+        for subnet in client
+            .subnets()
+            .get_all(&self.organization, &self.project, Default::default(), &vpc.name)
+            .await?
+        {
+            let interfaces = client
+                .subnets()
+                .network_interfaces_get_all(
+                    &self.organization,
+                    &self.project,
+                    oxide_api::types::NameSortMode::NameAscending,
+                    &subnet.name,
+                    &vpc.name,
+                )
+                .await?;
+
+            subnets.push(crate::topology::Subnet {
+                name: subnet.name.to_string(),
+                ipv4_block: subnet.ipv4_block.to_string(),
+                ipv6_block: subnet.ipv6_block.to_string(),
+                interfaces: interfaces
+                    .into_iter()
+                    .map(|interface| crate::topology::Interface {
+                        name: interface.name.to_string(),
+                        instance: instances
+                            .get(&interface.instance_id)
+                            .cloned()
+                            .unwrap_or_else(|| interface.instance_id.to_string()),
+                        ip: interface.ip.to_string(),
+                    })
+                    .collect(),
+            });
+        }
+
+        let mut routers = Vec::new();
+        for router in client
+            .routers()
+            .get_all(&self.organization, &self.project, Default::default(), &vpc.name)
+            .await?
+        {
+            let routes = client
+                .routes()
+                .get_all(
+                    &self.organization,
+                    &self.project,
+                    &router.name,
+                    Default::default(),
+                    &vpc.name,
+                )
+                .await?;
+
+            routers.push(crate::topology::Router {
+                name: router.name.to_string(),
+                kind: router.kind.to_string(),
+                routes: routes
+                    .into_iter()
+                    .map(|route| crate::topology::Route {
+                        name: route.name.to_string(),
+                        destination: crate::topology::tagged(&route.destination),
+                        target: crate::topology::tagged(&route.target),
+                    })
+                    .collect(),
+            });
+        }
+
+        let firewall_rules = client
+            .vpcs()
+            .firewall_rules_get(&self.organization, &self.project, &vpc.name)
+            .await?
+            .rules
+            .into_iter()
+            .map(|rule| {
+                // Only the filters that are present narrow down the rule.
+                let mut filters = Vec::new();
+                if let Ok(serde_json::Value::Object(object)) = serde_json::to_value(&rule.filters) {
+                    for (name, values) in object {
+                        if let serde_json::Value::Array(values) = values {
+                            filters.push(format!(
+                                "{} {}",
+                                name,
+                                values.iter().map(crate::topology::tagged).collect::<Vec<_>>().join(" ")
+                            ));
+                        }
+                    }
+                }
+
+                crate::topology::FirewallRule {
+                    name: rule.name.to_string(),
+                    direction: rule.direction.to_string(),
+                    action: rule.action.to_string(),
+                    priority: rule.priority.to_string(),
+                    status: rule.status.to_string(),
+                    targets: rule.targets.iter().map(crate::topology::tagged).collect(),
+                    filters,
+                }
+            })
+            .collect();
+
+        let topology = crate::topology::Topology {
+            vpc: vpc.name.to_string(),
+            ipv6_prefix: vpc.ipv6_prefix.to_string(),
+            dns_name: vpc.dns_name.to_string(),
+            subnets,
+            routers,
+            firewall_rules,
+        };
+
+        let out = match self.format {
+            crate::topology::TopologyFormat::Tree => crate::topology::to_tree(&topology),
+            crate::topology::TopologyFormat::Dot => crate::topology::to_dot(&topology),
+            crate::topology::TopologyFormat::Mermaid => crate::topology::to_mermaid(&topology),
+        };
+        write!(ctx.io.out, "{}", out)?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
@@ -126,9 +293,13 @@ mod test {
             TestItem {
                 name: "delete no --confirm non-interactive".to_string(),
                 cmd: crate::cmd_vpc::SubCommand::Delete(crate::cmd_vpc::CmdVpcDelete {
-                    vpc: "things".to_string(),
+                    vpc: vec!["things".to_string()],
                     organization: "".to_string(),
                     project: "".to_string(),
+                    all: false,
+                    selector: None,
+                    from_stdin: false,
+                    parallel: crate::bulk::DEFAULT_PARALLEL,
                     confirm: false,
                 }),
 
@@ -145,6 +316,7 @@ mod test {
                     project: "".to_string(),
                     paginate: false,
                     format: None,
+                    watch: None,
                 }),
 
                 stdin: "".to_string(),
@@ -0,0 +1,426 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+/// Wait for a resource to reach a condition.
+///
+/// Blocks until the resource is in the given state, exists, or has been deleted.
+/// This is handy in scripts, to wait for one step to settle before running the next:
+///
+///     $ oxide wait disk my-disk --for state=detached -o my-org -p my-project
+///     $ oxide wait instance my-app --for delete -o my-org -p my-project
+///
+/// Instances and disks report a state. The other resources do not, so for them
+/// `--for state=ready` (or `state=available`) waits until they can be fetched,
+/// which is the same as `--for exists`.
+#[derive(Parser, Debug, Clone)]
+#[clap(verbatim_doc_comment)]
+pub struct CmdWait {
+    /// The type of resource to wait for: instance, disk, snapshot, image, vpc,
+    /// subnet, project or organization.
+    #[clap(name = "resource-type", required = true)]
+    pub resource_type: ResourceType,
+
+    /// The name or ID of the resource.
+    #[clap(name = "name", required = true)]
+    pub name: String,
+
+    /// The condition to wait for: `state=<value>`, `exists` or `delete`.
+    #[clap(long = "for", required = true)]
+    pub condition: Condition,
+
+    /// The project that holds the resource.
+    #[clap(long, short, default_value = "")]
+    pub project: String,
+
+    /// The organization that holds the project.
+    #[clap(long, short, default_value = "", env = "OXIDE_ORG")]
+    pub organization: String,
+
+    /// The VPC that holds the subnet.
+    #[clap(long, default_value = "")]
+    pub vpc: String,
+
+    /// How long to wait, e.g. `90s` or `5m`. Use `0` to wait forever.
+    #[clap(long, default_value = crate::wait::DEFAULT_TIMEOUT, parse(try_from_str = crate::wait::parse_duration))]
+    pub timeout: std::time::Duration,
+
+    /// How often to check the resource, e.g. `500ms` or `2s`.
+    /// The interval backs off exponentially while waiting.
+    #[clap(long, default_value = crate::wait::DEFAULT_POLL_INTERVAL, parse(try_from_str = crate::wait::parse_duration))]
+    pub poll_interval: std::time::Duration,
+}
+
+/// The types of resources `oxide wait` knows how to poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, parse_display::FromStr, parse_display::Display)]
+#[display(style = "kebab-case")]
+pub enum ResourceType {
+    Instance,
+    Disk,
+    Snapshot,
+    Image,
+    Vpc,
+    Subnet,
+    Project,
+    Organization,
+}
+
+impl ResourceType {
+    /// Returns true if the resource reports a state we can wait on.
+    fn has_state(&self) -> bool {
+        matches!(self, ResourceType::Instance | ResourceType::Disk)
+    }
+
+    /// Returns true if a resource in `state` can never move on to another state.
+    fn is_terminal_state(&self, state: &str) -> bool {
+        match self {
+            ResourceType::Instance => state == "failed" || state == "destroyed",
+            ResourceType::Disk => state == "faulted" || state == "destroyed",
+            _ => false,
+        }
+    }
+}
+
+/// A condition to wait for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Wait for the resource to be in the given state.
+    State(String),
+    /// Wait for the resource to exist.
+    Exists,
+    /// Wait for the resource to be deleted.
+    Delete,
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "delete" | "deleted" => Ok(Condition::Delete),
+            "exists" => Ok(Condition::Exists),
+            s => match s.strip_prefix("state=") {
+                Some(state) if !state.trim().is_empty() => Ok(Condition::State(state.trim().to_lowercase())),
+                _ => Err(anyhow!(
+                    "invalid condition `{}`, expected `state=<value>`, `exists` or `delete`",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::State(state) => write!(f, "be `{}`", state),
+            Condition::Exists => write!(f, "exist"),
+            Condition::Delete => write!(f, "be deleted"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::cmd::Command for CmdWait {
+    async fn run(&self, ctx: &mut crate::context::Context) -> Result<()> {
+        self.validate()?;
+
+        let client = ctx.api_client("")?;
+        let opts = crate::wait::WaitOptions {
+            timeout: self.timeout,
+            poll_interval: self.poll_interval,
+        };
+
+        let what = format!("{} {} to {}", self.resource_type, self.name, self.condition);
+
+        // Start the progress bar.
+        let handle = ctx
+            .io
+            .start_process_indicator_with_label(&format!(" Waiting for {}", what));
+
+        let client = &client;
+        let result = crate::wait::poll(
+            &opts,
+            &what,
+            || async move { self.check(client).await },
+            |state| {
+                if let Some(handle) = &handle {
+                    handle.text(format!(" Waiting for {} [{}]", what, state));
+                }
+            },
+        )
+        .await;
+
+        // End the progress bar.
+        if let Some(handle) = handle {
+            if result.is_ok() {
+                handle.done();
+            } else {
+                handle.error();
+            }
+        }
+        result?;
+
+        let cs = ctx.io.color_scheme();
+        let done = match &self.condition {
+            Condition::State(state) => format!("is `{}`", state),
+            Condition::Exists => "exists".to_string(),
+            Condition::Delete => "was deleted".to_string(),
+        };
+        writeln!(
+            ctx.io.out,
+            "{} {} {} {}",
+            cs.success_icon(),
+            self.resource_type,
+            self.name,
+            done
+        )?;
+
+        Ok(())
+    }
+}
+
+impl CmdWait {
+    /// Make sure we were given everything we need to find the resource.
+    fn validate(&self) -> Result<()> {
+        match self.resource_type {
+            ResourceType::Organization => {}
+            ResourceType::Project => {
+                if self.organization.is_empty() {
+                    return Err(anyhow!("-o|--organization required"));
+                }
+            }
+            _ => {
+                if self.organization.is_empty() {
+                    return Err(anyhow!("-o|--organization required"));
+                }
+                if self.project.is_empty() {
+                    return Err(anyhow!("-p|--project required"));
+                }
+            }
+        }
+
+        if self.resource_type == ResourceType::Subnet && self.vpc.is_empty() {
+            return Err(anyhow!("--vpc required"));
+        }
+
+        if let Condition::State(state) = &self.condition {
+            if !self.resource_type.has_state() && state != "ready" && state != "available" {
+                return Err(anyhow!(
+                    "{} resources do not report a state, use `--for exists` or `--for delete` instead",
+                    self.resource_type
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the resource once and compare it against the condition.
+    async fn check(&self, client: &oxide_api::Client) -> Result<crate::wait::Poll> {
+        let state = match self.get_state(client).await {
+            Ok(state) => state,
+            Err(err) if is_not_found(&err) => {
+                return Ok(if self.condition == Condition::Delete {
+                    crate::wait::Poll::Done
+                } else {
+                    crate::wait::Poll::Pending("not found".to_string())
+                });
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok(match (&self.condition, state) {
+            (Condition::Delete, state) => crate::wait::Poll::Pending(state.unwrap_or_else(|| "exists".to_string())),
+            (Condition::Exists, _) => crate::wait::Poll::Done,
+            // Resources without a state are ready as soon as they can be fetched.
+            (Condition::State(_), None) => crate::wait::Poll::Done,
+            (Condition::State(want), Some(state)) if *want == state => crate::wait::Poll::Done,
+            (Condition::State(want), Some(state)) if self.resource_type.is_terminal_state(&state) => {
+                crate::wait::Poll::Failed(format!(
+                    "{} {} is `{}`, it will never be `{}`",
+                    self.resource_type, self.name, state, want
+                ))
+            }
+            (Condition::State(_), Some(state)) => crate::wait::Poll::Pending(state),
+        })
+    }
+
+    /// Fetch the resource, returning its state if it has one.
+    async fn get_state(&self, client: &oxide_api::Client) -> Result<Option<String>> {
+        let name = &self.name;
+        let organization = &self.organization;
+        let project = &self.project;
+
+        Ok(match self.resource_type {
+            ResourceType::Instance => {
+                let instance = client.instances().get(name, organization, project).await?;
+                Some(instance.run_state.to_string())
+            }
+            ResourceType::Disk => {
+                let disk = client.disks().get(name, organization, project).await?;
+                Some(disk_state(&disk.state)?)
+            }
+            ResourceType::Snapshot => {
+                client.snapshots().get(organization, project, name).await?;
+                None
+            }
+            ResourceType::Image => {
+                client.images().get(name, organization, project).await?;
+                None
+            }
+            ResourceType::Vpc => {
+                client.vpcs().get(organization, project, name).await?;
+                None
+            }
+            ResourceType::Subnet => {
+                client.subnets().get(organization, project, name, &self.vpc).await?;
+                None
+            }
+            ResourceType::Project => {
+                client.projects().get(organization, name).await?;
+                None
+            }
+            ResourceType::Organization => {
+                client.organizations().get(name).await?;
+                None
+            }
+        })
+    }
+}
+
+/// Returns the name of a disk state, e.g. `attached`, without the instance it refers to.
+pub fn disk_state(state: &oxide_api::types::DiskState) -> Result<String> {
+    let value = serde_json::to_value(state)?;
+    match value.get("state").and_then(|s| s.as_str()) {
+        Some(state) => Ok(state.to_string()),
+        None => Err(anyhow!("unexpected disk state: {}", value)),
+    }
+}
+
+/// Returns true if the error is the API telling us the resource does not exist.
+pub fn is_not_found(err: &anyhow::Error) -> bool {
+    if let Some(oxide_api::types::Error::ObjectNotFound { .. }) = err.downcast_ref::<oxide_api::types::Error>() {
+        return true;
+    }
+
+    matches!(
+        err.downcast_ref::<reqwest::Error>().and_then(|e| e.status()),
+        Some(reqwest::StatusCode::NOT_FOUND)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use crate::cmd::Command;
+
+    pub struct TestItem {
+        name: String,
+        cmd: crate::cmd_wait::CmdWait,
+        want_out: String,
+        want_err: String,
+    }
+
+    fn cmd(
+        resource_type: &str,
+        organization: &str,
+        project: &str,
+        vpc: &str,
+        condition: &str,
+    ) -> crate::cmd_wait::CmdWait {
+        crate::cmd_wait::CmdWait {
+            resource_type: resource_type.parse().unwrap(),
+            name: "things".to_string(),
+            condition: condition.parse().unwrap(),
+            project: project.to_string(),
+            organization: organization.to_string(),
+            vpc: vpc.to_string(),
+            timeout: Default::default(),
+            poll_interval: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        use crate::cmd_wait::Condition;
+
+        assert_eq!("delete".parse::<Condition>().unwrap(), Condition::Delete);
+        assert_eq!("exists".parse::<Condition>().unwrap(), Condition::Exists);
+        assert_eq!(
+            "state=Detached".parse::<Condition>().unwrap(),
+            Condition::State("detached".to_string())
+        );
+        assert!("state=".parse::<Condition>().is_err());
+        assert!("running".parse::<Condition>().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cmd_wait() {
+        let tests: Vec<TestItem> = vec![
+            TestItem {
+                name: "instance no organization".to_string(),
+                cmd: cmd("instance", "", "", "", "state=running"),
+                want_out: "".to_string(),
+                want_err: "-o|--organization required".to_string(),
+            },
+            TestItem {
+                name: "disk no project".to_string(),
+                cmd: cmd("disk", "maze-war", "", "", "state=detached"),
+                want_out: "".to_string(),
+                want_err: "-p|--project required".to_string(),
+            },
+            TestItem {
+                name: "subnet no vpc".to_string(),
+                cmd: cmd("subnet", "maze-war", "prod", "", "exists"),
+                want_out: "".to_string(),
+                want_err: "--vpc required".to_string(),
+            },
+            TestItem {
+                name: "snapshot with a state".to_string(),
+                cmd: cmd("snapshot", "maze-war", "prod", "", "state=creating"),
+                want_out: "".to_string(),
+                want_err: "snapshot resources do not report a state, use `--for exists` or `--for delete` instead"
+                    .to_string(),
+            },
+        ];
+
+        let mut config = crate::config::new_blank_config().unwrap();
+        let mut c = crate::config_from_env::EnvConfig::inherit_env(&mut config);
+
+        for t in tests {
+            let (mut io, stdout_path, stderr_path) = crate::iostreams::IoStreams::test();
+            // We need to also turn off the fancy terminal colors.
+            // This ensures it also works in GitHub actions/any CI.
+            io.set_color_enabled(false);
+            io.set_never_prompt(true);
+            let mut ctx = crate::context::Context {
+                config: &mut c,
+                io,
+                debug: false,
+            };
+
+            match t.cmd.run(&mut ctx).await {
+                Ok(()) => {
+                    let stdout = std::fs::read_to_string(stdout_path).unwrap();
+                    let stderr = std::fs::read_to_string(stderr_path).unwrap();
+                    assert!(stderr.is_empty(), "test {}: {}", t.name, stderr);
+                    if !stdout.contains(&t.want_out) {
+                        assert_eq!(stdout, t.want_out, "test {}: stdout mismatch", t.name);
+                    }
+                }
+                Err(err) => {
+                    let stdout = std::fs::read_to_string(stdout_path).unwrap();
+                    let stderr = std::fs::read_to_string(stderr_path).unwrap();
+                    assert_eq!(stdout, t.want_out, "test {}", t.name);
+                    if !err.to_string().contains(&t.want_err) {
+                        assert_eq!(err.to_string(), t.want_err, "test {}: err mismatch", t.name);
+                    }
+                    assert!(stderr.is_empty(), "test {}: {}", t.name, stderr);
+                }
+            }
+        }
+    }
+}
@@ -91,6 +91,23 @@ pub fn config_options() -> Vec<ConfigOption> {
             default_value: Uuid::new_v4().to_string(),
             allowed_values: vec![],
         },
+        ConfigOption {
+            key: "serial_escape_char".to_string(),
+            description: "the escape character of interactive serial consoles".to_string(),
+            comment:
+                "What starts the escape sequences of interactive serial consoles, e.g. `~.` to disconnect, or `none`."
+                    .to_string(),
+            default_value: crate::serial::DEFAULT_ESCAPE_CHAR.to_string(),
+            allowed_values: vec![],
+        },
+        ConfigOption {
+            key: "serial_ctrl_c".to_string(),
+            description: "what Ctrl-C does in interactive serial consoles".to_string(),
+            comment: "Whether Ctrl-C is sent to the instance in interactive serial consoles, or disconnects from them."
+                .to_string(),
+            default_value: "send".to_string(),
+            allowed_values: vec!["send".to_string(), "disconnect".to_string()],
+        },
     ]
 }
 
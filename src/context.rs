@@ -101,6 +101,29 @@ impl Context<'_> {
         Ok(())
     }
 
+    /// The editor to run to author text, e.g. the settings of a resource.
+    ///
+    /// Editor precedence:
+    /// 1. OXIDE_EDITOR
+    /// 2. editor from config
+    /// 3. VISUAL
+    /// 4. EDITOR
+    /// 5. vi, or notepad on Windows
+    pub fn editor(&self) -> String {
+        let editor = self.config.get("", "editor").unwrap_or_default();
+        if !editor.is_empty() {
+            editor
+        } else if !get_env_var("VISUAL").is_empty() {
+            get_env_var("VISUAL")
+        } else if !get_env_var("EDITOR").is_empty() {
+            get_env_var("EDITOR")
+        } else if cfg!(windows) {
+            "notepad".to_string()
+        } else {
+            "vi".to_string()
+        }
+    }
+
     /// Return the configured output format or override the default with the value passed in,
     /// if it is some.
     pub fn format(&self, format: &Option<FormatOutput>) -> Result<FormatOutput> {
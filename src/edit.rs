@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+/// Open `fields` as YAML in the editor, below `header` which is shown as comments,
+/// and return the fields that were changed, with their new values.
+pub fn edit_fields(
+    ctx: &crate::context::Context<'_>,
+    header: &str,
+    fields: &BTreeMap<String, Value>,
+) -> Result<BTreeMap<String, Value>> {
+    let path = std::env::temp_dir().join(format!("oxide-edit-{}.yaml", uuid::Uuid::new_v4().simple()));
+
+    let mut text = String::new();
+    for line in header.lines() {
+        text.push_str(&format!("# {}\n", line).replace("# \n", "#\n"));
+    }
+    text.push_str(&serde_yaml::to_string(fields)?);
+    std::fs::write(&path, text)?;
+
+    let edited = run_editor(&ctx.editor(), &path).and_then(|()| Ok(std::fs::read_to_string(&path)?));
+    std::fs::remove_file(&path).ok();
+
+    changes(fields, &parse(&edited?)?)
+}
+
+/// The changes to `fields`, one per line, e.g. `ncpus: 2 -> 4`.
+pub fn preview(fields: &BTreeMap<String, Value>, changes: &BTreeMap<String, Value>) -> String {
+    let mut text = String::new();
+    for (name, value) in changes {
+        let old = fields.get(name).cloned().unwrap_or(Value::Null);
+        text.push_str(&format!("  {}: {} -> {}\n", name, old, value));
+    }
+
+    text
+}
+
+fn run_editor(editor: &str, path: &std::path::Path) -> Result<()> {
+    let args = shlex::split(editor).ok_or_else(|| anyhow!("invalid editor `{}`", editor))?;
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("invalid editor `{}`", editor))?;
+
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .map_err(|e| anyhow!("running the editor `{}`: {}", editor, e))?;
+    if !status.success() {
+        bail!("the editor `{}` exited with {}, nothing was changed", editor, status);
+    }
+
+    Ok(())
+}
+
+fn parse(text: &str) -> Result<BTreeMap<String, Value>> {
+    if text
+        .lines()
+        .all(|line| line.trim().is_empty() || line.trim_start().starts_with('#'))
+    {
+        return Ok(BTreeMap::new());
+    }
+
+    serde_yaml::from_str(text).map_err(|e| anyhow!("invalid YAML, nothing was changed: {}", e))
+}
+
+/// The fields of `after` that differ from `before`. Fields that were removed are left
+/// as they are, and fields that were added are an error since they cannot be edited.
+fn changes(before: &BTreeMap<String, Value>, after: &BTreeMap<String, Value>) -> Result<BTreeMap<String, Value>> {
+    let mut changes = BTreeMap::new();
+
+    for (name, value) in after {
+        match before.get(name) {
+            None => bail!(
+                "`{}` cannot be edited, only {} can",
+                name,
+                before
+                    .keys()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Some(old) if old != value => {
+                changes.insert(name.to_string(), value.clone());
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_changes() {
+        let before: BTreeMap<String, Value> = serde_json::from_value(json!({
+            "description": "web server",
+            "memory": 1073741824,
+            "ncpus": 2,
+        }))
+        .unwrap();
+
+        let after = parse("# Edit the instance.\ndescription: web server\nncpus: 4\n").unwrap();
+        assert_eq!(
+            changes(&before, &after).unwrap(),
+            serde_json::from_value::<BTreeMap<String, Value>>(json!({ "ncpus": 4 })).unwrap()
+        );
+
+        assert!(changes(&before, &parse("# Nothing.\n").unwrap()).unwrap().is_empty());
+
+        assert_eq!(
+            changes(&before, &parse("hostname: web-1\n").unwrap())
+                .unwrap_err()
+                .to_string(),
+            "`hostname` cannot be edited, only `description`, `memory`, `ncpus` can"
+        );
+        assert!(parse("ncpus: [4\n").is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        let before: BTreeMap<String, Value> = serde_json::from_value(json!({
+            "description": "web server",
+            "ncpus": 2,
+        }))
+        .unwrap();
+        let changes: BTreeMap<String, Value> = serde_json::from_value(json!({
+            "description": "web server in eu",
+            "ncpus": 4,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            preview(&before, &changes),
+            "  description: \"web server\" -> \"web server in eu\"\n  ncpus: 2 -> 4\n"
+        );
+    }
+}
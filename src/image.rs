@@ -0,0 +1,492 @@
+use std::{
+    io::{Read, SeekFrom},
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Result};
+use futures::StreamExt;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::progress::ProgressBar;
+
+/// The first bytes of a qcow2 image.
+const QCOW2_MAGIC: &[u8] = b"QFI\xfb";
+
+/// Requests with a head larger than this are not from the rack fetching an image.
+const MAX_REQUEST_HEAD_LENGTH: usize = 8 * 1024;
+
+/// The formats of the disk images we can import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Raw,
+    Qcow2,
+}
+
+impl ImageFormat {
+    /// Tell the format of an image from its first bytes, anything that is not a
+    /// qcow2 image is taken as raw, e.g. ISOs.
+    pub fn detect(path: &Path) -> Result<Self> {
+        let mut magic = [0; 4];
+        let mut file = std::fs::File::open(path).map_err(|e| anyhow!("opening {}: {}", path.display(), e))?;
+        let n = file.read(&mut magic)?;
+
+        Ok(if magic[..n] == *QCOW2_MAGIC {
+            ImageFormat::Qcow2
+        } else {
+            ImageFormat::Raw
+        })
+    }
+}
+
+/// A raw copy of a qcow2 image, which is what the API takes. It is removed when
+/// dropped.
+pub struct RawCopy {
+    path: PathBuf,
+}
+
+impl RawCopy {
+    /// Convert `path` with `qemu-img`, we do not know about the internals of qcow2.
+    pub async fn convert(path: &Path) -> Result<Self> {
+        let copy = RawCopy {
+            path: std::env::temp_dir().join(format!("oxide-image-{}.raw", uuid::Uuid::new_v4().simple())),
+        };
+
+        let status = tokio::process::Command::new("qemu-img")
+            .args(["convert", "-f", "qcow2", "-O", "raw"])
+            .arg(path)
+            .arg(&copy.path)
+            .status()
+            .await;
+        match status {
+            Ok(status) if status.success() => Ok(copy),
+            Ok(status) => bail!("qemu-img exited with status {}", status),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => bail!(
+                "{} is a qcow2 image, install qemu-img to import it, or convert it to a raw image with `qemu-img \
+                 convert -f qcow2 -O raw {} <raw image>`",
+                path.display(),
+                shlex::quote(&path.to_string_lossy())
+            ),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for RawCopy {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// A reader that advances a progress bar, e.g. to show how far along the checksum of
+/// a large image is.
+pub struct ProgressReader<R> {
+    pub inner: R,
+    pub progress: Option<ProgressBar>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(progress) = &mut self.progress {
+            progress.inc(n as u64);
+        }
+        Ok(n)
+    }
+}
+
+/// The address of this machine on the network interface used to reach the Oxide API at
+/// `host`, which is where the rack can most likely reach it too.
+pub fn local_address(host: &str) -> Result<IpAddr> {
+    let url = if host.starts_with("http://") || host.starts_with("https://") {
+        url::Url::parse(host)?
+    } else {
+        url::Url::parse(&format!("https://{}", host))?
+    };
+    let target = url
+        .socket_addrs(|| None)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("cannot resolve {}", host))?;
+
+    // Connecting a UDP socket sends nothing, it only picks the route.
+    let socket = std::net::UdpSocket::bind(match target {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    })?;
+    socket.connect(target)?;
+    Ok(socket.local_addr()?.ip())
+}
+
+/// Check that the rack can create an image from `url` before asking it to: the URL
+/// answers, with a size that is a multiple of `block_size`, and the image has the
+/// checksum `sha256` when given.
+pub async fn check_url(
+    ctx: &mut crate::context::Context<'_>,
+    url: &str,
+    block_size: oxide_api::types::BlockSize,
+    sha256: Option<&str>,
+) -> Result<()> {
+    let client = reqwest::Client::new();
+
+    let response = client
+        .head(url)
+        .send()
+        .await
+        .map_err(|e| anyhow!("fetching {}: {}", url, e))?;
+    let size = match response.status() {
+        status if status.is_success() => response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse::<u64>().ok()),
+        // Some servers only do GET, the rack will tell.
+        reqwest::StatusCode::METHOD_NOT_ALLOWED => None,
+        status => bail!("fetching {}: {}", url, status),
+    };
+
+    if let Some(size) = size {
+        writeln!(
+            ctx.io.err_out,
+            "{} is {}",
+            url,
+            crate::progress::format_bytes(size as u128)
+        )?;
+
+        if block_size > 0 && size % block_size as u64 != 0 {
+            bail!(
+                "{} is {} bytes, which is not a multiple of the block size, {} bytes",
+                url,
+                size,
+                block_size
+            );
+        }
+    }
+
+    if let Some(sha256) = sha256 {
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow!("downloading {}: {}", url, e))?;
+
+        let mut progress = ctx.io.start_progress_bar("Checksumming", size);
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        let mut body = response.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| anyhow!("downloading {}: {}", url, e))?;
+            context.update(&chunk);
+            if let Some(progress) = &mut progress {
+                progress.inc(chunk.len() as u64);
+            }
+        }
+        drop(progress);
+
+        let digest = data_encoding::HEXLOWER.encode(context.finish().as_ref());
+        if !digest.eq_ignore_ascii_case(sha256) {
+            bail!("SHA-256 checksum mismatch: {} is {}, expected {}", url, digest, sha256);
+        }
+    }
+
+    Ok(())
+}
+
+/// Wait for a project image to be available, i.e. for the rack to know its size.
+pub async fn wait_for_image(
+    ctx: &mut crate::context::Context<'_>,
+    organization: &str,
+    project: &str,
+    image: &str,
+    opts: &crate::wait::WaitOptions,
+) -> Result<()> {
+    let client = ctx.api_client("")?;
+    let client = &client;
+    crate::wait::poll_with_spinner(
+        ctx,
+        opts,
+        &format!("image {} to be available", image),
+        &format!("Image {} available", image),
+        || async move {
+            let image = client.images().get(image, organization, project).await?;
+
+            // The rack only knows the size once it has fetched the image.
+            Ok(if image.size > 0 {
+                crate::wait::Poll::Done
+            } else {
+                crate::wait::Poll::Pending("importing".to_string())
+            })
+        },
+    )
+    .await
+}
+
+/// Serve the file at `path`, of `size` bytes, over HTTP at `url_path` until the task
+/// is dropped. Only `GET` and `HEAD` are supported, with byte ranges, which is all the
+/// rack needs to fetch an image from a URL.
+///
+/// `served` is updated with how many distinct bytes of the file were sent so far, over
+/// all the requests: bytes sent again, e.g. when the rack retries a request, only count
+/// once, so it reaches `size` once every byte of the file was sent.
+pub async fn serve(
+    listener: TcpListener,
+    path: PathBuf,
+    url_path: String,
+    size: u64,
+    mut progress: Option<ProgressBar>,
+    served: tokio::sync::watch::Sender<u64>,
+) {
+    let mut sent = Ranges::default();
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_) => continue,
+        };
+
+        // One request per connection, a failed request is for the client to retry.
+        let _ = respond(stream, &path, &url_path, size, |start, n| {
+            let new = sent.add(start, start + n);
+            if let Some(progress) = &mut progress {
+                progress.inc(new);
+            }
+            served.send(sent.len()).ok();
+        })
+        .await;
+    }
+}
+
+async fn respond<F>(stream: TcpStream, path: &Path, url_path: &str, size: u64, mut on_sent: F) -> Result<()>
+where
+    F: FnMut(u64, u64),
+{
+    let mut stream = BufReader::new(stream);
+
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") && !head.ends_with(b"\n\n") {
+        if stream.read_until(b'\n', &mut head).await? == 0 || head.len() > MAX_REQUEST_HEAD_LENGTH {
+            bail!("incomplete request");
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let request = parse_request(&head)?;
+
+    let (status, range) = if request.method != "GET" && request.method != "HEAD" {
+        ("405 Method Not Allowed", None)
+    } else if request.path != url_path {
+        ("404 Not Found", None)
+    } else {
+        match request.range.map(|range| parse_range(range, size)) {
+            None => ("200 OK", Some((0, size))),
+            Some(Some(range)) => ("206 Partial Content", Some(range)),
+            Some(None) => ("416 Range Not Satisfiable", None),
+        }
+    };
+
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\nAccept-Ranges: bytes\r\n", status);
+    match range {
+        Some((start, end)) => {
+            response.push_str("Content-Type: application/octet-stream\r\n");
+            response.push_str(&format!("Content-Length: {}\r\n", end - start));
+            if status.starts_with("206") {
+                response.push_str(&format!("Content-Range: bytes {}-{}/{}\r\n", start, end - 1, size));
+            }
+        }
+        None => {
+            if status.starts_with("416") {
+                response.push_str(&format!("Content-Range: bytes */{}\r\n", size));
+            }
+            response.push_str("Content-Length: 0\r\n");
+        }
+    }
+    response.push_str("\r\n");
+
+    let stream = stream.get_mut();
+    stream.write_all(response.as_bytes()).await?;
+
+    if let (Some((start, end)), "GET") = (range, request.method) {
+        let mut file = tokio::fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        let mut file = file.take(end - start);
+
+        let mut buf = vec![0; 64 * 1024];
+        let mut offset = start;
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            stream.write_all(&buf[..n]).await?;
+            on_sent(offset, n as u64);
+            offset += n as u64;
+        }
+    }
+
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// The byte ranges of a file that were sent, in order and merged.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Ranges(Vec<(u64, u64)>);
+
+impl Ranges {
+    /// Add the bytes from `start` up to `end`, returning how many were not in yet.
+    fn add(&mut self, start: u64, end: u64) -> u64 {
+        let before = self.len();
+
+        let (mut start, mut end) = (start, end);
+        let mut ranges = Vec::with_capacity(self.0.len() + 1);
+        for &(s, e) in &self.0 {
+            if e < start || s > end {
+                ranges.push((s, e));
+            } else {
+                start = start.min(s);
+                end = end.max(e);
+            }
+        }
+        ranges.push((start, end));
+        ranges.sort_unstable();
+        self.0 = ranges;
+
+        self.len() - before
+    }
+
+    /// How many bytes are in.
+    fn len(&self) -> u64 {
+        self.0.iter().map(|(start, end)| end - start).sum()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Request<'a> {
+    method: &'a str,
+    path: &'a str,
+    range: Option<&'a str>,
+}
+
+fn parse_request(head: &str) -> Result<Request<'_>> {
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (method, path) = match (request_line.next(), request_line.next()) {
+        (Some(method), Some(path)) => (method, path),
+        _ => bail!("invalid request line"),
+    };
+
+    let range = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("range").then(|| value.trim())
+    });
+
+    Ok(Request { method, path, range })
+}
+
+/// The bytes, from the start to the end excluded, of a `Range` header with a single
+/// range, e.g. `bytes=0-1023`, `bytes=1024-` or `bytes=-512`.
+fn parse_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => (size.saturating_sub(suffix.parse().ok()?), size),
+        (start, "") => (start.parse().ok()?, size),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.saturating_add(1).min(size),
+        ),
+    };
+
+    if start < end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            parse_request("GET /abc/disk.raw HTTP/1.1\r\nHost: 10.0.0.2\r\nrange: bytes=0-9\r\n\r\n").unwrap(),
+            Request {
+                method: "GET",
+                path: "/abc/disk.raw",
+                range: Some("bytes=0-9"),
+            }
+        );
+
+        assert_eq!(parse_range("bytes=0-9", 100), Some((0, 10)));
+        assert_eq!(parse_range("bytes=90-", 100), Some((90, 100)));
+        assert_eq!(parse_range("bytes=-10", 100), Some((90, 100)));
+        assert_eq!(parse_range("bytes=50-500", 100), Some((50, 100)));
+        assert_eq!(parse_range("bytes=100-", 100), None);
+        assert_eq!(parse_range("bytes=0-1,5-9", 100), None);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut ranges = Ranges::default();
+        assert_eq!(ranges.add(10, 20), 10);
+        assert_eq!(ranges.add(10, 20), 0);
+        assert_eq!(ranges.add(0, 5), 5);
+        assert_eq!(ranges.add(15, 30), 10);
+        assert_eq!(ranges, Ranges(vec![(0, 5), (10, 30)]));
+        assert_eq!(ranges.add(5, 10), 5);
+        assert_eq!(ranges, Ranges(vec![(0, 30)]));
+        assert_eq!(ranges.len(), 30);
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.raw");
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::watch::channel(0);
+        let server = tokio::spawn(serve(listener, path, "/disk.raw".to_string(), 10, None, tx));
+
+        let get = |request: &'static str| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        assert_eq!(
+            get("HEAD /disk.raw HTTP/1.1\r\n\r\n").await,
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nAccept-Ranges: bytes\r\nContent-Type: \
+             application/octet-stream\r\nContent-Length: 10\r\n\r\n"
+        );
+        assert_eq!(
+            get("GET /disk.raw HTTP/1.1\r\nRange: bytes=2-4\r\n\r\n").await,
+            "HTTP/1.1 206 Partial Content\r\nConnection: close\r\nAccept-Ranges: bytes\r\nContent-Type: \
+             application/octet-stream\r\nContent-Length: 3\r\nContent-Range: bytes 2-4/10\r\n\r\n234"
+        );
+        assert_eq!(
+            get("GET /other.raw HTTP/1.1\r\n\r\n").await,
+            "HTTP/1.1 404 Not Found\r\nConnection: close\r\nAccept-Ranges: bytes\r\nContent-Length: 0\r\n\r\n"
+        );
+        assert_eq!(*rx.borrow(), 3);
+
+        // Bytes sent again only count once.
+        get("GET /disk.raw HTTP/1.1\r\nRange: bytes=2-4\r\n\r\n").await;
+        assert_eq!(*rx.borrow(), 3);
+        get("GET /disk.raw HTTP/1.1\r\n\r\n").await;
+        assert_eq!(*rx.borrow(), 10);
+
+        server.abort();
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use parse_display::{Display, FromStr};
+use serde_json::{Map, Value};
+
+/// The formats of `oxide instance inventory`.
+#[derive(Debug, Clone, PartialEq, Eq, FromStr, Display)]
+#[display(style = "kebab-case")]
+pub enum InventoryFormat {
+    /// The JSON printed by Ansible inventory scripts for `--list`.
+    AnsibleJson,
+    /// The INI format of static Ansible inventory files.
+    Ini,
+}
+
+/// A host of an Ansible inventory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Host {
+    pub name: String,
+    pub groups: Vec<String>,
+    pub vars: Map<String, Value>,
+}
+
+/// Turns a project name, or an instance state, into a valid Ansible group name, they
+/// cannot contain dashes.
+pub fn group_name(prefix: &str, name: &str) -> String {
+    format!("{}_{}", prefix, name.replace('-', "_"))
+}
+
+fn groups(hosts: &[Host]) -> BTreeMap<&str, Vec<&Host>> {
+    let mut groups: BTreeMap<&str, Vec<&Host>> = BTreeMap::new();
+    for host in hosts {
+        for group in &host.groups {
+            groups.entry(group).or_default().push(host);
+        }
+    }
+    groups
+}
+
+/// The inventory in the format of `--list`, with the variables of every host under
+/// `_meta` so that Ansible does not have to ask for them host by host.
+pub fn to_json(hosts: &[Host]) -> Value {
+    let mut inventory = Map::new();
+
+    for (group, hosts) in groups(hosts) {
+        let names = hosts.iter().map(|host| host.name.as_str()).collect::<Vec<_>>();
+        inventory.insert(group.to_string(), serde_json::json!({ "hosts": names }));
+    }
+
+    let hostvars = hosts
+        .iter()
+        .map(|host| (host.name.to_string(), Value::Object(host.vars.clone())))
+        .collect::<Map<_, _>>();
+    inventory.insert("_meta".to_string(), serde_json::json!({ "hostvars": hostvars }));
+
+    Value::Object(inventory)
+}
+
+/// The inventory as an INI file. The variables of a host are set on its line in the
+/// first of its groups.
+pub fn to_ini(hosts: &[Host]) -> String {
+    let mut ini = String::new();
+
+    for (group, hosts) in groups(hosts) {
+        if !ini.is_empty() {
+            ini.push('\n');
+        }
+        ini.push_str(&format!("[{}]\n", group));
+
+        for host in hosts {
+            ini.push_str(&host.name);
+
+            if host.groups.first().map(|first| first.as_str()) == Some(group) {
+                for (key, value) in &host.vars {
+                    let value = match value {
+                        Value::Null => continue,
+                        Value::String(s) => shlex::quote(s).to_string(),
+                        value => value.to_string(),
+                    };
+                    ini.push_str(&format!(" {}={}", key, value));
+                }
+            }
+
+            ini.push('\n');
+        }
+    }
+
+    ini
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn hosts() -> Vec<Host> {
+        let vars = |ip: Option<&str>, state: &str| {
+            let mut vars = Map::new();
+            if let Some(ip) = ip {
+                vars.insert("ansible_host".to_string(), serde_json::json!(ip));
+            }
+            vars.insert("oxide_ncpus".to_string(), serde_json::json!(2));
+            vars.insert("oxide_run_state".to_string(), serde_json::json!(state));
+            vars
+        };
+
+        vec![
+            Host {
+                name: "web-1".to_string(),
+                groups: vec![group_name("project", "prod-eu"), group_name("state", "running")],
+                vars: vars(Some("203.0.113.5"), "running"),
+            },
+            Host {
+                name: "db-1".to_string(),
+                groups: vec![group_name("project", "prod-eu"), group_name("state", "stopped")],
+                vars: vars(None, "stopped"),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_inventory_json() {
+        assert_eq!(
+            to_json(&hosts()),
+            serde_json::json!({
+                "project_prod_eu": { "hosts": ["web-1", "db-1"] },
+                "state_running": { "hosts": ["web-1"] },
+                "state_stopped": { "hosts": ["db-1"] },
+                "_meta": {
+                    "hostvars": {
+                        "web-1": { "ansible_host": "203.0.113.5", "oxide_ncpus": 2, "oxide_run_state": "running" },
+                        "db-1": { "oxide_ncpus": 2, "oxide_run_state": "stopped" },
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_inventory_ini() {
+        assert_eq!(
+            to_ini(&hosts()),
+            r#"[project_prod_eu]
+web-1 ansible_host=203.0.113.5 oxide_ncpus=2 oxide_run_state=running
+db-1 oxide_ncpus=2 oxide_run_state=stopped
+
+[state_running]
+web-1
+
+[state_stopped]
+db-1
+"#
+        );
+    }
+}
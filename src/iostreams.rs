@@ -2,6 +2,11 @@ use anyhow::{anyhow, Result};
 use terminal_size::{terminal_size, Height, Width};
 
 const DEFAULT_WIDTH: i32 = 80;
+const DEFAULT_HEIGHT: i32 = 24;
+
+/// The columns of tables that hold a number of bytes, shown in binary units, e.g.
+/// `20.00 GiB`. The JSON and YAML output keep the number of bytes.
+const BYTE_COLUMNS: &[&str] = &["size"];
 
 pub struct IoStreams {
     pub stdin: Box<dyn std::io::Read + Send + Sync>,
@@ -28,6 +33,9 @@ pub struct IoStreams {
     never_prompt: bool,
 
     pub tmp_file_override: Option<std::fs::File>,
+
+    /// The file `err_out` writes to in tests, so that progress bars can write to it too.
+    err_out_file: Option<std::fs::File>,
 }
 
 impl IoStreams {
@@ -169,7 +177,32 @@ impl IoStreams {
         Some(pi.start())
     }
 
-    #[allow(dead_code)]
+    /// This returns a progress bar for a transfer of `total` bytes, if we know how many.
+    /// The bar is finished when it is dropped.
+    pub fn start_progress_bar(&self, label: &str, total: Option<u64>) -> Option<crate::progress::ProgressBar> {
+        if !self.progress_indicator_enabled {
+            return None;
+        }
+
+        Some(crate::progress::ProgressBar::new(
+            label,
+            total,
+            self.terminal_width() as usize,
+            self.err_out_handle(),
+        ))
+    }
+
+    /// Another handle on `err_out`, for progress bars, which outlive borrows of us
+    /// since transfers run in their own tasks.
+    fn err_out_handle(&self) -> Box<dyn std::io::Write + Send + Sync> {
+        match self.err_out_file.as_ref().map(|file| file.try_clone()) {
+            Some(Ok(file)) => Box::new(file),
+            // Failing to draw a progress bar is no reason to fail the transfer.
+            Some(Err(_)) => Box::new(std::io::sink()),
+            None => Box::new(std::io::stderr()),
+        }
+    }
+
     pub fn terminal_width(&self) -> i32 {
         if self.terminal_width_override > 0 {
             return self.terminal_width_override;
@@ -179,6 +212,11 @@ impl IoStreams {
         w
     }
 
+    pub fn terminal_height(&self) -> i32 {
+        let (_, h) = tty_size().unwrap_or((0, DEFAULT_HEIGHT));
+        h
+    }
+
     pub fn force_terminal(&mut self, spec: &str) {
         self.color_enabled = !crate::colors::env_color_disabled();
         self.set_stdout_tty(true);
@@ -250,7 +288,11 @@ impl IoStreams {
     }
 
     pub fn write_output_table_for_vec<T: tabled::Tabled>(&mut self, value: impl IntoIterator<Item = T>) -> Result<()> {
-        let table = tabled::Table::new(value).with(tabled::Style::psql()).to_string();
+        let rows = value
+            .into_iter()
+            .map(|item| TableRow::<T>::new(table_fields(&item)))
+            .collect::<Vec<_>>();
+        let table = tabled::Table::new(rows).with(tabled::Style::psql()).to_string();
 
         writeln!(self.out, "{}", table)?;
 
@@ -258,7 +300,7 @@ impl IoStreams {
     }
 
     pub fn write_output_table<T: tabled::Tabled>(&mut self, value: &T) -> Result<()> {
-        let table = tabled::Table::new(vec![value])
+        let table = tabled::Table::new(vec![TableRow::<T>::new(table_fields(value))])
             .with(tabled::Rotate::Left)
             .with(
                 tabled::Modify::new(tabled::Full)
@@ -321,6 +363,7 @@ impl IoStreams {
 
             never_prompt: false,
             tmp_file_override: None,
+            err_out_file: None,
         };
 
         if stdout_is_tty && stderr_is_tty {
@@ -342,6 +385,7 @@ impl IoStreams {
         let (stderr, stderr_path) = tempfile::NamedTempFile::new().unwrap().keep().unwrap();
 
         io.out = Box::new(stdout);
+        io.err_out_file = Some(stderr.try_clone().unwrap());
         io.err_out = Box::new(stderr);
 
         io.tty_size = test_tty_size;
@@ -354,6 +398,46 @@ impl IoStreams {
     }
 }
 
+/// A row of already formatted, and possibly colored, cells rendered with the
+/// headers of `T`.
+pub struct TableRow<T> {
+    cells: Vec<String>,
+    _item: std::marker::PhantomData<T>,
+}
+
+impl<T> TableRow<T> {
+    pub fn new(cells: Vec<String>) -> Self {
+        TableRow {
+            cells,
+            _item: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: tabled::Tabled> tabled::Tabled for TableRow<T> {
+    const LENGTH: usize = T::LENGTH;
+
+    fn fields(&self) -> Vec<String> {
+        self.cells.clone()
+    }
+
+    fn headers() -> Vec<String> {
+        T::headers()
+    }
+}
+
+/// The cells of `item` in a table, with the sizes in binary units.
+pub fn table_fields<T: tabled::Tabled>(item: &T) -> Vec<String> {
+    T::headers()
+        .iter()
+        .zip(item.fields())
+        .map(|(header, cell)| match cell.parse::<u128>() {
+            Ok(bytes) if BYTE_COLUMNS.contains(&header.as_str()) => crate::progress::format_bytes(bytes),
+            _ => cell,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 fn test_tty_size() -> Result<(i32, i32)> {
     Err(anyhow::anyhow!("tty_size not implemented in tests"))
@@ -372,9 +456,17 @@ fn tty_size() -> Result<(i32, i32)> {
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
+    use tabled::Tabled;
 
     use super::*;
 
+    #[derive(Tabled)]
+    struct Volume {
+        name: String,
+        size: u64,
+        block_size: u64,
+    }
+
     pub struct TestItem {
         name: String,
         io: IoStreams,
@@ -442,4 +534,29 @@ mod test {
             assert_eq!(width, t.want_width, "test {}", t.name);
         }
     }
+
+    #[test]
+    fn test_table_fields() {
+        let volume = Volume {
+            name: "boot".to_string(),
+            size: 20 * 1024 * 1024 * 1024,
+            block_size: 512,
+        };
+
+        assert_eq!(table_fields(&volume), vec!["boot", "20.00 GiB", "512"]);
+    }
+
+    #[test]
+    fn test_progress_bar_on_err_out() {
+        let (mut io, _, stderr_path) = IoStreams::test();
+        io.progress_indicator_enabled = true;
+
+        let mut bar = io.start_progress_bar("app.toml", Some(10)).unwrap();
+        bar.inc(10);
+        drop(bar);
+
+        let stderr = std::fs::read_to_string(stderr_path).unwrap();
+        assert!(stderr.contains("app.toml"), "stderr: {:?}", stderr);
+        assert!(stderr.ends_with('\n'), "stderr: {:?}", stderr);
+    }
 }
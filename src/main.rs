@@ -15,6 +15,9 @@ pub mod cmd_auth;
 pub mod cmd_completion;
 /// The config command.
 pub mod cmd_config;
+#[cfg(unix)]
+/// The dashboard command.
+pub mod cmd_dashboard;
 /// The disk command.
 pub mod cmd_disk;
 /// The generate command.
@@ -56,6 +59,8 @@ pub mod cmd_update;
 pub mod cmd_version;
 /// The vpc command.
 pub mod cmd_vpc;
+/// The wait command.
+pub mod cmd_wait;
 
 // Use of a mod or pub mod is not actually necessary.
 mod built_info {
@@ -63,6 +68,8 @@ mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
 
+mod bulk;
+mod cloud_init;
 mod colors;
 mod config;
 mod config_alias;
@@ -73,14 +80,26 @@ mod config_map;
 mod context;
 mod docs_man;
 mod docs_markdown;
+mod edit;
+mod image;
+mod inventory;
 mod iostreams;
+mod progress;
 mod prompt_ext;
+mod schema;
+mod serial;
+mod serial_script;
+mod ssh;
+mod teardown;
+mod topology;
 mod types;
 
 #[cfg(test)]
 mod tests;
 
 mod update;
+mod wait;
+mod watch;
 
 use std::io::{Read, Write};
 
@@ -153,6 +172,9 @@ enum SubCommand {
     Auth(cmd_auth::CmdAuth),
     Completion(cmd_completion::CmdCompletion),
     Config(cmd_config::CmdConfig),
+    #[cfg(unix)]
+    #[clap(alias = "top", alias = "tui")]
+    Dashboard(cmd_dashboard::CmdDashboard),
     #[clap(alias = "disks")]
     Disk(cmd_disk::CmdDisk),
     Generate(cmd_generate::CmdGenerate),
@@ -186,6 +208,7 @@ enum SubCommand {
     Version(cmd_version::CmdVersion),
     #[clap(alias = "vpcs")]
     Vpc(cmd_vpc::CmdVpc),
+    Wait(cmd_wait::CmdWait),
 }
 
 #[tokio::main]
@@ -294,6 +317,8 @@ async fn do_main(mut args: Vec<String>, ctx: &mut crate::context::Context<'_>) -
         SubCommand::Auth(cmd) => run_cmd(&cmd, ctx).await,
         SubCommand::Completion(cmd) => run_cmd(&cmd, ctx).await,
         SubCommand::Config(cmd) => run_cmd(&cmd, ctx).await,
+        #[cfg(unix)]
+        SubCommand::Dashboard(cmd) => run_cmd(&cmd, ctx).await,
         SubCommand::Disk(cmd) => run_cmd(&cmd, ctx).await,
         SubCommand::Generate(cmd) => run_cmd(&cmd, ctx).await,
         SubCommand::Image(cmd) => run_cmd(&cmd, ctx).await,
@@ -312,6 +337,7 @@ async fn do_main(mut args: Vec<String>, ctx: &mut crate::context::Context<'_>) -
         SubCommand::Update(cmd) => run_cmd(&cmd, ctx).await,
         SubCommand::Version(cmd) => run_cmd(&cmd, ctx).await,
         SubCommand::Vpc(cmd) => run_cmd(&cmd, ctx).await,
+        SubCommand::Wait(cmd) => run_cmd(&cmd, ctx).await,
     }
 }
 
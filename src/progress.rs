@@ -0,0 +1,163 @@
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// How often the progress bar is redrawn, drawing it for every chunk of a transfer
+/// would slow the transfer down.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The bar itself is never wider than this, the rest of the line is left for the label
+/// and the numbers.
+const MAX_BAR_WIDTH: usize = 40;
+
+/// Below this there is no point drawing the bar, the percentage says it all.
+const MIN_BAR_WIDTH: usize = 10;
+
+/// A progress bar for transfers, drawn on the stderr of the command.
+///
+/// It shows how many bytes were transferred, out of how many when the total is known,
+/// and the throughput. The final state of the bar is drawn when it is dropped.
+///
+/// Use `IoStreams::start_progress_bar` to get one, it only returns a bar when we are
+/// attached to a terminal.
+pub struct ProgressBar {
+    label: String,
+    total: Option<u64>,
+    position: u64,
+    width: usize,
+    started: Instant,
+    drawn: Option<Instant>,
+    out: Box<dyn Write + Send + Sync>,
+}
+
+impl ProgressBar {
+    pub fn new(label: &str, total: Option<u64>, width: usize, out: Box<dyn Write + Send + Sync>) -> Self {
+        ProgressBar {
+            label: label.to_string(),
+            total,
+            position: 0,
+            width,
+            started: Instant::now(),
+            drawn: None,
+            out,
+        }
+    }
+
+    /// Record that `n` more bytes were transferred.
+    pub fn inc(&mut self, n: u64) {
+        self.position += n;
+
+        let due = match self.drawn {
+            Some(drawn) => drawn.elapsed() >= REDRAW_INTERVAL,
+            None => true,
+        };
+        if due {
+            self.draw();
+        }
+    }
+
+    fn draw(&mut self) {
+        self.drawn = Some(Instant::now());
+
+        // Failing to draw the bar is no reason to fail the transfer.
+        let line = self.line(self.started.elapsed());
+        write!(self.out, "\r{}\x1b[K", line).ok();
+        self.out.flush().ok();
+    }
+
+    fn line(&self, elapsed: Duration) -> String {
+        let throughput = match elapsed.as_secs_f64() {
+            secs if secs > 0.0 => (self.position as f64 / secs) as u128,
+            _ => 0,
+        };
+
+        let total = match self.total {
+            Some(total) => total,
+            None => {
+                return format!(
+                    "{} {} {}/s",
+                    self.label,
+                    format_bytes(self.position as u128),
+                    format_bytes(throughput)
+                )
+            }
+        };
+
+        // The total is sometimes an estimate, e.g. the size of the files in an archive
+        // without its headers, do not go past it.
+        let position = self.position.min(total);
+        let percent = match total {
+            0 => 100,
+            total => (position * 100 / total) as usize,
+        };
+        let numbers = format!(
+            " {:>3}% {}/{} {}/s",
+            percent,
+            format_bytes(position as u128),
+            format_bytes(total as u128),
+            format_bytes(throughput)
+        );
+
+        // The label, a space and the brackets around the bar.
+        let taken = self.label.chars().count() + numbers.chars().count() + 3;
+        let bar_width = self.width.saturating_sub(taken).min(MAX_BAR_WIDTH);
+        if bar_width < MIN_BAR_WIDTH {
+            return format!("{}{}", self.label, numbers);
+        }
+
+        let filled = bar_width * percent / 100;
+        format!(
+            "{} [{}{}]{}",
+            self.label,
+            "=".repeat(filled),
+            " ".repeat(bar_width - filled),
+            numbers
+        )
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        self.draw();
+        writeln!(self.out).ok();
+    }
+}
+
+/// A number of bytes in binary units, e.g. `2.44 KiB`.
+pub fn format_bytes(bytes: u128) -> String {
+    byte_unit::Byte::from_bytes(bytes)
+        .get_appropriate_unit(true)
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_progress_bar_line() {
+        let mut bar = ProgressBar::new("app.toml", Some(10_000), 80, Box::new(std::io::sink()));
+        bar.position = 2_500;
+
+        assert_eq!(
+            bar.line(Duration::from_secs(2)),
+            "app.toml [========                           ]  25% 2.44 KiB/9.77 KiB 1.22 KiB/s"
+        );
+
+        let mut narrow = ProgressBar::new("app.toml", Some(10_000), 40, Box::new(std::io::sink()));
+        narrow.position = 20_000;
+
+        assert_eq!(
+            narrow.line(Duration::from_secs(1)),
+            "app.toml 100% 9.77 KiB/9.77 KiB 19.53 KiB/s"
+        );
+
+        let mut unknown = ProgressBar::new("logs", None, 80, Box::new(std::io::sink()));
+        unknown.position = 3_000_000;
+
+        assert_eq!(unknown.line(Duration::ZERO), "logs 2.86 MiB 0 B/s");
+    }
+}
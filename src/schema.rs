@@ -0,0 +1,414 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// The OpenAPI document the commands are generated from, for its schemas.
+const SPEC: &str = include_str!("../spec.json");
+
+/// Something wrong with a value, at a path like `disks[0].disk_source.image_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Validate `value` against the schema named `name` in the components of the API
+/// spec, e.g. `InstanceCreate`.
+///
+/// This covers the parts of JSON schema that the spec uses, and every problem found
+/// is returned rather than the first one.
+pub fn validate(name: &str, value: &Value) -> Result<Vec<Error>> {
+    let spec: Value = serde_json::from_str(SPEC)?;
+    let schemas = &spec["components"]["schemas"];
+    let schema = schemas
+        .get(name)
+        .ok_or_else(|| anyhow!("no schema named `{}` in the API spec", name))?;
+
+    let mut errors = Vec::new();
+    Validator { schemas }.validate(schema, value, "", &mut errors);
+    Ok(errors)
+}
+
+/// Fill in the properties of an object that are missing, and have a default value
+/// in the schema named `name`.
+pub fn apply_defaults(name: &str, value: &mut Value) -> Result<()> {
+    let spec: Value = serde_json::from_str(SPEC)?;
+    let properties = &spec["components"]["schemas"][name]["properties"];
+
+    if let (Some(properties), Some(object)) = (properties.as_object(), value.as_object_mut()) {
+        for (property, schema) in properties {
+            if let Some(default) = schema.get("default") {
+                object.entry(property).or_insert_with(|| default.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct Validator<'a> {
+    schemas: &'a Value,
+}
+
+impl Validator<'_> {
+    fn validate(&self, schema: &Value, value: &Value, path: &str, errors: &mut Vec<Error>) {
+        let error = |message: String| Error {
+            path: path.to_string(),
+            message,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            match self.schemas.get(name) {
+                Some(schema) => self.validate(schema, value, path, errors),
+                None => errors.push(error(format!("unknown schema `{}`", reference))),
+            }
+            return;
+        }
+
+        if value.is_null() && schema.get("nullable").and_then(Value::as_bool) == Some(true) {
+            return;
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for schema in all {
+                self.validate(schema, value, path, errors);
+            }
+        }
+
+        if let Some(variants) = schema.get("oneOf").and_then(Value::as_array) {
+            self.validate_one_of(variants, value, path, errors);
+        }
+
+        match schema.get("type").and_then(Value::as_str) {
+            Some("object") => self.validate_object(schema, value, path, errors),
+            Some("array") => match value.as_array() {
+                Some(items) => {
+                    if let Some(schema) = schema.get("items") {
+                        for (i, item) in items.iter().enumerate() {
+                            self.validate(schema, item, &format!("{}[{}]", path, i), errors);
+                        }
+                    }
+                }
+                None => errors.push(error(format!("expected a list, got {}", describe(value)))),
+            },
+            Some("string") => match value.as_str() {
+                Some(s) => {
+                    if let Some(message) = check_string(schema, s) {
+                        errors.push(error(message));
+                    }
+                }
+                None => errors.push(error(format!("expected a string, got {}", describe(value)))),
+            },
+            Some("integer") => {
+                if let Some(message) = check_integer(schema, value) {
+                    errors.push(error(message));
+                }
+            }
+            Some("number") if !value.is_number() => {
+                errors.push(error(format!("expected a number, got {}", describe(value))))
+            }
+            Some("boolean") if !value.is_boolean() => {
+                errors.push(error(format!("expected true or false, got {}", describe(value))))
+            }
+            _ => {}
+        }
+
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !value.is_null() && !values.contains(value) {
+                errors.push(error(format!(
+                    "expected one of {}, got {}",
+                    list(values),
+                    describe(value)
+                )));
+            }
+        }
+    }
+
+    fn validate_object(&self, schema: &Value, value: &Value, path: &str, errors: &mut Vec<Error>) {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => {
+                errors.push(Error {
+                    path: path.to_string(),
+                    message: format!("expected a mapping, got {}", describe(value)),
+                });
+                return;
+            }
+        };
+        let properties = schema.get("properties").and_then(Value::as_object);
+
+        for required in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(required) = required.as_str() {
+                if !object.contains_key(required) {
+                    errors.push(Error {
+                        path: join(path, required),
+                        message: "is required".to_string(),
+                    });
+                }
+            }
+        }
+
+        for (key, value) in object {
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(schema) => self.validate(schema, value, &join(path, key), errors),
+                None => {
+                    let known: Vec<Value> = properties
+                        .map(|properties| properties.keys().map(|key| Value::String(key.to_string())).collect())
+                        .unwrap_or_default();
+                    errors.push(Error {
+                        path: join(path, key),
+                        message: format!("unknown field, expected one of {}", list(&known)),
+                    });
+                }
+            }
+        }
+    }
+
+    /// The variants of a `oneOf` in the spec are told apart by their `type`, so we
+    /// only report the problems of the variant of that type.
+    fn validate_one_of(&self, variants: &[Value], value: &Value, path: &str, errors: &mut Vec<Error>) {
+        let types = variants
+            .iter()
+            .filter_map(|variant| variant["properties"]["type"]["enum"].as_array())
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if types.len() == variants.len() && value.is_object() {
+            let variant = variants
+                .iter()
+                .find(|variant| variant["properties"]["type"]["enum"].as_array() == Some(&vec![value["type"].clone()]));
+            match variant {
+                Some(variant) => self.validate(variant, value, path, errors),
+                None if value["type"].is_null() => errors.push(Error {
+                    path: join(path, "type"),
+                    message: format!("is required, one of {}", list(&types)),
+                }),
+                None => errors.push(Error {
+                    path: join(path, "type"),
+                    message: format!("expected one of {}, got {}", list(&types), describe(&value["type"])),
+                }),
+            }
+            return;
+        }
+
+        // Otherwise, report the problems of the variant that came closest.
+        let mut closest: Option<Vec<Error>> = None;
+        for variant in variants {
+            let mut variant_errors = Vec::new();
+            self.validate(variant, value, path, &mut variant_errors);
+            if variant_errors.is_empty() {
+                return;
+            }
+            if closest.as_ref().map(|closest| variant_errors.len() < closest.len()) != Some(false) {
+                closest = Some(variant_errors);
+            }
+        }
+        errors.extend(closest.unwrap_or_default());
+    }
+}
+
+fn check_string(schema: &Value, s: &str) -> Option<String> {
+    let length = s.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            return Some(format!("must be at least {} characters long", min));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            return Some(format!("must be at most {} characters long", max));
+        }
+    }
+
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        if !matches_pattern(pattern, s) {
+            return Some(match schema.get("description").and_then(Value::as_str) {
+                Some(description) => format!("`{}` is invalid. {}", s, description),
+                None => format!("`{}` does not match `{}`", s, pattern),
+            });
+        }
+    }
+
+    let valid = match schema.get("format").and_then(Value::as_str) {
+        Some("uuid") => s.parse::<uuid::Uuid>().is_ok(),
+        Some("ip") => s.parse::<std::net::IpAddr>().is_ok(),
+        Some("ipv4") => s.parse::<std::net::Ipv4Addr>().is_ok(),
+        Some("ipv6") => s.parse::<std::net::Ipv6Addr>().is_ok(),
+        Some("date-time") => chrono::DateTime::parse_from_rfc3339(s).is_ok(),
+        _ => true,
+    };
+    if !valid {
+        return Some(format!(
+            "`{}` is not a valid {}",
+            s,
+            schema["format"].as_str().unwrap_or_default()
+        ));
+    }
+
+    None
+}
+
+fn check_integer(schema: &Value, value: &Value) -> Option<String> {
+    let n = match value
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| value.as_u64().map(i128::from))
+    {
+        Some(n) => n,
+        None => return Some(format!("expected an integer, got {}", describe(value))),
+    };
+
+    let max = match schema.get("format").and_then(Value::as_str) {
+        Some("uint8") => Some(u8::MAX as i128),
+        Some("uint16") => Some(u16::MAX as i128),
+        Some("uint32") => Some(u32::MAX as i128),
+        // The API takes byte counts as an i64.
+        Some("uint64") | Some("int64") => Some(i64::MAX as i128),
+        _ => None,
+    };
+    let min = match schema.get("minimum").and_then(Value::as_i64) {
+        Some(min) => Some(min as i128),
+        None if schema["format"].as_str().unwrap_or_default().starts_with("uint") => Some(0),
+        None => None,
+    };
+
+    match (min, max) {
+        (Some(min), _) if n < min => Some(format!("must be at least {}", min)),
+        (_, Some(max)) if n > max => Some(format!("must be at most {}", max)),
+        _ => None,
+    }
+}
+
+/// The regex crate does not do look-arounds, the spec only uses a leading negative
+/// lookahead, e.g. names that are not UUIDs, so that one is checked on its own.
+fn matches_pattern(pattern: &str, s: &str) -> bool {
+    let (negative, pattern) = match pattern.strip_prefix("^(?!") {
+        Some(rest) => {
+            let mut depth = 1;
+            let mut escaped = false;
+            let end = rest.char_indices().find_map(|(i, c)| {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    Some(i)
+                } else {
+                    None
+                }
+            });
+            match end {
+                Some(end) => (Some(format!("^{}", &rest[..end])), &rest[end + 1..]),
+                None => (None, pattern),
+            }
+        }
+        None => (None, pattern),
+    };
+
+    let matches = |pattern: &str| regex::Regex::new(pattern).ok().map(|regex| regex.is_match(s));
+
+    // A pattern we cannot compile is not the user's fault, let the API check it.
+    let excluded = negative.and_then(|negative| matches(&negative)).unwrap_or(false);
+    !excluded && matches(pattern).unwrap_or(true)
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn list(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|value| {
+            format!(
+                "`{}`",
+                value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "nothing".to_string(),
+        Value::Bool(b) => format!("`{}`", b),
+        Value::Number(n) => format!("`{}`", n),
+        Value::String(s) => format!("`{}`", s),
+        Value::Array(_) => "a list".to_string(),
+        Value::Object(_) => "a mapping".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let value = serde_json::json!({
+            "name": "web-1",
+            "description": "A web server",
+            "hostname": "web-1",
+            "ncpus": 2,
+            "memory": 1073741824,
+            "disks": [
+                {
+                    "type": "create",
+                    "name": "Web-boot",
+                    "description": "",
+                    "size": -1,
+                    "disk_source": { "type": "image", "image_id": "nope" },
+                },
+                { "type": "attach", "name": "web-data", "size": 10 },
+                { "type": "detach" },
+            ],
+            "network_interfaces": { "type": "default" },
+            "external_ips": [{ "type": "ephemeral", "pool_name": null }],
+            "start": "yes",
+        });
+
+        let errors = validate("InstanceCreate", &value)
+            .unwrap()
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            vec![
+                "disks[0].disk_source.image_id: `nope` is not a valid uuid",
+                "disks[0].name: `Web-boot` is invalid. Names must begin with a lower case ASCII letter, be composed \
+                 exclusively of lowercase ASCII, uppercase ASCII, numbers, and '-', and may not end with a '-'. Names \
+                 cannot be a UUID though they may contain a UUID.",
+                "disks[0].size: must be at least 0",
+                "disks[1].size: unknown field, expected one of `name`, `type`",
+                "disks[2].type: expected one of `create`, `attach`, got `detach`",
+                "start: expected true or false, got `yes`",
+            ]
+        );
+
+        assert!(matches_pattern("^(?![0-9]+$)^[a-z0-9]*$", "abc123"));
+        assert!(!matches_pattern("^(?![0-9]+$)^[a-z0-9]*$", "123"));
+    }
+}
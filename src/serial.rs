@@ -0,0 +1,401 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Result;
+
+/// The escape character of interactive sessions, unless configured otherwise with
+/// `serial_escape_char`.
+pub const DEFAULT_ESCAPE_CHAR: &str = "~";
+
+/// How many previous files `--rotate-size` keeps by default.
+pub const DEFAULT_ROTATE_KEEP: usize = 5;
+
+/// How much of the current line we keep around to match `--until` against, lines
+/// longer than that are only matched on their end.
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// Parse a size, e.g. `10MiB`.
+pub fn parse_size(size: &str) -> Result<u64> {
+    let bytes = size.parse::<byte_unit::Byte>()?;
+    Ok(bytes.get_bytes() as u64)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum AnsiState {
+    #[default]
+    Ground,
+    Escape,
+    /// `ESC (` and the like, which are followed by a single byte.
+    Charset,
+    Csi,
+    /// OSC and the other sequences that last until a string terminator.
+    String,
+    StringEscape,
+}
+
+/// Removes the ANSI escape sequences, colors, cursor movements and the like, from the
+/// serial console output. Sequences can be split across chunks.
+#[derive(Debug, Default)]
+pub struct StripAnsi {
+    state: AnsiState,
+}
+
+impl StripAnsi {
+    pub fn strip(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut stripped = Vec::with_capacity(data.len());
+
+        for &b in data {
+            self.state = match (self.state, b) {
+                (AnsiState::Ground, 0x1b) => AnsiState::Escape,
+                (AnsiState::Ground, b) => {
+                    stripped.push(b);
+                    AnsiState::Ground
+                }
+                (AnsiState::Escape, b'[') => AnsiState::Csi,
+                (AnsiState::Escape, b']' | b'P' | b'X' | b'^' | b'_') => AnsiState::String,
+                (AnsiState::Escape, b'(' | b')' | b'*' | b'+' | b'-' | b'.' | b'/' | b'#' | b'%') => AnsiState::Charset,
+                (AnsiState::Escape | AnsiState::Charset, _) => AnsiState::Ground,
+                (AnsiState::Csi, 0x40..=0x7e) => AnsiState::Ground,
+                (AnsiState::Csi, _) => AnsiState::Csi,
+                (AnsiState::String, 0x07) => AnsiState::Ground,
+                (AnsiState::String, 0x1b) => AnsiState::StringEscape,
+                (AnsiState::String, _) => AnsiState::String,
+                (AnsiState::StringEscape, _) => AnsiState::Ground,
+            };
+        }
+
+        stripped
+    }
+}
+
+/// Where the output of the serial console goes, with the changes asked for on the
+/// command line.
+pub struct Output<W: Write> {
+    out: W,
+    strip_ansi: Option<StripAnsi>,
+    timestamps: bool,
+    at_line_start: bool,
+    now: fn() -> chrono::DateTime<chrono::Utc>,
+    until: Option<Until>,
+}
+
+/// Matches `--until` against the current line of output, whatever its colors.
+struct Until {
+    regex: regex::Regex,
+    strip_ansi: StripAnsi,
+    line: String,
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(out: W, timestamps: bool, strip_ansi: bool, until: Option<regex::Regex>) -> Self {
+        Output {
+            out,
+            strip_ansi: if strip_ansi { Some(StripAnsi::default()) } else { None },
+            timestamps,
+            at_line_start: true,
+            now: chrono::Utc::now,
+            until: until.map(|regex| Until {
+                regex,
+                strip_ansi: StripAnsi::default(),
+                line: String::new(),
+            }),
+        }
+    }
+
+    /// Write a chunk of output. Returns whether `--until` matched.
+    pub fn write(&mut self, data: &[u8]) -> std::io::Result<bool> {
+        let stripped;
+        let data = match &mut self.strip_ansi {
+            Some(strip_ansi) => {
+                stripped = strip_ansi.strip(data);
+                &stripped
+            }
+            None => data,
+        };
+
+        if self.timestamps {
+            let mut rest = data;
+            while !rest.is_empty() {
+                if self.at_line_start {
+                    write!(self.out, "[{}] ", (self.now)().format("%Y-%m-%dT%H:%M:%S%.3fZ"))?;
+                }
+
+                let end = match rest.iter().position(|&b| b == b'\n') {
+                    Some(newline) => newline + 1,
+                    None => rest.len(),
+                };
+                self.out.write_all(&rest[..end])?;
+                self.at_line_start = rest[end - 1] == b'\n';
+                rest = &rest[end..];
+            }
+        } else {
+            self.out.write_all(data)?;
+        }
+        self.out.flush()?;
+
+        let until = match &mut self.until {
+            Some(until) => until,
+            None => return Ok(false),
+        };
+
+        until
+            .line
+            .push_str(&String::from_utf8_lossy(&until.strip_ansi.strip(data)));
+        let matched = until.line.lines().any(|line| until.regex.is_match(line));
+
+        // Only keep the line we are in the middle of, prompts do not end with a newline.
+        if let Some(newline) = until.line.rfind('\n') {
+            until.line.drain(..=newline);
+        }
+        if until.line.len() > MAX_LINE_LENGTH {
+            let mut start = until.line.len() - MAX_LINE_LENGTH;
+            while !until.line.is_char_boundary(start) {
+                start += 1;
+            }
+            until.line.drain(..start);
+        }
+
+        Ok(matched)
+    }
+}
+
+/// A file that is rotated when it grows past a maximum size. The previous files are
+/// kept as `<path>.1`, the most recent, `<path>.2`, and so on.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: Option<u64>,
+    keep: usize,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, it is never rotated without a `max_size`.
+    pub fn open(path: &Path, max_size: Option<u64>, keep: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_size,
+            keep,
+        })
+    }
+
+    fn numbered(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                match std::fs::rename(self.numbered(n), self.numbered(n + 1)) {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+            std::fs::rename(&self.path, self.numbered(1))?;
+        }
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(max_size) = self.max_size {
+            if self.size > 0 && self.size + buf.len() as u64 > max_size {
+                self.rotate()?;
+            }
+        }
+
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// A recording of a session in the asciicast v2 format, which can be played back
+/// with `asciinema play`, see
+/// https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md.
+pub struct Recording<W: Write> {
+    out: W,
+    started: Instant,
+    paused: Option<Instant>,
+    /// The end of the previous chunk of output, when it stopped in the middle of a
+    /// UTF-8 character.
+    partial: Vec<u8>,
+}
+
+impl<W: Write> Recording<W> {
+    /// Start a recording of a terminal of `width` columns and `height` rows.
+    pub fn new(mut out: W, width: i32, height: i32) -> std::io::Result<Self> {
+        let header = serde_json::json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": chrono::Utc::now().timestamp(),
+            "env": {
+                "TERM": std::env::var("TERM").unwrap_or_default(),
+            },
+        });
+        writeln!(out, "{}", header)?;
+
+        Ok(Recording {
+            out,
+            started: Instant::now(),
+            paused: None,
+            partial: Vec::new(),
+        })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Stop recording until `resume`, the pause is cut from the recording.
+    pub fn pause(&mut self) {
+        if self.paused.is_none() {
+            self.paused = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused) = self.paused.take() {
+            self.started += paused.elapsed();
+        }
+    }
+
+    /// Record a chunk of output.
+    pub fn output(&mut self, data: &[u8]) -> std::io::Result<()> {
+        if self.is_paused() {
+            return Ok(());
+        }
+
+        let text = self.decode(data);
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let elapsed = self.started.elapsed().as_micros() as f64 / 1_000_000.0;
+        writeln!(self.out, "{}", serde_json::json!([elapsed, "o", text]))?;
+        self.out.flush()
+    }
+
+    /// Events are text, keep the start of a character split across chunks for the
+    /// next one.
+    fn decode(&mut self, data: &[u8]) -> String {
+        self.partial.extend_from_slice(data);
+
+        let end = match std::str::from_utf8(&self.partial) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.partial.len(),
+        };
+
+        let text = String::from_utf8_lossy(&self.partial[..end]).to_string();
+        self.partial.drain(..end);
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        let mut strip_ansi = StripAnsi::default();
+
+        assert_eq!(
+            strip_ansi.strip(b"\x1b[0;32mOK\x1b[0m \x1b]0;title\x07boot\x1b(B\x1b=done"),
+            b"OK bootdone"
+        );
+
+        // Sequences split across chunks.
+        assert_eq!(strip_ansi.strip(b"a\x1b[1;3"), b"a");
+        assert_eq!(strip_ansi.strip(b"1mb\x1b]8;;http://x\x1b"), b"b");
+        assert_eq!(strip_ansi.strip(b"\\c"), b"c");
+    }
+
+    #[test]
+    fn test_output() {
+        let mut buf = Vec::new();
+        let mut output = Output::new(&mut buf, true, true, Some(regex::Regex::new("login: $").unwrap()));
+        output.now = || chrono::Utc.ymd(2022, 9, 1).and_hms_milli(12, 30, 5, 42);
+
+        assert!(!output.write(b"\x1b[1mbooting\x1b[0m\r\n\nhost lo").unwrap());
+        // The prompt is matched even though it was split, and does not end the line.
+        assert!(output.write(b"gin: ").unwrap());
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "[2022-09-01T12:30:05.042Z] booting\r\n[2022-09-01T12:30:05.042Z] \n[2022-09-01T12:30:05.042Z] host login: "
+        );
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+
+        let mut file = RotatingFile::open(&path, Some(10), 2).unwrap();
+        for chunk in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(chunk.as_bytes()).unwrap();
+        }
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(path.clone()), "fourth\n");
+        assert_eq!(read(dir.path().join("console.log.1")), "third\n");
+        assert_eq!(read(dir.path().join("console.log.2")), "second\n");
+        assert!(!dir.path().join("console.log.3").exists());
+    }
+
+    #[test]
+    fn test_recording() {
+        let mut buf = Vec::new();
+        let mut recording = Recording::new(&mut buf, 80, 24).unwrap();
+
+        recording.output(b"caf\xc3").unwrap();
+        recording.output(b"\xa9\r\n").unwrap();
+        recording.pause();
+        recording.output(b"not recorded").unwrap();
+        recording.resume();
+        recording.output(b"\x1b[0m").unwrap();
+
+        let lines = String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[0]["height"], 24);
+
+        let events = lines[1..]
+            .iter()
+            .map(|event| (event[1].as_str().unwrap(), event[2].as_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec![("o", "caf"), ("o", "é\r\n"), ("o", "\x1b[0m")]);
+    }
+}
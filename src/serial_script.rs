@@ -0,0 +1,268 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use regex::bytes::Regex;
+
+/// How long `expect` and `capture` wait, unless the script says otherwise with
+/// `timeout`.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How much of the output we keep around to match against. Output that does not
+/// match for that long is dropped from the start.
+const MAX_PENDING_LENGTH: usize = 64 * 1024;
+
+/// A command of a `--script`.
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Send these bytes to the serial console.
+    Send(Vec<u8>),
+    /// Wait for the output to match `regex`, and print the output before the match
+    /// when `capture` is set.
+    Expect {
+        regex: Regex,
+        timeout: Duration,
+        capture: bool,
+    },
+    Sleep(Duration),
+}
+
+/// A script for `oxide instance serial --script`, one command per line:
+///
+/// ```text
+/// # Comments and blank lines are ignored.
+/// # How long the following expect and capture wait, 30s by default.
+/// timeout 2m
+/// # Wait for the output to match a regular expression.
+/// expect login:
+/// # Send a line.
+/// send root
+/// # Send without the carriage return.
+/// write \x03
+/// # Like expect, and print the output before the match.
+/// capture [#$] $
+/// sleep 5s
+/// ```
+///
+/// Comments take a whole line: a `#` after a command is part of its argument.
+/// `send` and `write` understand the `\r`, `\n`, `\t`, `\e`, `\\` and `\xNN` escapes.
+#[derive(Debug, Clone)]
+pub struct Script {
+    /// The commands, with their line numbers for the errors.
+    pub commands: Vec<(usize, Command)>,
+}
+
+impl Script {
+    pub fn parse(script: &str) -> Result<Self> {
+        let mut commands = Vec::new();
+        let mut timeout = DEFAULT_TIMEOUT;
+
+        for (i, line) in script.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, arg) = match line.split_once(char::is_whitespace) {
+                Some((keyword, arg)) => (keyword, arg.trim_start()),
+                None => (line, ""),
+            };
+
+            let command = match keyword {
+                "timeout" => {
+                    timeout = crate::wait::parse_duration(arg).map_err(|e| anyhow!("line {}: {}", line_number, e))?;
+                    continue;
+                }
+                "expect" | "capture" => {
+                    if arg.is_empty() {
+                        bail!("line {}: `{}` needs a regular expression", line_number, keyword);
+                    }
+                    Command::Expect {
+                        regex: Regex::new(arg).map_err(|e| anyhow!("line {}: {}", line_number, e))?,
+                        timeout,
+                        capture: keyword == "capture",
+                    }
+                }
+                "send" => {
+                    let mut data = unescape(arg).map_err(|e| anyhow!("line {}: {}", line_number, e))?;
+                    data.push(b'\r');
+                    Command::Send(data)
+                }
+                "write" => Command::Send(unescape(arg).map_err(|e| anyhow!("line {}: {}", line_number, e))?),
+                "sleep" => Command::Sleep(
+                    crate::wait::parse_duration(arg).map_err(|e| anyhow!("line {}: {}", line_number, e))?,
+                ),
+                _ => bail!(
+                    "line {}: unknown command `{}`, expected one of `expect`, `capture`, `send`, `write`, `sleep` or \
+                     `timeout`",
+                    line_number,
+                    keyword
+                ),
+            };
+            commands.push((line_number, command));
+        }
+
+        Ok(Script { commands })
+    }
+}
+
+fn unescape(s: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            data.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('r') => data.push(b'\r'),
+            Some('n') => data.push(b'\n'),
+            Some('t') => data.push(b'\t'),
+            Some('e') => data.push(0x1b),
+            Some('\\') => data.push(b'\\'),
+            Some('x') => {
+                let hex = chars.by_ref().take(2).collect::<String>();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(b) if hex.len() == 2 => data.push(b),
+                    _ => bail!("invalid escape `\\x{}`, expected two hexadecimal digits", hex),
+                }
+            }
+            Some(c) => bail!("unknown escape `\\{}`", c),
+            None => bail!("trailing `\\`"),
+        }
+    }
+
+    Ok(data)
+}
+
+/// The output of the serial console that was not matched yet, without the ANSI escape
+/// sequences.
+#[derive(Debug, Default)]
+pub struct Pending {
+    strip: crate::serial::StripAnsi,
+    data: Vec<u8>,
+}
+
+impl Pending {
+    pub fn push(&mut self, data: &[u8]) {
+        self.data.extend(self.strip.strip(data));
+
+        if self.data.len() > MAX_PENDING_LENGTH {
+            self.data.drain(..self.data.len() - MAX_PENDING_LENGTH);
+        }
+    }
+
+    /// If the output matches `regex`, consume it up to the end of the match and return
+    /// what came before the match.
+    pub fn find(&mut self, regex: &Regex) -> Option<Vec<u8>> {
+        let m = regex.find(&self.data)?;
+        let (start, end) = (m.start(), m.end());
+
+        let mut before = self.data.drain(..end).collect::<Vec<_>>();
+        before.truncate(start);
+        Some(before)
+    }
+
+    /// The end of the output, to show what we got instead of what we expected.
+    pub fn tail(&self, lines: usize) -> String {
+        let text = String::from_utf8_lossy(&self.data);
+        let text = text.trim_end();
+        let start = text
+            .rmatch_indices('\n')
+            .nth(lines - 1)
+            .map(|(i, _)| i + 1)
+            .unwrap_or(0);
+        text[start..].replace('\r', "")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let script = Script::parse(
+            r#"# Log in.
+timeout 2m
+expect login:
+send root
+
+write \x03\e[A
+capture [#$] $
+sleep 500ms
+"#,
+        )
+        .unwrap();
+
+        let commands = script
+            .commands
+            .iter()
+            .map(|(line, command)| match command {
+                Command::Send(data) => format!("{} send {:?}", line, String::from_utf8_lossy(data)),
+                Command::Expect {
+                    regex,
+                    timeout,
+                    capture,
+                } => format!("{} expect {} {:?} {}", line, regex, timeout, capture),
+                Command::Sleep(duration) => format!("{} sleep {:?}", line, duration),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            commands,
+            vec![
+                "3 expect login: 120s false",
+                "4 send \"root\\r\"",
+                "6 send \"\\u{3}\\u{1b}[A\"",
+                "7 expect [#$] $ 120s true",
+                "8 sleep 500ms",
+            ]
+        );
+
+        for (script, err) in [
+            ("expect", "line 1: `expect` needs a regular expression"),
+            ("\nsend \\q", "line 2: unknown escape `\\q`"),
+            (
+                "write \\x4",
+                "line 1: invalid escape `\\x4`, expected two hexadecimal digits",
+            ),
+            (
+                "timeout soon",
+                "line 1: invalid duration `soon`, expected something like `30s`, `5m` or `1h30m`",
+            ),
+            (
+                "wait 5s",
+                "line 1: unknown command `wait`, expected one of `expect`, `capture`, `send`, `write`, `sleep` or \
+                 `timeout`",
+            ),
+        ] {
+            assert_eq!(Script::parse(script).unwrap_err().to_string(), err);
+        }
+    }
+
+    #[test]
+    fn test_pending() {
+        let mut pending = Pending::default();
+        let prompt = Regex::new(r"[#$] $").unwrap();
+
+        pending.push(b"uname -s\r\n\x1b[1mLin");
+        assert_eq!(pending.find(&prompt), None);
+
+        pending.push(b"ux\x1b[0m\r\nroot@web-1:~# ");
+        assert_eq!(
+            pending.find(&prompt),
+            Some(b"uname -s\r\nLinux\r\nroot@web-1:~".to_vec())
+        );
+        assert_eq!(pending.find(&prompt), None);
+
+        pending.push(b"one\r\ntwo\r\nthree\r\n");
+        assert_eq!(pending.tail(2), "two\nthree");
+        assert_eq!(pending.tail(5), "one\ntwo\nthree");
+    }
+}
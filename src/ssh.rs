@@ -0,0 +1,507 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+use ssh_key::{
+    private::{Ed25519Keypair, KeypairData},
+    rand_core::OsRng,
+    LineEnding, PrivateKey,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    process::Command,
+};
+
+use crate::progress::ProgressBar;
+
+/// An ed25519 key registered with the Oxide account of the user only for the
+/// duration of a command, e.g. `oxide instance ssh`.
+///
+/// The private key is written to a file that is removed when the key is dropped.
+/// The public key has to be removed from the account explicitly with `delete`.
+pub struct TemporaryKey {
+    name: String,
+    path: PathBuf,
+}
+
+impl TemporaryKey {
+    /// Generate a key and add it to the Oxide account of the user.
+    pub async fn create(client: &oxide_api::Client) -> Result<Self> {
+        let name = format!("oxide-cli-{}", uuid::Uuid::new_v4().simple());
+        let private_key = PrivateKey::new(
+            KeypairData::Ed25519(Ed25519Keypair::random(&mut OsRng)),
+            "temporary key generated by the oxide cli",
+        )?;
+
+        let key = TemporaryKey {
+            path: std::env::temp_dir().join(&name),
+            name,
+        };
+        private_key.write_openssh_file(&key.path, LineEnding::default())?;
+
+        // ssh refuses keys that other users can read.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&key.path, std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        client
+            .sshkeys()
+            .post(&oxide_api::types::SshKeyCreate {
+                name: key.name.clone(),
+                description: "Temporary key generated by the oxide cli, it can safely be deleted.".to_string(),
+                public_key: private_key.public_key().to_string(),
+            })
+            .await?;
+
+        Ok(key)
+    }
+
+    /// The file holding the private key.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove the key from the Oxide account of the user, and its private key from disk.
+    /// The error says how to remove the key by hand.
+    pub async fn delete(self, client: &oxide_api::Client) -> Result<()> {
+        client.sshkeys().delete_key(&self.name).await.map_err(|err| {
+            anyhow!(
+                "could not remove the temporary ssh key {} from your user account, remove it with `oxide \
+                 ssh-key delete {}`: {}",
+                self.name,
+                self.name,
+                err
+            )
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for TemporaryKey {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Returns the external IP address of an instance.
+pub async fn external_ip(
+    client: &oxide_api::Client,
+    organization: &str,
+    project: &str,
+    instance: &str,
+) -> Result<String> {
+    let ips = client
+        .instances()
+        .external_ip_list(instance, organization, project)
+        .await?;
+
+    match ips.items.first() {
+        Some(ip) => Ok(ip.ip.to_string()),
+        None => Err(anyhow!("instance {} does not have an external IP address", instance)),
+    }
+}
+
+/// The arguments to pass to **ssh(1)**, or **scp(1)**, to authenticate with `key`,
+/// followed by the extra `flags` given by the user, e.g. `-L 80:localhost:80`.
+pub fn args(key: &TemporaryKey, flags: &[String]) -> Result<Vec<String>> {
+    let mut args = vec![
+        "-i".to_string(),
+        key.path().display().to_string(),
+        // Do not try every key loaded in the agent first, the server might give up
+        // before we get to ours.
+        "-o".to_string(),
+        "IdentitiesOnly=yes".to_string(),
+    ];
+
+    for flag in flags {
+        match shlex::split(flag) {
+            Some(mut split) => args.append(&mut split),
+            None => return Err(anyhow!("invalid ssh flag `{}`", flag)),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Run a command until it exits, and return its exit code.
+///
+/// Ctrl-C is delivered to the command, which is then given a chance to exit
+/// instead of killing us, so that the caller can always clean up afterwards.
+pub async fn run(command: &mut Command) -> Result<i32> {
+    let mut child = command.spawn()?;
+
+    let status = tokio::select! {
+        status = child.wait() => status?,
+        _ = tokio::signal::ctrl_c() => child.wait().await?,
+    };
+
+    // Exit codes are not set when the command was killed by a signal.
+    Ok(status.code().unwrap_or(1))
+}
+
+/// One side of `oxide instance cp`, a local path or a path on an instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Local(PathBuf),
+    Instance { instance: String, path: String },
+}
+
+impl Location {
+    /// Like with **scp(1)**, paths on an instance are written `<instance>:<path>`.
+    /// Local paths with a colon in their name can be written `./<path>`.
+    pub fn parse(location: &str) -> Self {
+        match location.split_once(':') {
+            Some((instance, path)) if !instance.is_empty() && !instance.contains('/') => Location::Instance {
+                instance: instance.to_string(),
+                // Like with scp, an empty path is the home directory of the user.
+                path: if path.is_empty() {
+                    ".".to_string()
+                } else {
+                    path.to_string()
+                },
+            },
+            _ => Location::Local(PathBuf::from(location)),
+        }
+    }
+
+    pub fn instance(&self) -> Option<&str> {
+        match self {
+            Location::Local(_) => None,
+            Location::Instance { instance, .. } => Some(instance),
+        }
+    }
+}
+
+/// Copy `from` to `to`, one of them being on the instance `ssh` connects to.
+///
+/// `ssh` is the **ssh(1)** command, up to and including the destination, we add the
+/// command to run on the instance. Files are streamed through `cat`, and directories
+/// through `tar` into the `to` directory, which is created if it does not exist.
+pub async fn copy(
+    io: &crate::iostreams::IoStreams,
+    ssh: Command,
+    from: &Location,
+    to: &Location,
+    recursive: bool,
+) -> Result<()> {
+    match (from, to) {
+        (Location::Local(from), Location::Instance { path, .. }) => upload(io, ssh, from, path, recursive).await,
+        (Location::Instance { path, .. }, Location::Local(to)) => download(io, ssh, path, to, recursive).await,
+        _ => Err(anyhow!("one of the paths must be local and the other on an instance")),
+    }
+}
+
+async fn upload(
+    io: &crate::iostreams::IoStreams,
+    mut ssh: Command,
+    from: &Path,
+    to: &str,
+    recursive: bool,
+) -> Result<()> {
+    // So that `.` has a name.
+    let from = &std::fs::canonicalize(from)?;
+    let name = match from.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => return Err(anyhow!("cannot copy {}, give it a name", from.display())),
+    };
+    let to = shlex::quote(to);
+
+    ssh.stdin(Stdio::piped()).kill_on_drop(true);
+
+    if recursive {
+        let mut tar = Command::new("tar");
+        tar.arg("-cf")
+            .arg("-")
+            .arg("-C")
+            .arg(match from.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            })
+            .arg(&name)
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+        ssh.arg(format!("mkdir -p {to} && tar -xf - -C {to}", to = to));
+
+        let mut tar = tar.spawn()?;
+        let mut ssh = ssh.spawn()?;
+
+        let mut progress = io.start_progress_bar(&format!("Copying {}", name), Some(disk_usage(from)?));
+        let copied = pump(tar.stdout.take().unwrap(), ssh.stdin.take().unwrap(), &mut progress).await;
+        drop(progress);
+
+        // Whichever side failed, the other one noticed the pipe closing.
+        let (ssh_status, tar_status) = (ssh.wait().await?, tar.wait().await?);
+        exited("ssh", ssh_status)?;
+        exited("tar", tar_status)?;
+        copied
+    } else {
+        let file = tokio::fs::File::open(from).await?;
+        let size = file.metadata().await?.len();
+        ssh.arg(format!(
+            "if [ -d {to} ]; then cat > {to}/{name}; else cat > {to}; fi",
+            to = to,
+            name = shlex::quote(&name)
+        ));
+
+        let mut ssh = ssh.spawn()?;
+
+        let mut progress = io.start_progress_bar(&format!("Copying {}", name), Some(size));
+        let copied = pump(file, ssh.stdin.take().unwrap(), &mut progress).await;
+        drop(progress);
+
+        exited("ssh", ssh.wait().await?)?;
+        copied
+    }
+}
+
+async fn download(
+    io: &crate::iostreams::IoStreams,
+    mut ssh: Command,
+    from: &str,
+    to: &Path,
+    recursive: bool,
+) -> Result<()> {
+    let name = match Path::new(from).file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => from.to_string(),
+    };
+    let path = shlex::quote(from);
+
+    ssh.stdin(Stdio::null()).stdout(Stdio::piped()).kill_on_drop(true);
+
+    if recursive {
+        tokio::fs::create_dir_all(to).await?;
+
+        let mut tar = Command::new("tar");
+        tar.arg("-xf")
+            .arg("-")
+            .arg("-C")
+            .arg(to)
+            .stdin(Stdio::piped())
+            .kill_on_drop(true);
+        ssh.arg(format!(
+            "tar -cf - -C \"$(dirname -- {path})\" \"$(basename -- {path})\"",
+            path = path
+        ));
+
+        let mut ssh = ssh.spawn()?;
+        let mut tar = tar.spawn()?;
+
+        // We do not know how much there is to copy without walking the directory
+        // on the instance first, show what was copied so far.
+        let mut progress = io.start_progress_bar(&format!("Copying {}", name), None);
+        let copied = pump(ssh.stdout.take().unwrap(), tar.stdin.take().unwrap(), &mut progress).await;
+        drop(progress);
+
+        let (ssh_status, tar_status) = (ssh.wait().await?, tar.wait().await?);
+        exited("ssh", ssh_status)?;
+        exited("tar", tar_status)?;
+        copied
+    } else {
+        // Send the size of the file first, for the progress bar.
+        ssh.arg(format!("wc -c < {path} && cat {path}", path = path));
+
+        let mut ssh = ssh.spawn()?;
+        let mut stdout = tokio::io::BufReader::new(ssh.stdout.take().unwrap());
+
+        let mut size = String::new();
+        stdout.read_line(&mut size).await?;
+        let size = match size.trim().parse::<u64>() {
+            Ok(size) => size,
+            Err(_) => {
+                // The file could not be read, ssh relayed why.
+                drop(stdout);
+                exited("ssh", ssh.wait().await?)?;
+                return Err(anyhow!("could not read the size of {}", from));
+            }
+        };
+
+        let to = if to.is_dir() { to.join(&name) } else { to.to_path_buf() };
+        let file = tokio::fs::File::create(to).await?;
+
+        let mut progress = io.start_progress_bar(&format!("Copying {}", name), Some(size));
+        let copied = pump(stdout, file, &mut progress).await;
+        drop(progress);
+
+        exited("ssh", ssh.wait().await?)?;
+        copied
+    }
+}
+
+/// Copy everything from `reader` to `writer`, then close `writer`.
+async fn pump(
+    mut reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    progress: &mut Option<ProgressBar>,
+) -> Result<()> {
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+
+        writer.write_all(&buf[..n]).await?;
+        if let Some(progress) = progress {
+            progress.inc(n as u64);
+        }
+    }
+
+    writer.shutdown().await?;
+    Ok(())
+}
+
+fn exited(command: &str, status: ExitStatus) -> Result<()> {
+    if status.success() {
+        return Ok(());
+    }
+
+    Err(anyhow!("{} exited with status {}", command, status.code().unwrap_or(1)))
+}
+
+/// The size of the files under `path`.
+fn disk_usage(path: &Path) -> Result<u64> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        size += disk_usage(&entry?.path())?;
+    }
+
+    Ok(size)
+}
+
+/// A `Host` entry of an **ssh_config(5)** file.
+pub fn config_entry(host: &str, hostname: &str, user: &str, identity_file: &str) -> String {
+    format!(
+        "Host {}\n    HostName {}\n    User {}\n    IdentityFile {}\n    IdentitiesOnly yes\n",
+        host, hostname, user, identity_file
+    )
+}
+
+/// Replace the lines between the `# BEGIN <marker>` and `# END <marker>` lines of
+/// `contents` with `block`, or append them to `contents` if it has no such lines yet.
+pub fn update_block(contents: &str, marker: &str, block: &str) -> String {
+    let begin = format!("# BEGIN {}", marker);
+    let end = format!("# END {}", marker);
+    let managed = format!("{}\n{}{}\n", begin, block, end);
+
+    let lines = contents.split_inclusive('\n').collect::<Vec<_>>();
+    if let Some(first) = lines.iter().position(|line| line.trim_end() == begin) {
+        if let Some(last) = lines[first..].iter().position(|line| line.trim_end() == end) {
+            return format!(
+                "{}{}{}",
+                lines[..first].concat(),
+                managed,
+                lines[first + last + 1..].concat()
+            );
+        }
+    }
+
+    match contents {
+        "" => managed,
+        contents if contents.ends_with('\n') => format!("{}\n{}", contents, managed),
+        contents => format!("{}\n\n{}", contents, managed),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_ssh_args() {
+        let key = TemporaryKey {
+            name: "oxide-cli-test".to_string(),
+            path: PathBuf::from("/tmp/oxide-cli-test"),
+        };
+
+        assert_eq!(
+            args(
+                &key,
+                &["-L 80:localhost:80".to_string(), "-o 'SendEnv=A B'".to_string()]
+            )
+            .unwrap(),
+            vec![
+                "-i",
+                "/tmp/oxide-cli-test",
+                "-o",
+                "IdentitiesOnly=yes",
+                "-L",
+                "80:localhost:80",
+                "-o",
+                "SendEnv=A B"
+            ]
+        );
+
+        assert_eq!(
+            args(&key, &["-o 'unterminated".to_string()]).unwrap_err().to_string(),
+            "invalid ssh flag `-o 'unterminated`"
+        );
+    }
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            Location::parse("web-1:/etc/app.toml"),
+            Location::Instance {
+                instance: "web-1".to_string(),
+                path: "/etc/app.toml".to_string()
+            }
+        );
+        assert_eq!(
+            Location::parse("web-1:"),
+            Location::Instance {
+                instance: "web-1".to_string(),
+                path: ".".to_string()
+            }
+        );
+        assert_eq!(Location::parse("app.toml"), Location::Local(PathBuf::from("app.toml")));
+        assert_eq!(Location::parse("./a:b"), Location::Local(PathBuf::from("./a:b")));
+        assert_eq!(Location::parse(":b"), Location::Local(PathBuf::from(":b")));
+    }
+
+    #[test]
+    fn test_update_block() {
+        let block = config_entry("web-1", "203.0.113.5", "alice", "~/.ssh/id_ed25519");
+        assert_eq!(
+            block,
+            "Host web-1\n    HostName 203.0.113.5\n    User alice\n    IdentityFile ~/.ssh/id_ed25519\n    IdentitiesOnly yes\n"
+        );
+
+        let managed = format!("# BEGIN oxide maze-war/prod\n{}# END oxide maze-war/prod\n", block);
+
+        assert_eq!(update_block("", "oxide maze-war/prod", &block), managed);
+        assert_eq!(
+            update_block("Host *\n    ForwardAgent no", "oxide maze-war/prod", &block),
+            format!("Host *\n    ForwardAgent no\n\n{}", managed)
+        );
+
+        // Updating again only replaces the block.
+        let contents = format!("Include other\n\n{}\nHost github.com\n", managed);
+        let moved = config_entry("web-1", "203.0.113.6", "alice", "~/.ssh/id_ed25519");
+        assert_eq!(
+            update_block(&contents, "oxide maze-war/prod", &moved),
+            format!(
+                "Include other\n\n# BEGIN oxide maze-war/prod\n{}# END oxide maze-war/prod\n\nHost github.com\n",
+                moved
+            )
+        );
+        assert_eq!(update_block(&contents, "oxide maze-war/prod", &block), contents);
+
+        // Blocks for other projects are left alone.
+        assert_eq!(
+            update_block(&contents, "oxide maze-war/dev", ""),
+            format!("{}\n# BEGIN oxide maze-war/dev\n# END oxide maze-war/dev\n", contents)
+        );
+    }
+}
@@ -0,0 +1,562 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+
+/// One step of tearing down a project. Steps are run in the order of the
+/// variants, so that nothing is deleted while something else still depends on it.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    StopInstance {
+        project: String,
+        instance: String,
+    },
+    DetachDisk {
+        project: String,
+        instance: String,
+        disk: String,
+    },
+    DeleteInstance {
+        project: String,
+        instance: String,
+    },
+    DeleteDisk {
+        project: String,
+        disk: String,
+    },
+    DeleteSnapshot {
+        project: String,
+        snapshot: String,
+    },
+    DeleteImage {
+        project: String,
+        image: String,
+    },
+    DeleteRoute {
+        project: String,
+        vpc: String,
+        router: String,
+        route: String,
+    },
+    DeleteRouter {
+        project: String,
+        vpc: String,
+        router: String,
+    },
+    DeleteSubnet {
+        project: String,
+        vpc: String,
+        subnet: String,
+    },
+    DeleteVpc {
+        project: String,
+        vpc: String,
+    },
+    DeleteProject {
+        project: String,
+    },
+    DeleteOrganization,
+}
+
+/// Everything in a project that has to be deleted before the project itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Contents {
+    instances: Vec<InstanceContents>,
+    disks: Vec<String>,
+    snapshots: Vec<String>,
+    images: Vec<String>,
+    vpcs: Vec<VpcContents>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct InstanceContents {
+    name: String,
+    stopped: bool,
+    disks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct VpcContents {
+    name: String,
+    subnets: Vec<String>,
+    /// The routers with their custom routes, and whether the router is a custom one.
+    /// The system router of a VPC is deleted along with the VPC.
+    routers: Vec<(String, bool, Vec<String>)>,
+}
+
+/// Delete the projects of an organization, along with everything in them.
+pub async fn delete_projects(
+    ctx: &mut crate::context::Context<'_>,
+    client: &oxide_api::Client,
+    organization: &str,
+    projects: &[String],
+    confirm: bool,
+) -> Result<()> {
+    let mut steps = Vec::new();
+    for project in projects {
+        steps.append(&mut project_steps(
+            project,
+            &project_contents(client, organization, project).await?,
+        ));
+    }
+
+    let plan = vec![(organization.to_string(), steps)];
+    tear_down(ctx, client, "projects", projects, &plan, confirm).await
+}
+
+/// Delete organizations, along with their projects and everything in them.
+pub async fn delete_organizations(
+    ctx: &mut crate::context::Context<'_>,
+    client: &oxide_api::Client,
+    organizations: &[String],
+    confirm: bool,
+) -> Result<()> {
+    let mut plan = Vec::new();
+    for organization in organizations {
+        let mut steps = Vec::new();
+        for project in client.projects().get_all(organization, Default::default()).await? {
+            let project = project.name.to_string();
+            steps.append(&mut project_steps(
+                &project,
+                &project_contents(client, organization, &project).await?,
+            ));
+        }
+        steps.push(Step::DeleteOrganization);
+
+        plan.push((organization.to_string(), steps));
+    }
+
+    tear_down(ctx, client, "organizations", organizations, &plan, confirm).await
+}
+
+/// Print the plan, confirm it, and run its steps one at a time, stopping at the
+/// first one that fails.
+async fn tear_down(
+    ctx: &mut crate::context::Context<'_>,
+    client: &oxide_api::Client,
+    kind: &str,
+    names: &[String],
+    plan: &[(String, Vec<Step>)],
+    confirm: bool,
+) -> Result<()> {
+    let total: usize = plan.iter().map(|(_, steps)| steps.len()).sum();
+
+    writeln!(ctx.io.out, "This will run the following {} steps, in order:", total)?;
+    let mut n = 0;
+    for (organization, steps) in plan {
+        for step in steps {
+            n += 1;
+            writeln!(ctx.io.out, "  {:>3}. {}", n, step.describe(organization))?;
+        }
+    }
+
+    if !confirm {
+        crate::bulk::confirm(ctx, "the recursive deletion", kind, names)?;
+    }
+
+    let opts = crate::wait::WaitOptions::default();
+    let mut n = 0;
+    for (organization, steps) in plan {
+        for step in steps {
+            n += 1;
+            let what = step.describe(organization);
+
+            let handle = ctx
+                .io
+                .start_process_indicator_with_label(&format!(" [{}/{}] {}", n, total, what));
+            let result = step.run(client, organization, &opts).await;
+            if let Some(handle) = handle {
+                if result.is_ok() {
+                    handle.done();
+                } else {
+                    handle.error();
+                }
+            }
+
+            if let Err(err) = result {
+                return Err(anyhow!(
+                    "{} failed, stopping after {} of {} steps: {}",
+                    what,
+                    n - 1,
+                    total,
+                    err
+                ));
+            }
+
+            let cs = ctx.io.color_scheme();
+            writeln!(
+                ctx.io.out,
+                "{} [{}/{}] {}",
+                cs.success_icon_with_color(ansi_term::Color::Red),
+                n,
+                total,
+                what
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// List everything in a project that has to be deleted before the project.
+async fn project_contents(client: &oxide_api::Client, organization: &str, project: &str) -> Result<Contents> {
+    let mut contents = Contents::default();
+
+    for instance in client
+        .instances()
+        .get_all(organization, project, Default::default())
+        .await?
+    {
+        let disks = client
+            .instances()
+            .disks_get_all(
+                &instance.name,
+                organization,
+                project,
+                oxide_api::types::NameSortMode::NameAscending,
+            )
+            .await?;
+
+        contents.instances.push(InstanceContents {
+            name: instance.name.to_string(),
+            stopped: instance.run_state == oxide_api::types::InstanceState::Stopped,
+            disks: disks.into_iter().map(|disk| disk.name.to_string()).collect(),
+        });
+    }
+
+    contents.disks = client
+        .disks()
+        .get_all(organization, project, Default::default())
+        .await?
+        .into_iter()
+        .map(|disk| disk.name.to_string())
+        .collect();
+    contents.snapshots = client
+        .snapshots()
+        .get_all(organization, project, Default::default())
+        .await?
+        .into_iter()
+        .map(|snapshot| snapshot.name.to_string())
+        .collect();
+    contents.images = client
+        .images()
+        .get_all(organization, project, Default::default())
+        .await?
+        .into_iter()
+        .map(|image| image.name.to_string())
+        .collect();
+
+    for vpc in client.vpcs().get_all(organization, project, Default::default()).await? {
+        let subnets = client
+            .subnets()
+            .get_all(organization, project, Default::default(), &vpc.name)
+            .await?;
+
+        let mut routers = Vec::new();
+        for router in client
+            .routers()
+            .get_all(organization, project, Default::default(), &vpc.name)
+            .await?
+        {
+            let routes = client
+                .routes()
+                .get_all(organization, project, &router.name, Default::default(), &vpc.name)
+                .await?;
+
+            routers.push((
+                router.name.to_string(),
+                router.kind == oxide_api::types::VpcRouterKind::Custom,
+                routes
+                    .into_iter()
+                    .filter(|route| route.kind == oxide_api::types::RouterRouteKind::Custom)
+                    .map(|route| route.name.to_string())
+                    .collect(),
+            ));
+        }
+
+        contents.vpcs.push(VpcContents {
+            name: vpc.name.to_string(),
+            subnets: subnets.into_iter().map(|subnet| subnet.name.to_string()).collect(),
+            routers,
+        });
+    }
+
+    Ok(contents)
+}
+
+/// The steps to delete a project with everything in it, in dependency order.
+fn project_steps(project: &str, contents: &Contents) -> Vec<Step> {
+    let project = project.to_string();
+    let mut steps = Vec::new();
+
+    for instance in contents.instances.iter().filter(|instance| !instance.stopped) {
+        steps.push(Step::StopInstance {
+            project: project.to_string(),
+            instance: instance.name.to_string(),
+        });
+    }
+    for instance in &contents.instances {
+        for disk in &instance.disks {
+            steps.push(Step::DetachDisk {
+                project: project.to_string(),
+                instance: instance.name.to_string(),
+                disk: disk.to_string(),
+            });
+        }
+    }
+    for instance in &contents.instances {
+        steps.push(Step::DeleteInstance {
+            project: project.to_string(),
+            instance: instance.name.to_string(),
+        });
+    }
+    for disk in &contents.disks {
+        steps.push(Step::DeleteDisk {
+            project: project.to_string(),
+            disk: disk.to_string(),
+        });
+    }
+    for snapshot in &contents.snapshots {
+        steps.push(Step::DeleteSnapshot {
+            project: project.to_string(),
+            snapshot: snapshot.to_string(),
+        });
+    }
+    for image in &contents.images {
+        steps.push(Step::DeleteImage {
+            project: project.to_string(),
+            image: image.to_string(),
+        });
+    }
+
+    for vpc in &contents.vpcs {
+        for (router, _, routes) in &vpc.routers {
+            for route in routes {
+                steps.push(Step::DeleteRoute {
+                    project: project.to_string(),
+                    vpc: vpc.name.to_string(),
+                    router: router.to_string(),
+                    route: route.to_string(),
+                });
+            }
+        }
+        for (router, custom, _) in &vpc.routers {
+            if *custom {
+                steps.push(Step::DeleteRouter {
+                    project: project.to_string(),
+                    vpc: vpc.name.to_string(),
+                    router: router.to_string(),
+                });
+            }
+        }
+        for subnet in &vpc.subnets {
+            steps.push(Step::DeleteSubnet {
+                project: project.to_string(),
+                vpc: vpc.name.to_string(),
+                subnet: subnet.to_string(),
+            });
+        }
+        steps.push(Step::DeleteVpc {
+            project: project.to_string(),
+            vpc: vpc.name.to_string(),
+        });
+    }
+
+    steps.push(Step::DeleteProject { project });
+
+    steps
+}
+
+impl Step {
+    /// Describe the step, for the plan and the progress output.
+    fn describe(&self, organization: &str) -> String {
+        match self {
+            Step::StopInstance { project, instance } => {
+                format!("Stop instance {} in {}/{}", instance, organization, project)
+            }
+            Step::DetachDisk {
+                project,
+                instance,
+                disk,
+            } => format!(
+                "Detach disk {} from instance {} in {}/{}",
+                disk, instance, organization, project
+            ),
+            Step::DeleteInstance { project, instance } => {
+                format!("Delete instance {} in {}/{}", instance, organization, project)
+            }
+            Step::DeleteDisk { project, disk } => format!("Delete disk {} in {}/{}", disk, organization, project),
+            Step::DeleteSnapshot { project, snapshot } => {
+                format!("Delete snapshot {} in {}/{}", snapshot, organization, project)
+            }
+            Step::DeleteImage { project, image } => format!("Delete image {} in {}/{}", image, organization, project),
+            Step::DeleteRoute {
+                project,
+                vpc,
+                router,
+                route,
+            } => format!(
+                "Delete route {} of router {} in VPC {} in {}/{}",
+                route, router, vpc, organization, project
+            ),
+            Step::DeleteRouter { project, vpc, router } => format!(
+                "Delete router {} in VPC {} in {}/{}",
+                router, vpc, organization, project
+            ),
+            Step::DeleteSubnet { project, vpc, subnet } => format!(
+                "Delete subnet {} in VPC {} in {}/{}",
+                subnet, vpc, organization, project
+            ),
+            Step::DeleteVpc { project, vpc } => format!("Delete VPC {} in {}/{}", vpc, organization, project),
+            Step::DeleteProject { project } => format!("Delete project {}/{}", organization, project),
+            Step::DeleteOrganization => format!("Delete organization {}", organization),
+        }
+    }
+
+    /// Run the step, waiting for instances to stop and disks to be detached.
+    async fn run(&self, client: &oxide_api::Client, organization: &str, opts: &crate::wait::WaitOptions) -> Result<()> {
+        match self {
+            Step::StopInstance { project, instance } => {
+                client.instances().stop(instance, organization, project).await?;
+
+                crate::cmd_instance::InstanceDetails {
+                    host: "".to_string(),
+                    organization: organization.to_string(),
+                    project: project.to_string(),
+                    instance: instance.to_string(),
+                }
+                .poll_state(client, &oxide_api::types::InstanceState::Stopped, opts, |_| {})
+                .await?;
+            }
+            Step::DetachDisk {
+                project,
+                instance,
+                disk,
+            } => {
+                client
+                    .instances()
+                    .disks_detach(
+                        instance,
+                        organization,
+                        project,
+                        &oxide_api::types::DiskIdentifier { name: disk.to_string() },
+                    )
+                    .await?;
+
+                crate::wait::poll(
+                    opts,
+                    &format!("disk {} to be `detached`", disk),
+                    || async move {
+                        let state =
+                            crate::cmd_wait::disk_state(&client.disks().get(disk, organization, project).await?.state)?;
+
+                        Ok(if state == "detached" {
+                            crate::wait::Poll::Done
+                        } else {
+                            crate::wait::Poll::Pending(state)
+                        })
+                    },
+                    |_| {},
+                )
+                .await?;
+            }
+            Step::DeleteInstance { project, instance } => {
+                client.instances().delete(instance, organization, project).await?;
+            }
+            Step::DeleteDisk { project, disk } => {
+                client.disks().delete(disk, organization, project).await?;
+            }
+            Step::DeleteSnapshot { project, snapshot } => {
+                client.snapshots().delete(organization, project, snapshot).await?;
+            }
+            Step::DeleteImage { project, image } => {
+                client.images().delete(image, organization, project).await?;
+            }
+            Step::DeleteRoute {
+                project,
+                vpc,
+                router,
+                route,
+            } => {
+                client
+                    .routes()
+                    .delete(organization, project, route, router, vpc)
+                    .await?;
+            }
+            Step::DeleteRouter { project, vpc, router } => {
+                client.routers().delete(organization, project, router, vpc).await?;
+            }
+            Step::DeleteSubnet { project, vpc, subnet } => {
+                client.subnets().delete(organization, project, subnet, vpc).await?;
+            }
+            Step::DeleteVpc { project, vpc } => {
+                client.vpcs().delete(organization, project, vpc).await?;
+            }
+            Step::DeleteProject { project } => {
+                client.projects().delete(organization, project).await?;
+            }
+            Step::DeleteOrganization => {
+                client.organizations().delete(organization).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_project_steps() {
+        let contents = Contents {
+            instances: vec![
+                InstanceContents {
+                    name: "web".to_string(),
+                    stopped: false,
+                    disks: vec!["web-boot".to_string()],
+                },
+                InstanceContents {
+                    name: "db".to_string(),
+                    stopped: true,
+                    disks: vec![],
+                },
+            ],
+            disks: vec!["web-boot".to_string()],
+            snapshots: vec!["web-boot-1".to_string()],
+            images: vec![],
+            vpcs: vec![VpcContents {
+                name: "default".to_string(),
+                subnets: vec!["default".to_string()],
+                routers: vec![
+                    ("system".to_string(), false, vec!["gateway".to_string()]),
+                    ("edge".to_string(), true, vec![]),
+                ],
+            }],
+        };
+
+        let steps = project_steps("prod", &contents)
+            .iter()
+            .map(|step| step.describe("acme"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            steps,
+            vec![
+                "Stop instance web in acme/prod",
+                "Detach disk web-boot from instance web in acme/prod",
+                "Delete instance web in acme/prod",
+                "Delete instance db in acme/prod",
+                "Delete disk web-boot in acme/prod",
+                "Delete snapshot web-boot-1 in acme/prod",
+                "Delete route gateway of router system in VPC default in acme/prod",
+                "Delete router edge in VPC default in acme/prod",
+                "Delete subnet default in VPC default in acme/prod",
+                "Delete VPC default in acme/prod",
+                "Delete project acme/prod",
+            ]
+        );
+    }
+}
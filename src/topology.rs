@@ -0,0 +1,433 @@
+use std::collections::BTreeSet;
+
+use parse_display::{Display, FromStr};
+
+/// The formats of `oxide vpc describe`.
+#[derive(Debug, Clone, PartialEq, Eq, FromStr, Display)]
+#[display(style = "kebab-case")]
+pub enum TopologyFormat {
+    /// A tree of everything in the VPC.
+    Tree,
+    /// A Graphviz diagram of the subnets, instances and route targets.
+    Dot,
+    /// A Mermaid flowchart of the subnets, instances and route targets.
+    Mermaid,
+}
+
+/// Everything in a VPC, with the enums of the API already turned into text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Topology {
+    pub vpc: String,
+    pub ipv6_prefix: String,
+    pub dns_name: String,
+    pub subnets: Vec<Subnet>,
+    pub routers: Vec<Router>,
+    pub firewall_rules: Vec<FirewallRule>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subnet {
+    pub name: String,
+    pub ipv4_block: String,
+    pub ipv6_block: String,
+    pub interfaces: Vec<Interface>,
+}
+
+/// A network interface in a subnet, and the instance it belongs to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    pub instance: String,
+    pub ip: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Router {
+    pub name: String,
+    pub kind: String,
+    pub routes: Vec<Route>,
+}
+
+/// A route, its destination and target are `<type>:<value>`, e.g. `subnet:default`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Route {
+    pub name: String,
+    pub destination: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FirewallRule {
+    pub name: String,
+    pub direction: String,
+    pub action: String,
+    pub priority: String,
+    pub status: String,
+    /// The targets, as `<type>:<value>`, and the filters, e.g. `ports 22`.
+    pub targets: Vec<String>,
+    pub filters: Vec<String>,
+}
+
+/// Turn a value of one of the tagged enums of the API, e.g. a `RouteTarget`, into
+/// `<type>:<value>`.
+pub fn tagged<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::Object(object)) => match (object.get("type"), object.get("value")) {
+            (Some(serde_json::Value::String(kind)), Some(serde_json::Value::String(value))) => {
+                format!("{}:{}", kind, value)
+            }
+            (Some(serde_json::Value::String(kind)), Some(value)) => format!("{}:{}", kind, value),
+            _ => serde_json::Value::Object(object).to_string(),
+        },
+        Ok(serde_json::Value::String(value)) => value,
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// A node of the tree view, printed with box drawing characters.
+struct Node {
+    label: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(label: impl Into<String>, children: Vec<Node>) -> Self {
+        Node {
+            label: label.into(),
+            children,
+        }
+    }
+
+    fn leaf(label: impl Into<String>) -> Self {
+        Node::new(label, Vec::new())
+    }
+
+    fn render(&self, out: &mut String, prefix: &str) {
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i == self.children.len() - 1;
+            out.push_str(&format!(
+                "{}{}{}\n",
+                prefix,
+                if last { "└── " } else { "├── " },
+                child.label
+            ));
+            child.render(out, &format!("{}{}", prefix, if last { "    " } else { "│   " }));
+        }
+    }
+}
+
+/// Print the VPC as a tree.
+pub fn to_tree(topology: &Topology) -> String {
+    let subnets = topology
+        .subnets
+        .iter()
+        .map(|subnet| {
+            Node::new(
+                format!("{}  {}  {}", subnet.name, subnet.ipv4_block, subnet.ipv6_block),
+                subnet
+                    .interfaces
+                    .iter()
+                    .map(|interface| {
+                        Node::leaf(format!("{}  {}  {}", interface.ip, interface.instance, interface.name))
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+
+    let routers = topology
+        .routers
+        .iter()
+        .map(|router| {
+            Node::new(
+                format!("{} ({})", router.name, router.kind),
+                router
+                    .routes
+                    .iter()
+                    .map(|route| Node::leaf(format!("{}  {} -> {}", route.name, route.destination, route.target)))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    let firewall_rules = topology
+        .firewall_rules
+        .iter()
+        .map(|rule| {
+            let mut label = format!(
+                "{}  {} {}, priority {}, {}",
+                rule.name, rule.direction, rule.action, rule.priority, rule.status
+            );
+            if !rule.targets.is_empty() {
+                label.push_str(&format!(", targets {}", rule.targets.join(" ")));
+            }
+            for filter in &rule.filters {
+                label.push_str(&format!(", {}", filter));
+            }
+
+            Node::leaf(label)
+        })
+        .collect();
+
+    let tree = Node::new(
+        "",
+        vec![
+            Node::new("Subnets", subnets),
+            Node::new("Routers", routers),
+            Node::new("Firewall rules", firewall_rules),
+        ],
+    );
+
+    let mut out = format!(
+        "VPC {}  {}  dns {}\n",
+        topology.vpc, topology.ipv6_prefix, topology.dns_name
+    );
+    tree.render(&mut out, "");
+    out
+}
+
+/// The shapes of the nodes of a diagram.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+    Network,
+    Instance,
+    Router,
+    Target,
+}
+
+/// The subnets, instances, routers and route targets of a VPC, as nodes and edges.
+#[derive(Debug, Default)]
+struct Graph {
+    nodes: Vec<(String, String, Shape)>,
+    edges: Vec<(String, String, String)>,
+    ids: BTreeSet<String>,
+}
+
+impl Graph {
+    fn new(topology: &Topology) -> Self {
+        let mut graph = Graph::default();
+
+        let vpc = graph.node(
+            &format!("vpc:{}", topology.vpc),
+            &format!("VPC {}\n{}", topology.vpc, topology.ipv6_prefix),
+            Shape::Network,
+        );
+        for subnet in &topology.subnets {
+            let id = graph.node(
+                &format!("subnet:{}", subnet.name),
+                &format!("{}\n{}", subnet.name, subnet.ipv4_block),
+                Shape::Network,
+            );
+            graph.edges.push((vpc.to_string(), id.to_string(), String::new()));
+
+            for interface in &subnet.interfaces {
+                let instance = graph.node(
+                    &format!("instance:{}", interface.instance),
+                    &interface.instance,
+                    Shape::Instance,
+                );
+                graph
+                    .edges
+                    .push((id.to_string(), instance, format!("{} {}", interface.name, interface.ip)));
+            }
+        }
+
+        for router in &topology.routers {
+            let id = graph.node(
+                &format!("router:{}", router.name),
+                &format!("router {}", router.name),
+                Shape::Router,
+            );
+            graph.edges.push((vpc.to_string(), id.to_string(), String::new()));
+
+            for route in &router.routes {
+                let target = graph.node(&route.target, &route.target.replacen(':', " ", 1), Shape::Target);
+                graph
+                    .edges
+                    .push((id.to_string(), target, format!("{} {}", route.name, route.destination)));
+            }
+        }
+
+        graph
+    }
+
+    /// Add a node, unless it was already added, and return its ID. The ID only
+    /// has characters that are valid in both Graphviz and Mermaid IDs.
+    fn node(&mut self, key: &str, label: &str, shape: Shape) -> String {
+        let id = key
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+
+        if self.ids.insert(id.to_string()) {
+            self.nodes.push((id.to_string(), label.to_string(), shape));
+        }
+
+        id
+    }
+}
+
+/// Print the subnets, instances and route targets of the VPC as a Graphviz diagram.
+pub fn to_dot(topology: &Topology) -> String {
+    let graph = Graph::new(topology);
+
+    let mut out = format!("digraph {:?} {{\n    rankdir=LR;\n", topology.vpc);
+    for (id, label, shape) in &graph.nodes {
+        let shape = match shape {
+            Shape::Network => "box",
+            Shape::Instance => "component",
+            Shape::Router => "diamond",
+            Shape::Target => "ellipse",
+        };
+        out.push_str(&format!("    {} [label={:?}, shape={}];\n", id, label, shape));
+    }
+    for (from, to, label) in &graph.edges {
+        if label.is_empty() {
+            out.push_str(&format!("    {} -> {};\n", from, to));
+        } else {
+            out.push_str(&format!("    {} -> {} [label={:?}];\n", from, to, label));
+        }
+    }
+    out.push_str("}\n");
+
+    out
+}
+
+/// Print the subnets, instances and route targets of the VPC as a Mermaid flowchart.
+pub fn to_mermaid(topology: &Topology) -> String {
+    let graph = Graph::new(topology);
+
+    let mut out = "flowchart LR\n".to_string();
+    for (id, label, shape) in &graph.nodes {
+        let label = label.replace('"', "#quot;").replace('\n', "<br/>");
+        let (open, close) = match shape {
+            Shape::Network => ("[", "]"),
+            Shape::Instance => ("(", ")"),
+            Shape::Router => ("{", "}"),
+            Shape::Target => ("((", "))"),
+        };
+        out.push_str(&format!("    {}{}\"{}\"{}\n", id, open, label, close));
+    }
+    for (from, to, label) in &graph.edges {
+        if label.is_empty() {
+            out.push_str(&format!("    {} --> {}\n", from, to));
+        } else {
+            out.push_str(&format!(
+                "    {} -->|\"{}\"| {}\n",
+                from,
+                label.replace('"', "#quot;"),
+                to
+            ));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn topology() -> Topology {
+        Topology {
+            vpc: "default".to_string(),
+            ipv6_prefix: "fd00:1122:3344::/48".to_string(),
+            dns_name: "default".to_string(),
+            subnets: vec![Subnet {
+                name: "default".to_string(),
+                ipv4_block: "172.30.0.0/22".to_string(),
+                ipv6_block: "fd00:1122:3344:100::/64".to_string(),
+                interfaces: vec![Interface {
+                    name: "net0".to_string(),
+                    instance: "web-1".to_string(),
+                    ip: "172.30.0.5".to_string(),
+                }],
+            }],
+            routers: vec![Router {
+                name: "system".to_string(),
+                kind: "system".to_string(),
+                routes: vec![Route {
+                    name: "default".to_string(),
+                    destination: "vpc:default".to_string(),
+                    target: "internet_gateway:outbound".to_string(),
+                }],
+            }],
+            firewall_rules: vec![FirewallRule {
+                name: "allow-ssh".to_string(),
+                direction: "inbound".to_string(),
+                action: "allow".to_string(),
+                priority: "65534".to_string(),
+                status: "enabled".to_string(),
+                targets: vec!["vpc:default".to_string()],
+                filters: vec!["ports 22".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_tagged() {
+        assert_eq!(
+            tagged(&serde_json::json!({ "type": "subnet", "value": "default" })),
+            "subnet:default"
+        );
+        assert_eq!(tagged(&serde_json::json!("inbound")), "inbound");
+    }
+
+    #[test]
+    fn test_to_tree() {
+        assert_eq!(
+            to_tree(&topology()),
+            r#"VPC default  fd00:1122:3344::/48  dns default
+├── Subnets
+│   └── default  172.30.0.0/22  fd00:1122:3344:100::/64
+│       └── 172.30.0.5  web-1  net0
+├── Routers
+│   └── system (system)
+│       └── default  vpc:default -> internet_gateway:outbound
+└── Firewall rules
+    └── allow-ssh  inbound allow, priority 65534, enabled, targets vpc:default, ports 22
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        assert_eq!(
+            to_dot(&topology()),
+            r#"digraph "default" {
+    rankdir=LR;
+    vpc_default [label="VPC default\nfd00:1122:3344::/48", shape=box];
+    subnet_default [label="default\n172.30.0.0/22", shape=box];
+    instance_web_1 [label="web-1", shape=component];
+    router_system [label="router system", shape=diamond];
+    internet_gateway_outbound [label="internet_gateway outbound", shape=ellipse];
+    vpc_default -> subnet_default;
+    subnet_default -> instance_web_1 [label="net0 172.30.0.5"];
+    vpc_default -> router_system;
+    router_system -> internet_gateway_outbound [label="default vpc:default"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        assert_eq!(
+            to_mermaid(&topology()),
+            r#"flowchart LR
+    vpc_default["VPC default<br/>fd00:1122:3344::/48"]
+    subnet_default["default<br/>172.30.0.0/22"]
+    instance_web_1("web-1")
+    router_system{"router system"}
+    internet_gateway_outbound(("internet_gateway outbound"))
+    vpc_default --> subnet_default
+    subnet_default -->|"net0 172.30.0.5"| instance_web_1
+    vpc_default --> router_system
+    router_system -->|"default vpc:default"| internet_gateway_outbound
+"#
+        );
+    }
+}
@@ -19,3 +19,28 @@ impl FormatOutput {
         vec!["table".to_string(), "json".to_string(), "yaml".to_string()]
     }
 }
+
+/// Parse a size in bytes, with an optional unit, e.g. `20GiB`, `512M` or `1.5T`.
+pub fn parse_byte_count(s: &str) -> anyhow::Result<oxide_api::types::ByteCount> {
+    let bytes = s
+        .trim()
+        .parse::<byte_unit::Byte>()
+        .map_err(|e| anyhow::anyhow!("invalid size `{}`: {}", s, e))?;
+    Ok(oxide_api::types::ByteCount::try_from(bytes.get_bytes())?)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_byte_count() {
+        assert_eq!(parse_byte_count("1073741824").unwrap(), 1073741824);
+        assert_eq!(parse_byte_count("20GiB").unwrap(), 20 * 1024 * 1024 * 1024);
+        assert_eq!(parse_byte_count("512M").unwrap(), 512_000_000);
+        assert_eq!(parse_byte_count("1.5T").unwrap(), 1_500_000_000_000);
+        assert!(parse_byte_count("lots").is_err());
+    }
+}
@@ -233,7 +233,7 @@ pub async fn download_binary_to_temp_file(version: &str) -> Result<String> {
 }
 
 /// Calculates the SHA256 hash of a reader.
-fn sha256_digest<R: std::io::Read>(mut reader: R) -> Result<String> {
+pub(crate) fn sha256_digest<R: std::io::Read>(mut reader: R) -> Result<String> {
     let mut context = ring::digest::Context::new(&ring::digest::SHA256);
     let mut buffer = [0; 1024];
 
@@ -0,0 +1,366 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Once,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+/// The default amount of time to wait for a resource to reach a state.
+pub const DEFAULT_TIMEOUT: &str = "10m";
+
+/// The default interval between the first few polls of a resource.
+pub const DEFAULT_POLL_INTERVAL: &str = "250ms";
+
+/// Backing off never makes us wait longer than this between two polls, unless the
+/// poll interval itself is longer.
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Never poll more often than this, even with a zero poll interval.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long, and how often, to poll a resource while waiting for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaitOptions {
+    /// Give up after this long. A zero timeout waits forever.
+    pub timeout: Duration,
+    /// The initial interval between polls, doubled after every poll. Anything
+    /// shorter than 100ms is rounded up to 100ms.
+    pub poll_interval: Duration,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions {
+            timeout: parse_duration(DEFAULT_TIMEOUT).unwrap(),
+            poll_interval: parse_duration(DEFAULT_POLL_INTERVAL).unwrap(),
+        }
+    }
+}
+
+/// The outcome of a single poll of a resource.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Poll {
+    /// The resource reached the state we were waiting for.
+    Done,
+    /// The resource is still in the given state, keep waiting.
+    Pending(String),
+    /// The resource can never reach the state we are waiting for, the message
+    /// explains why.
+    Failed(String),
+}
+
+/// Parse a duration like `500ms`, `30s`, `5m`, `1h30m` or `7d`. A bare number is
+/// a number of seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let invalid = || {
+        anyhow!(
+            "invalid duration `{}`, expected something like `30s`, `5m` or `1h30m`",
+            s
+        )
+    };
+
+    if s.is_empty() {
+        return Err(invalid());
+    }
+
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(invalid());
+        }
+        let value: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+
+        let unit = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let millis = match &rest[..unit] {
+            "ms" => 1,
+            "s" => 1_000,
+            "m" => 60 * 1_000,
+            "h" => 60 * 60 * 1_000,
+            "d" => 24 * 60 * 60 * 1_000,
+            "w" => 7 * 24 * 60 * 60 * 1_000,
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit..];
+
+        total += Duration::from_millis(value.checked_mul(millis).ok_or_else(invalid)?);
+    }
+
+    Ok(total)
+}
+
+/// Call `check` until it reports the resource is done, backing off exponentially
+/// between polls.
+///
+/// `what` describes what we are waiting for (e.g. "instance `web` to be `running`")
+/// and is used in error messages. `on_change` is called with the new state every
+/// time the observed state changes.
+///
+/// Hitting Ctrl-C only stops the waiting: whatever operation was already sent to the
+/// server carries on.
+pub async fn poll<F, Fut, C>(opts: &WaitOptions, what: &str, mut check: F, mut on_change: C) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Poll>>,
+    C: FnMut(&str),
+{
+    let deadline = if opts.timeout.is_zero() {
+        None
+    } else {
+        Some(tokio::time::Instant::now() + opts.timeout)
+    };
+    let mut interval = opts.poll_interval.max(MIN_POLL_INTERVAL);
+    let max_interval = MAX_BACKOFF.max(interval);
+    let mut last_state: Option<String> = None;
+
+    let interrupted = || {
+        anyhow!(
+            "stopped waiting for {}, the operation will carry on in the background",
+            what
+        )
+    };
+
+    let _waiting = Waiting::start();
+    let ctrl_c = INTERRUPTED.notified();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let state = tokio::select! {
+            _ = &mut ctrl_c => return Err(interrupted()),
+            state = check() => state?,
+        };
+
+        match state {
+            Poll::Done => return Ok(()),
+            Poll::Failed(msg) => return Err(anyhow!(msg)),
+            Poll::Pending(state) => {
+                if last_state.as_ref() != Some(&state) {
+                    on_change(&state);
+                    last_state = Some(state);
+                }
+            }
+        }
+
+        let sleep = match deadline {
+            Some(deadline) => {
+                let now = tokio::time::Instant::now();
+                if now >= deadline {
+                    return Err(anyhow!("timed out after {:?} waiting for {}", opts.timeout, what));
+                }
+
+                interval.min(deadline - now)
+            }
+            None => interval,
+        };
+
+        tokio::select! {
+            _ = &mut ctrl_c => return Err(interrupted()),
+            _ = tokio::time::sleep(sleep) => {},
+        }
+
+        // Back off a bit.
+        interval = (interval * 2).min(max_interval);
+    }
+}
+
+/// The number of `poll`s going on, which Ctrl-C stops.
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// Notified when Ctrl-C is hit while a `poll` is going on.
+static INTERRUPTED: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+static LISTEN: Once = Once::new();
+
+/// Marks a `poll` as going on until it is dropped.
+struct Waiting;
+
+impl Waiting {
+    fn start() -> Self {
+        // Once Ctrl-C is listened for, it no longer stops the process for as long as the
+        // process runs. So listen once, and outside of `poll` exit like it would have.
+        LISTEN.call_once(|| {
+            tokio::spawn(async {
+                while tokio::signal::ctrl_c().await.is_ok() {
+                    if WAITING.load(Ordering::SeqCst) == 0 {
+                        std::process::exit(130);
+                    }
+                    INTERRUPTED.notify_waiters();
+                }
+            });
+        });
+
+        WAITING.fetch_add(1, Ordering::SeqCst);
+        Waiting
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        WAITING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Like `poll`, showing a spinner with the latest state of the resource while we
+/// wait, and `done` once we are done.
+pub async fn poll_with_spinner<F, Fut>(
+    ctx: &mut crate::context::Context<'_>,
+    opts: &WaitOptions,
+    what: &str,
+    done: &str,
+    check: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Poll>>,
+{
+    // Start the progress bar.
+    let handle = ctx
+        .io
+        .start_process_indicator_with_label(&format!(" Waiting for {}", what));
+
+    let result = poll(opts, what, check, |state| {
+        if let Some(handle) = &handle {
+            handle.text(format!(" Waiting for {} [{}]", what, state));
+        }
+    })
+    .await;
+
+    // End the progress bar.
+    if let Some(handle) = handle {
+        if result.is_ok() {
+            handle.text(done.to_string());
+            handle.done();
+        } else {
+            handle.error();
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(7 * 24 * 60 * 60));
+        assert_eq!(parse_duration("0").unwrap(), Duration::ZERO);
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5y").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_poll_done() {
+        let opts = WaitOptions {
+            timeout: Duration::from_secs(5),
+            poll_interval: Duration::from_millis(1),
+        };
+
+        let mut polls = 0;
+        let mut changes = Vec::new();
+        poll(
+            &opts,
+            "thing",
+            || {
+                polls += 1;
+                let n = polls;
+                async move {
+                    Ok(match n {
+                        1 | 2 => Poll::Pending("starting".to_string()),
+                        3 => Poll::Pending("booting".to_string()),
+                        _ => Poll::Done,
+                    })
+                }
+            },
+            |state| changes.push(state.to_string()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(polls, 4);
+        assert_eq!(changes, vec!["starting".to_string(), "booting".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_poll_failed() {
+        let opts = WaitOptions {
+            timeout: Duration::from_secs(5),
+            poll_interval: Duration::from_millis(1),
+        };
+
+        let err = poll(
+            &opts,
+            "thing",
+            || async { Ok(Poll::Failed("thing is `failed`".to_string())) },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "thing is `failed`");
+    }
+
+    #[tokio::test]
+    async fn test_poll_timeout() {
+        let opts = WaitOptions {
+            timeout: Duration::from_millis(20),
+            poll_interval: Duration::from_millis(5),
+        };
+
+        let err = poll(
+            &opts,
+            "thing to be `ready`",
+            || async { Ok(Poll::Pending("creating".to_string())) },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "timed out after 20ms waiting for thing to be `ready`");
+    }
+
+    #[tokio::test]
+    async fn test_poll_zero_interval() {
+        let opts = WaitOptions {
+            timeout: Duration::from_millis(350),
+            poll_interval: Duration::ZERO,
+        };
+
+        let mut polls = 0;
+        poll(
+            &opts,
+            "thing to be `ready`",
+            || {
+                polls += 1;
+                async { Ok(Poll::Pending("creating".to_string())) }
+            },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+
+        // At most at 0ms, 100ms, 300ms and at the deadline, rather than in a busy loop.
+        assert!(polls <= 4, "polled {} times", polls);
+    }
+}
@@ -0,0 +1,373 @@
+use std::{future::Future, io::Write, time::Duration};
+
+use anyhow::Result;
+
+/// The default interval between two refreshes of a watched command.
+pub const DEFAULT_INTERVAL: &str = "2s";
+
+/// Columns are never truncated to less than this many characters to make the table
+/// fit in the terminal.
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// How a watched command lays out its results in a table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// One row per item, like the list commands.
+    Rows,
+    /// A single item with one row per field, like the view commands.
+    Record,
+}
+
+/// A change to the watched items, printed as a line of NDJSON when we are not
+/// drawing to a terminal.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Event {
+    Added { object: serde_json::Value },
+    Modified { object: serde_json::Value },
+    Deleted { object: serde_json::Value },
+}
+
+/// The items returned by a single fetch.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Snapshot {
+    keys: Vec<String>,
+    objects: Vec<serde_json::Value>,
+    fields: Vec<Vec<String>>,
+}
+
+impl Snapshot {
+    fn new<T: serde::Serialize + tabled::Tabled>(items: &[T]) -> Result<Self> {
+        let mut snapshot = Snapshot::default();
+        for (i, item) in items.iter().enumerate() {
+            let object = serde_json::to_value(item)?;
+            // Prefer the ID so that items keep their identity when the order changes.
+            let key = match object.get("id").and_then(|id| id.as_str()) {
+                Some(id) => id.to_string(),
+                None => i.to_string(),
+            };
+
+            snapshot.keys.push(key);
+            snapshot.objects.push(object);
+            snapshot.fields.push(crate::iostreams::table_fields(item));
+        }
+
+        Ok(snapshot)
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.keys.iter().position(|k| k == key)
+    }
+
+    /// The events that turn `prev` into this snapshot.
+    fn events(&self, prev: &Snapshot) -> Vec<Event> {
+        let mut events = Vec::new();
+        for (key, object) in self.keys.iter().zip(&self.objects) {
+            match prev.position(key) {
+                None => events.push(Event::Added { object: object.clone() }),
+                Some(i) if &prev.objects[i] != object => events.push(Event::Modified { object: object.clone() }),
+                Some(_) => {}
+            }
+        }
+
+        for (key, object) in prev.keys.iter().zip(&prev.objects) {
+            if self.position(key).is_none() {
+                events.push(Event::Deleted { object: object.clone() });
+            }
+        }
+
+        events
+    }
+
+    /// For every row, which cells changed since `prev`. Rows that are new are `None`.
+    fn changes(&self, prev: &Snapshot) -> Vec<Option<Vec<bool>>> {
+        self.keys
+            .iter()
+            .zip(&self.fields)
+            .map(|(key, fields)| {
+                let old = &prev.fields[prev.position(key)?];
+                Some(fields.iter().enumerate().map(|(i, f)| old.get(i) != Some(f)).collect())
+            })
+            .collect()
+    }
+
+    fn deleted(&self, prev: &Snapshot) -> usize {
+        prev.keys.iter().filter(|key| self.position(key).is_none()).count()
+    }
+}
+
+/// Re-run `fetch` every `interval` until interrupted with Ctrl-C.
+///
+/// When drawing a table to a terminal the whole table is redrawn in place, with the
+/// cells that changed since the previous refresh highlighted. Otherwise only the
+/// items that were added, modified or deleted are printed, as NDJSON events.
+pub async fn watch<T, F, Fut>(
+    ctx: &mut crate::context::Context,
+    interval: Duration,
+    layout: Layout,
+    format: &Option<crate::types::FormatOutput>,
+    mut fetch: F,
+) -> Result<()>
+where
+    T: serde::Serialize + tabled::Tabled,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let draw = ctx.io.is_stdout_tty() && ctx.format(format)? == crate::types::FormatOutput::Table;
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    let mut prev: Option<Snapshot> = None;
+    loop {
+        let items = tokio::select! {
+            _ = &mut ctrl_c => return Ok(()),
+            items = fetch() => items,
+        };
+
+        match items.and_then(|items| Snapshot::new(&items)) {
+            Ok(next) => {
+                if draw {
+                    redraw::<T>(ctx, interval, layout, &next, prev.as_ref(), None)?;
+                } else {
+                    for event in next.events(prev.as_ref().unwrap_or(&Snapshot::default())) {
+                        writeln!(ctx.io.out, "{}", serde_json::to_string(&event)?)?;
+                    }
+                    ctx.io.out.flush()?;
+                }
+
+                prev = Some(next);
+            }
+            // Keep the last table on screen, the next refresh might work.
+            Err(err) if draw => match &prev {
+                Some(last) => redraw::<T>(ctx, interval, layout, last, Some(last), Some(&err))?,
+                None => redraw::<T>(ctx, interval, layout, &Snapshot::default(), None, Some(&err))?,
+            },
+            Err(err) => return Err(err),
+        }
+
+        tokio::select! {
+            _ = &mut ctrl_c => return Ok(()),
+            _ = tokio::time::sleep(interval) => {},
+        }
+    }
+}
+
+/// Draw the snapshot over whatever we drew last.
+fn redraw<T: tabled::Tabled>(
+    ctx: &mut crate::context::Context,
+    interval: Duration,
+    layout: Layout,
+    next: &Snapshot,
+    prev: Option<&Snapshot>,
+    err: Option<&anyhow::Error>,
+) -> Result<()> {
+    let cs = ctx.io.color_scheme();
+    let width = ctx.io.terminal_width().max(0) as usize;
+
+    let mut header = format!(
+        "Every {:?}, last updated {}. Press Ctrl-C to stop.",
+        interval,
+        chrono::Local::now().format("%H:%M:%S")
+    );
+    if let Some(prev) = prev {
+        let deleted = next.deleted(prev);
+        if deleted > 0 {
+            header.push_str(&cs.red(&format!(" {} deleted.", deleted)));
+        }
+    }
+    if let Some(err) = err {
+        header.push_str(&cs.red(&format!(" Refresh failed: {}", err)));
+    }
+
+    let mut rows = next.fields.clone();
+    match layout {
+        Layout::Rows => fit_columns(&T::headers(), &mut rows, width),
+        Layout::Record => fit_record(&T::headers(), &mut rows, width),
+    }
+
+    let changes = match prev {
+        Some(prev) => next.changes(prev),
+        // Nothing to compare the first fetch with.
+        None => vec![Some(vec![]); next.keys.len()],
+    };
+    let rows = rows
+        .into_iter()
+        .zip(changes)
+        .map(|(cells, changed)| {
+            crate::iostreams::TableRow::<T>::new(
+                cells
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| match &changed {
+                        None => cs.green(cell),
+                        Some(changed) if changed.get(i) == Some(&true) => cs.yellow(cell),
+                        Some(_) => cell.to_string(),
+                    })
+                    .collect(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let table = match layout {
+        Layout::Rows => tabled::Table::new(rows).with(tabled::Style::psql()).to_string(),
+        Layout::Record => tabled::Table::new(rows)
+            .with(tabled::Rotate::Left)
+            .with(
+                tabled::Modify::new(tabled::Full)
+                    .with(tabled::Alignment::left())
+                    .with(tabled::Alignment::top()),
+            )
+            .with(tabled::Style::psql().header_off())
+            .to_string(),
+    };
+
+    // Clear the screen once, then overwrite it line by line so it does not flicker.
+    if prev.is_none() {
+        write!(ctx.io.out, "\x1b[2J")?;
+    }
+    write!(ctx.io.out, "\x1b[H")?;
+    for line in std::iter::once(header.as_str())
+        .chain(std::iter::once(""))
+        .chain(table.lines())
+    {
+        writeln!(ctx.io.out, "{}\x1b[K", line)?;
+    }
+    write!(ctx.io.out, "\x1b[J")?;
+    ctx.io.out.flush()?;
+
+    Ok(())
+}
+
+/// Truncate the widest columns of a table until it fits in `width`.
+fn fit_columns(headers: &[String], rows: &mut [Vec<String>], width: usize) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows.iter() {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+
+    // Every cell is padded with a space on both sides, and separated from the next
+    // one with a `|`.
+    let total = |widths: &[usize]| widths.iter().map(|w| w + 3).sum::<usize>().saturating_sub(1);
+    while total(&widths) > width {
+        let widest = widths
+            .iter()
+            .enumerate()
+            .filter(|(i, w)| **w > MIN_COLUMN_WIDTH.max(headers[*i].chars().count()))
+            .max_by_key(|(_, w)| **w)
+            .map(|(i, _)| i);
+
+        match widest {
+            Some(i) => widths[i] -= 1,
+            None => break,
+        }
+    }
+
+    for row in rows.iter_mut() {
+        for (cell, w) in row.iter_mut().zip(&widths) {
+            truncate(cell, *w);
+        }
+    }
+}
+
+/// Truncate the values of a single item drawn with one row per field until they
+/// fit in `width`.
+fn fit_record(headers: &[String], rows: &mut [Vec<String>], width: usize) {
+    let header_width = headers.iter().map(|h| h.chars().count()).max().unwrap_or_default();
+    let value_width = width.saturating_sub(header_width + 5).max(MIN_COLUMN_WIDTH);
+
+    for row in rows.iter_mut() {
+        for cell in row.iter_mut() {
+            truncate(cell, value_width);
+        }
+    }
+}
+
+fn truncate(s: &mut String, width: usize) {
+    if s.chars().count() > width {
+        *s = s
+            .chars()
+            .take(width.saturating_sub(1))
+            .chain(std::iter::once('…'))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use tabled::Tabled;
+
+    use super::*;
+
+    #[derive(serde::Serialize, Tabled)]
+    struct Thing {
+        id: String,
+        state: String,
+    }
+
+    fn thing(id: &str, state: &str) -> Thing {
+        Thing {
+            id: id.to_string(),
+            state: state.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_watch_events() {
+        let prev = Snapshot::new(&[thing("a", "running"), thing("b", "running")]).unwrap();
+        let next = Snapshot::new(&[thing("c", "starting"), thing("a", "stopping")]).unwrap();
+
+        assert_eq!(
+            next.events(&prev),
+            vec![
+                Event::Added {
+                    object: json!({"id": "c", "state": "starting"})
+                },
+                Event::Modified {
+                    object: json!({"id": "a", "state": "stopping"})
+                },
+                Event::Deleted {
+                    object: json!({"id": "b", "state": "running"})
+                },
+            ]
+        );
+        assert!(next.events(&next).is_empty());
+        assert_eq!(next.deleted(&prev), 1);
+
+        assert_eq!(
+            serde_json::to_string(&next.events(&prev)[0]).unwrap(),
+            r#"{"type":"added","object":{"id":"c","state":"starting"}}"#
+        );
+    }
+
+    #[test]
+    fn test_watch_changes() {
+        let prev = Snapshot::new(&[thing("a", "running"), thing("b", "running")]).unwrap();
+        let next = Snapshot::new(&[thing("b", "running"), thing("a", "stopping"), thing("c", "starting")]).unwrap();
+
+        assert_eq!(
+            next.changes(&prev),
+            vec![Some(vec![false, false]), Some(vec![false, true]), None]
+        );
+    }
+
+    #[test]
+    fn test_watch_fit_columns() {
+        let headers = vec!["id".to_string(), "description".to_string()];
+        let mut rows = vec![vec!["0123456789abcdef".to_string(), "a".repeat(40)]];
+
+        fit_columns(&headers, &mut rows, 40);
+        assert_eq!(rows[0][0], "0123456789abcdef");
+        assert_eq!(rows[0][1], format!("{}…", "a".repeat(18)));
+
+        // Columns are never shrunk below their header, or the minimum width.
+        let mut rows = vec![vec!["0123456789abcdef".to_string(), "a".repeat(40)]];
+        fit_columns(&headers, &mut rows, 10);
+        assert_eq!(rows[0][0], "0123456…");
+        assert_eq!(rows[0][1], format!("{}…", "a".repeat(10)));
+    }
+}